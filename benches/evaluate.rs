@@ -0,0 +1,76 @@
+// Evaluating equations across a row of points, walking the node trees
+// against running them compiled, see `parse::bytecode`.
+//
+// Run with `cargo +nightly bench`.
+
+#![feature(test)]
+#![allow(clippy::needless_return)]
+extern crate test;
+
+use std::collections::HashMap;
+use test::{Bencher, black_box};
+
+use lrinser_laser_etcher::{Node, parse_equations, Format};
+use lrinser_laser_etcher::parse::bytecode::Compiler;
+use lrinser_laser_etcher::parse::values::EvaluatedValues;
+
+
+const POINTS : usize = 1000;
+
+const POLYNOMIAL : &str = "y = 3*x^4 - 2*x^3 + x^2 - 5*x + 7";
+const TRIGONOMETRY : &str = "y = sin(x)^2 + cos(x)^2 * tan(x / 2) - sin(x) * cos(x)";
+const DEFINITIONS : &str = "a = 2 * pi\nb = sqrt(a) + 1\nc = sin(a * x) + b\ny = c^2 - c + cosh(x / 4)";
+const MULTIVALUE : &str = "y = [1, -1] * sqrt(25 - x^2)";
+
+
+fn equations(source : &str) -> Vec<Node> {
+    return parse_equations(source, Format::Text).unwrap().nodes;
+}
+
+fn x_at(point : usize) -> f64 {
+    return -5.0 + 10.0 * (point as f64) / (POINTS as f64);
+}
+
+fn tree(bencher : &mut Bencher, source : &str) {
+    let nodes = equations(source);
+    bencher.iter(|| {
+        for point in 0..POINTS {
+            let mut variables = HashMap::new();
+            variables.insert(String::from("pi"), EvaluatedValues::from(vec![std::f64::consts::PI]));
+            variables.insert(String::from("x"), EvaluatedValues::from(vec![x_at(point)]));
+            for node in &nodes {
+                let _ = black_box(node.evaluate(&String::from("y"), &mut variables));
+            }
+            black_box(variables.remove("y"));
+        }
+    });
+}
+
+fn compiled(bencher : &mut Bencher, source : &str) {
+    let nodes = equations(source);
+    let mut compiler = Compiler::new();
+    compiler.set_constant("pi", &[std::f64::consts::PI]);
+    compiler.set_input("x");
+    let mut output = 0;
+    for node in &nodes {
+        output = compiler.compile(node).unwrap();
+    }
+    let program     = compiler.finish(vec![output]);
+    let mut machine = program.machine();
+    bencher.iter(|| {
+        for point in 0..POINTS {
+            program.run(&mut machine, &[x_at(point)]);
+            black_box(program.output(&machine, 0));
+        }
+    });
+}
+
+
+#[bench] fn tree_polynomial       (bencher : &mut Bencher) {tree(bencher, POLYNOMIAL);}
+#[bench] fn compiled_polynomial   (bencher : &mut Bencher) {compiled(bencher, POLYNOMIAL);}
+#[bench] fn tree_trigonometry     (bencher : &mut Bencher) {tree(bencher, TRIGONOMETRY);}
+#[bench] fn compiled_trigonometry (bencher : &mut Bencher) {compiled(bencher, TRIGONOMETRY);}
+#[bench] fn tree_definitions      (bencher : &mut Bencher) {tree(bencher, DEFINITIONS);}
+#[bench] fn compiled_definitions  (bencher : &mut Bencher) {compiled(bencher, DEFINITIONS);}
+#[bench] fn tree_multivalue       (bencher : &mut Bencher) {tree(bencher, MULTIVALUE);}
+#[bench] fn compiled_multivalue   (bencher : &mut Bencher) {compiled(bencher, MULTIVALUE);}
@@ -0,0 +1,302 @@
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand, Args, ValueEnum};
+use loggerithm::level;
+
+use lrinser_laser_etcher::{Format, Evaluation, LaserMode, MAX_SPLIT_DEPTH};
+use lrinser_laser_etcher::parse::var;
+
+
+#[derive(Parser, Debug)]
+#[command(name = "lrinser_laser_etcher", version, about = "Plot equations for laser etching.")]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command   : Command,
+    /// Minimum level of log messages to print.
+    #[arg(long, global = true, value_enum, default_value_t = LogLevel::Debug)]
+    pub log_level : LogLevel
+}
+
+
+// Parsed once, so the size of `RenderArgs` does not matter.
+#[allow(clippy::large_enum_variant)]
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Render an equation file to an image.
+    Render(RenderArgs),
+    /// Parse an equation file and report any errors without rendering.
+    Check(CheckArgs)
+}
+
+
+#[derive(Args, Debug)]
+pub struct RenderArgs {
+    /// Equation file, one equation per line.
+    pub input               : PathBuf,
+    /// Format of the equation file. Picked from the extension if not given.
+    #[arg(short, long, value_enum)]
+    pub format              : Option<InputFormat>,
+    /// File to write. `.svg` writes SVG, `.gcode`, `.gc`, `.nc` and `.ngc` write G-code, anything else an image in the format picked from the extension.
+    #[arg(short, long, default_value = "target.png")]
+    pub output              : PathBuf,
+    /// Corners of the graph. Desmos graphs use their saved viewport if not given, anything else `-5,-5,5,5`.
+    #[arg(long, value_name = "LEFT,BOTTOM,RIGHT,TOP", value_parser = parse_frame, allow_hyphen_values = true)]
+    pub frame               : Option<[f64; 4]>,
+    /// Number of times the render tree is split.
+    #[arg(short = 'd', long, value_parser = clap::value_parser!(u32).range(0..=MAX_SPLIT_DEPTH as i64), default_value_t = 8)]
+    pub split_depth         : u32,
+    /// Image size. `0` on either axis uses 2 ^ split depth.
+    #[arg(short, long, value_name = "WIDTHxHEIGHT", value_parser = parse_resolution, default_value = "0x0")]
+    pub resolution          : [u32; 2],
+    /// How cells are tested for curves.
+    #[arg(short, long, value_enum, default_value_t = EvaluationMode::Points)]
+    pub evaluation          : EvaluationMode,
+    /// Largest imaginary part drawn with complex evaluation, relative to the real part once that is over one.
+    #[arg(long, value_name = "TOLERANCE", value_parser = parse_positive, default_value = "1e-9")]
+    pub imaginary_tolerance : f64,
+    /// Values of `t` parametric curves such as `(cos(t), sin(2t))` are traced over. Bounds may be multiples of `pi`.
+    #[arg(long, value_name = "START,END", value_parser = parse_domain, allow_hyphen_values = true, default_value = "0,2pi")]
+    pub t_domain            : [f64; 2],
+    /// Values of `θ` polar curves such as `r = 1 + cos(θ)` are traced over. Bounds may be multiples of `pi`.
+    #[arg(long, value_name = "START,END", value_parser = parse_domain, allow_hyphen_values = true, default_value = "0,2pi")]
+    pub theta_domain        : [f64; 2],
+    /// Number of render threads. `0` uses one per core.
+    #[arg(short = 'j', long, default_value_t = 0)]
+    pub threads             : usize,
+    /// Colours of the equations in order, as `#rgb`, `#rrggbb` or `#rrggbbaa`. Replace the colours saved in Desmos graphs. Others use the Desmos defaults.
+    #[arg(short, long = "colour", alias = "color", value_name = "COLOURS", value_parser = parse_colour, value_delimiter = ',')]
+    pub colours             : Vec<[u8; 4]>,
+    /// Image background colour, as `#rgb`, `#rrggbb`, `#rrggbbaa` or `transparent`.
+    #[arg(long, value_name = "COLOUR", value_parser = parse_colour, default_value = "#ffffff")]
+    pub background          : [u8; 4],
+    /// SVG and G-code job size in millimetres.
+    #[arg(long, value_name = "WIDTHxHEIGHT", value_parser = parse_size, default_value = "100x100")]
+    pub size                : [f64; 2],
+    /// SVG line width in millimetres.
+    #[arg(long, value_parser = parse_positive, default_value = "0.2")]
+    pub stroke_width        : f64,
+    /// Draw the axes in SVG output.
+    #[arg(long)]
+    pub axes                : bool,
+    /// Draw grid lines this far apart, in graph units, in SVG output.
+    #[arg(long, value_parser = parse_positive)]
+    pub grid                : Option<f64>,
+    /// Distance between the fill lines of regions in SVG and G-code output, in millimetres.
+    #[arg(long, value_name = "MILLIMETRES", value_parser = parse_positive, default_value = "0.5")]
+    pub hatch_spacing       : f64,
+    /// Direction of the fill lines in degrees, anticlockwise from the `x` axis.
+    #[arg(long, value_name = "DEGREES", allow_hyphen_values = true, default_value_t = 0.0)]
+    pub hatch_angle         : f64,
+    /// Add fill lines at right angles to the others.
+    #[arg(long)]
+    pub cross_hatch         : bool,
+    /// Run every other fill line back the other way, so the laser zigzags.
+    #[arg(long)]
+    pub bidirectional       : bool,
+    /// Also fill the areas enclosed by closed curves, not only regions.
+    #[arg(long)]
+    pub fill_closed         : bool,
+    /// G-code burning speed in millimetres per minute.
+    #[arg(long, value_parser = parse_positive, default_value = "1000")]
+    pub feed                : f64,
+    /// G-code laser power, the `S` value of `M3`/`M4`.
+    #[arg(long, default_value_t = 1000)]
+    pub power               : u32,
+    /// G-code laser mode.
+    #[arg(long, value_enum, default_value_t = LaserModeArg::Dynamic)]
+    pub laser_mode          : LaserModeArg
+}
+
+
+#[derive(Args, Debug)]
+pub struct CheckArgs {
+    /// Equation file, one equation per line.
+    pub input      : PathBuf,
+    /// Format of the equation file. Picked from the extension if not given.
+    #[arg(short, long, value_enum)]
+    pub format     : Option<InputFormat>,
+    /// How the equations would be rendered. Complex evaluation defines `i`.
+    #[arg(short, long, value_enum, default_value_t = EvaluationMode::Points)]
+    pub evaluation : EvaluationMode
+}
+
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum InputFormat {
+    /// Plain text, see `parse::import::text`.
+    Text,
+    /// Desmos flavoured LaTeX, see `parse::import::latex`.
+    Latex,
+    /// Saved Desmos graph state JSON, see `parse::import::desmos`.
+    Desmos
+}
+impl From<InputFormat> for Format {
+    fn from(format : InputFormat) -> Format {
+        return match (format) {
+            InputFormat::Text   => Format::Text,
+            InputFormat::Latex  => Format::Latex,
+            InputFormat::Desmos => Format::Desmos
+        };
+    }
+}
+
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum EvaluationMode {
+    /// Sample the equations at points. Fast, but thin features can be missed.
+    Points,
+    /// Interval arithmetic over whole cells. Slower, but never misses a curve.
+    Intervals,
+    /// Sample at points over the complex numbers, with `i` defined, drawing only real values.
+    Complex
+}
+impl From<EvaluationMode> for Evaluation {
+    fn from(mode : EvaluationMode) -> Evaluation {
+        return match (mode) {
+            EvaluationMode::Points    => Evaluation::Points,
+            EvaluationMode::Intervals => Evaluation::Intervals,
+            EvaluationMode::Complex   => Evaluation::Complex
+        };
+    }
+}
+
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum LaserModeArg {
+    /// `M3`, the same power at any speed.
+    Constant,
+    /// `M4`, power scaled with speed, so corners are not overburnt.
+    Dynamic
+}
+impl From<LaserModeArg> for LaserMode {
+    fn from(mode : LaserModeArg) -> LaserMode {
+        return match (mode) {
+            LaserModeArg::Constant => LaserMode::Constant,
+            LaserModeArg::Dynamic  => LaserMode::Dynamic
+        };
+    }
+}
+
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum LogLevel {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error
+}
+impl LogLevel {
+    pub fn severity(&self) -> u32 {
+        return match (self) {
+            LogLevel::Trace => level::TRACE::SEVERITY(),
+            LogLevel::Debug => level::DEBUG::SEVERITY(),
+            LogLevel::Info  => level::INFO::SEVERITY(),
+            LogLevel::Warn  => level::WARN::SEVERITY(),
+            LogLevel::Error => level::ERROR::SEVERITY()
+        };
+    }
+}
+
+
+fn parse_frame(text : &str) -> Result<[f64; 4], String> {
+    let parts = text.split(',').map(|part| part.trim().parse::<f64>()).collect::<Vec<_>>();
+    if (parts.len() != 4) {
+        return Err(format!("expected 4 comma separated numbers, found {}", parts.len()));
+    }
+    let mut frame = [0.0; 4];
+    for i in 0..4 {
+        frame[i] = match (&parts[i]) {
+            Ok(value) if (value.is_finite()) => *value,
+            _ => return Err(format!("`{}` is not a number", text.split(',').nth(i).unwrap().trim()))
+        };
+    }
+    if (frame[0] >= frame[2]) {
+        return Err(format!("left ({}) must be less than right ({})", frame[0], frame[2]));
+    }
+    if (frame[1] >= frame[3]) {
+        return Err(format!("bottom ({}) must be less than top ({})", frame[1], frame[3]));
+    }
+    return Ok(frame);
+}
+
+// Two increasing bounds, each a number, optionally times `pi`, as `-pi,2pi` or `0,0.5pi`.
+fn parse_domain(text : &str) -> Result<[f64; 2], String> {
+    let parts = text.split(',').collect::<Vec<&str>>();
+    if (parts.len() != 2) {
+        return Err(format!("expected 2 comma separated bounds, found {}", parts.len()));
+    }
+    let mut domain = [0.0; 2];
+    for i in 0..2 {
+        let part = parts[i].trim();
+        let (number, multiple) = match (part.strip_suffix("pi")) {
+            Some(number) => (number, var::PI),
+            None         => (part, 1.0)
+        };
+        domain[i] = match (number) {
+            ""  => multiple,
+            "-" => -multiple,
+            _   => match (number.parse::<f64>()) {
+                Ok(value) if (value.is_finite()) => value * multiple,
+                _ => return Err(format!("`{}` is not a number", part))
+            }
+        };
+    }
+    if (domain[0] >= domain[1]) {
+        return Err(format!("start ({}) must be less than end ({})", parts[0].trim(), parts[1].trim()));
+    }
+    return Ok(domain);
+}
+
+fn parse_resolution(text : &str) -> Result<[u32; 2], String> {
+    let parts = text.split(['x', 'X']).collect::<Vec<&str>>();
+    if (parts.len() != 2) {
+        return Err(String::from("expected WIDTHxHEIGHT, for example `512x512`"));
+    }
+    let mut resolution = [0; 2];
+    for i in 0..2 {
+        resolution[i] = match (parts[i].trim().parse::<u32>()) {
+            Ok(value) => value,
+            Err(_)    => return Err(format!("`{}` is not a whole number", parts[i].trim()))
+        };
+    }
+    return Ok(resolution);
+}
+
+fn parse_size(text : &str) -> Result<[f64; 2], String> {
+    let parts = text.split(['x', 'X']).collect::<Vec<&str>>();
+    if (parts.len() != 2) {
+        return Err(String::from("expected WIDTHxHEIGHT, for example `100x100`"));
+    }
+    let mut size = [0.0; 2];
+    for i in 0..2 {
+        size[i] = parse_positive(parts[i].trim())?;
+    }
+    return Ok(size);
+}
+
+fn parse_positive(text : &str) -> Result<f64, String> {
+    return match (text.parse::<f64>()) {
+        Ok(value) if (value.is_finite() && value > 0.0) => Ok(value),
+        _                                                => Err(format!("`{}` is not a positive number", text))
+    };
+}
+
+// `#rgb`, `#rrggbb`, `#rrggbbaa` or `transparent`.
+fn parse_colour(text : &str) -> Result<[u8; 4], String> {
+    let text = text.trim();
+    if (text.eq_ignore_ascii_case("transparent")) {
+        return Ok([0, 0, 0, 0]);
+    }
+    let digits = text.strip_prefix('#').unwrap_or(text);
+    if (! digits.chars().all(|ch| ch.is_ascii_hexdigit())) {
+        return Err(format!("`{}` is not a hex colour", text));
+    }
+    let channel = |i : usize, width : usize| u8::from_str_radix(&digits[i * width..(i + 1) * width], 16).unwrap();
+    return match (digits.len()) {
+        3 => Ok([channel(0, 1) * 17, channel(1, 1) * 17, channel(2, 1) * 17, 255]),
+        6 => Ok([channel(0, 2), channel(1, 2), channel(2, 2), 255]),
+        8 => Ok([channel(0, 2), channel(1, 2), channel(2, 2), channel(3, 2)]),
+        _ => Err(format!("`{}` is not a hex colour, expected `#rgb`, `#rrggbb` or `#rrggbbaa`", text))
+    };
+}
@@ -0,0 +1,121 @@
+use std::{fmt, io};
+
+use crate::parse::import::text::ParseError;
+use crate::parse::import::desmos::DesmosError;
+use crate::parse::list;
+use crate::render::{settings, svg, hatch};
+
+
+#[derive(Debug)]
+pub enum Error {
+    Read               {path : String, error : io::Error},
+    Parse              (ParseError),
+    Desmos             (DesmosError),
+    UndefinedVariable  (String),
+    VariableRedefined  (String),
+    // Names along the loop, starting and ending with the same variable.
+    CyclicDefinition   (Vec<String>),
+    // Read by an equation, but its definition was skipped.
+    SkippedDefinition  (String),
+    // Number of equations that can not be drawn, each reported as it was skipped.
+    SkippedEquations   (usize),
+    UndefinedFunction  (String),
+    // Names along the loop, starting and ending with the same function.
+    RecursiveFunction  (Vec<String>),
+    ArgumentCount      {name : String, expected : usize, found : usize},
+    FunctionRedefined  (String),
+    DuplicateParameter {name : String, parameter : String},
+    // A point used where a number is needed.
+    PointValue         (String),
+    // A list whose length or order would change from point to point.
+    VaryingList        (String),
+    // A range whose bounds are not single numbers, or whose step is zero.
+    RangeBounds        (String),
+    // A list with more than `list::MAX_LENGTH` elements.
+    ListLength         (String),
+    ShuffleSeed        (String),
+    // A quantile or quartile picked by something other than numbers known before drawing.
+    QuantileFraction   (String),
+    // A statistic of two lists of different lengths.
+    PairedLengths      (String),
+    // A split depth over `settings::MAX_SPLIT_DEPTH`.
+    SplitDepth         (u32),
+    // Corners of a graph that are not numbers, or do not have left below right and bottom below top.
+    Frame              ([f64; 4]),
+    // A grid spacing that is not a positive number, or draws more than `svg::MAX_GRID_LINES` lines.
+    GridSpacing        (f64),
+    // A hatch spacing that is not a positive number, or draws more than `hatch::MAX_ROWS` rows.
+    HatchSpacing       (f64),
+    Write              {path : String, error : io::Error},
+    ImageWrite         {path : String, error : image::ImageError},
+    ThreadPool         (rayon::ThreadPoolBuildError),
+    // A broken invariant, never caused by user input.
+    Internal           (String)
+}
+impl fmt::Display for Error {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match (self) {
+            Error::Read               {path, error}            => write!(f, "Failed to read `{}`: {}.", path, error),
+            Error::Parse              (error)                  => write!(f, "{}", error),
+            Error::Desmos             (error)                  => write!(f, "{}", error),
+            Error::UndefinedVariable  (name)                   => write!(f, "Variable `{}` not defined.", name),
+            Error::VariableRedefined  (name)                   => write!(f, "Variable `{}` is defined more than once.", name),
+            Error::CyclicDefinition   (names)                  => write!(f,
+                "Variable `{}` depends on itself: {}.",
+                names[0], names.iter().map(|name| format!("`{}`", name)).collect::<Vec<String>>().join(" -> ")
+            ),
+            Error::SkippedDefinition  (name)                   => write!(f, "Definition of `{}` was skipped.", name),
+            Error::SkippedEquations   (count)                  => write!(f, "{} equation{} can not be drawn.", count, if (*count == 1) {""} else {"s"}),
+            Error::UndefinedFunction  (name)                   => write!(f, "Function `{}` not defined.", name),
+            Error::RecursiveFunction  (names)                  => write!(f,
+                "Function `{}` calls itself: {}.",
+                names[0], names.iter().map(|name| format!("`{}`", name)).collect::<Vec<String>>().join(" -> ")
+            ),
+            Error::ArgumentCount      {name, expected, found}  => write!(f,
+                "Function `{}` takes {} argument{}, found {}.",
+                name, expected, if (*expected == 1) {""} else {"s"}, found
+            ),
+            Error::FunctionRedefined  (name)                   => write!(f, "Function `{}` is defined more than once.", name),
+            Error::DuplicateParameter {name, parameter}        => write!(f, "Function `{}` has more than one parameter named `{}`.", name, parameter),
+            Error::PointValue         (point)                  => write!(f, "Point `{}` can not be used as a number.", point),
+            Error::VaryingList        (list)                   => write!(f, "List `{}` can not depend on the coordinates, its length and order must be the same at every point.", list),
+            Error::RangeBounds        (range)                  => write!(f, "Range `{}` needs single numbers as bounds, and a step that is not zero.", range),
+            Error::ListLength         (list)                   => write!(f, "List `{}` has more than {} elements.", list, list::MAX_LENGTH),
+            Error::ShuffleSeed        (list)                   => write!(f, "Shuffle `{}` needs a single number as its seed.", list),
+            Error::QuantileFraction   (quantile)               => write!(f, "Quantile `{}` needs real numbers that do not depend on the coordinates as its fraction.", quantile),
+            Error::PairedLengths      (statistic)              => write!(f, "Statistic `{}` needs two lists of the same length.", statistic),
+            Error::SplitDepth         (split_depth)            => write!(f,
+                "Split depth `{}` must be at most {}.",
+                split_depth, settings::MAX_SPLIT_DEPTH
+            ),
+            Error::Frame              (frame)                  => write!(f,
+                "Frame `{},{},{},{}` must have left less than right and bottom less than top.",
+                frame[0], frame[1], frame[2], frame[3]
+            ),
+            Error::GridSpacing        (spacing)                => write!(f,
+                "Grid spacing `{}` must be a positive number drawing at most {} lines across the frame.",
+                spacing, svg::MAX_GRID_LINES
+            ),
+            Error::HatchSpacing       (spacing)                => write!(f,
+                "Hatch spacing `{}` must be a positive number drawing at most {} rows across the job.",
+                spacing, hatch::MAX_ROWS
+            ),
+            Error::Write              {path, error}            => write!(f, "Failed to write `{}`: {}.", path, error),
+            Error::ImageWrite         {path, error}            => write!(f, "Failed to write image `{}`: {}.", path, error),
+            Error::ThreadPool         (error)                  => write!(f, "Failed to start render threads: {}.", error),
+            Error::Internal           (message)                => write!(f, "Internal error: {}", message)
+        };
+    }
+}
+impl std::error::Error for Error {}
+
+impl From<ParseError> for Error {
+    fn from(error : ParseError) -> Error {
+        return Error::Parse(error);
+    }
+}
+impl From<DesmosError> for Error {
+    fn from(error : DesmosError) -> Error {
+        return Error::Desmos(error);
+    }
+}
@@ -0,0 +1,23 @@
+//! Plots equations for laser etching.
+//!
+//! Equations are read with `parse_equations` or `load_equations`, then
+//! drawn with `render_image` into an `RgbaImage`, or with `render_file`
+//! straight to the file named by `RenderSettings::target`.
+//!
+//! Logs go through `loggerithm`. No logger is registered here, so the
+//! host's logger, or the `loggerithm` default, receives them.
+#![allow(unused_parens)]
+#![allow(clippy::needless_return, clippy::redundant_field_names)]
+#![feature(decl_macro)]
+
+
+pub mod error;
+pub mod helper;
+pub mod parse;
+pub mod render;
+
+pub use error::Error;
+pub use parse::import::{Format, LoadedGraph, parse as parse_equations, load as load_equations};
+pub use parse::node::{Node, NodeBase};
+pub use render::{render_image, render_paths, render_svg, render_gcode, render_file, check as check_equations, Output};
+pub use render::settings::{RenderSettings, Evaluation, GcodeSettings, LaserMode, SvgSettings, HatchSettings, DEFAULT_COLOURS, MAX_SPLIT_DEPTH};
@@ -1,7 +1,11 @@
 #![allow(unused_parens)]
-#![feature(decl_macro)]
+#![allow(clippy::needless_return, clippy::redundant_field_names)]
 
 
+use std::process;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use clap::Parser;
 use static_init::dynamic;
 use colored::Colorize;
 use chrono::{DateTime, Utc};
@@ -9,10 +13,16 @@ use chrono::{DateTime, Utc};
 static START : DateTime<Utc> = Utc::now();
 use loggerithm::{logger, log};
 use loggerithm::logger::Logger;
-use loggerithm::level::{DEBUG, INFO, SUCCESS};
+use loggerithm::level::{TRACE, INFO, SUCCESS, FATAL};
+// Set from the command line before anything is logged.
+// The library shares this crate's name, so its logs come here too.
+static MIN_SEVERITY : AtomicU32 = AtomicU32::new(0);
 logger!(Logger::new()
-    .set_min_severity(DEBUG::SEVERITY)
+    .set_min_severity(TRACE::SEVERITY)
     .add_target(|context| {
+        if (context.level().get_severity() < MIN_SEVERITY.load(Ordering::Relaxed)) {
+            return;
+        }
         let duration = (Utc::now() - *START).to_std().unwrap();
         let hours    = duration.as_secs() / 3600;
         let minutes  = duration.as_secs() % 3600 / 60;
@@ -38,70 +48,69 @@ logger!(Logger::new()
     })
 );
 
-pub mod helper;
-pub mod parse;
-pub mod render;
-use parse::node::{Node, NodeBase};
-use render::{render, settings::RenderSettings};
+mod cli;
+use cli::{Cli, Command};
+use lrinser_laser_etcher::{load_equations, render_file, check_equations, RenderSettings, GcodeSettings, SvgSettings, HatchSettings};
 
 
 fn main() {
+    let cli = Cli::parse();
+    MIN_SEVERITY.store(cli.log_level.severity(), Ordering::Relaxed);
     log!(INFO, "Initialised.");
-    log!(INFO, "Reading equations from {}.", "<null>");
-
-    /*let eq0 = *Node::new(NodeBase::Equals(
-        Node::new(NodeBase::Addition(
-            Node::new(NodeBase::Power(
-                Node::new(NodeBase::Variable(String::from("x"))),
-                Node::new(NodeBase::Number(2.0))
-            )),
-            Node::new(NodeBase::Power(
-                Node::new(NodeBase::Variable(String::from("y"))),
-                Node::new(NodeBase::Number(2.0))
-            ))
-        )),
-        Node::new(NodeBase::Power(
-            Node::new(NodeBase::Number(2.5)),
-            Node::new(NodeBase::Number(2.0))
-        ))
-    ));*/
-    /*let eq1 = *Node::new(NodeBase::Equals(
-        Node::new(NodeBase::Variable(String::from("y"))),
-        Node::new(NodeBase::SquareRoot(
-            Node::new(NodeBase::Subtraction(
-                Node::new(NodeBase::Power(
-                    Node::new(NodeBase::Number(2.5)),
-                    Node::new(NodeBase::Number(2.0))
-                )),
-                Node::new(NodeBase::Power(
-                    Node::new(NodeBase::Variable(String::from("x"))),
-                    Node::new(NodeBase::Number(2.0))
-                ))
-            ))
-        ))
-    ));*/
-    let eq0 = *Node::new(NodeBase::Equals(
-        Node::new(NodeBase::Variable(String::from("y"))),
-        Node::new(NodeBase::Addition(
-            Node::new(NodeBase::Sine(
-                Node::new(NodeBase::Variable(String::from("x")))
-            )),
-            Node::new(NodeBase::Multiplication(
-                Node::new(NodeBase::Variable(String::from("x"))),
-                Node::new(NodeBase::Number(0.0))
-            ))
-        ))
-    ));
-    let equations = vec![eq0];
-    log!(DEBUG, "Loaded {} equation{}.", equations.len(), if (equations.len() == 1) {""} else {"s"});
 
-    let settings = RenderSettings {
-        frame: [-5.0, -5.0, 5.0, 5.0],
-        split_depth: 8,
-        resolution: [0, 0],
-        target: String::from("target.png"),
+    let result = match (cli.command) {
+        Command::Render(args) => {
+            log!(INFO, "Reading equations from `{}`.", args.input.display());
+            load_equations(&args.input, args.format.map(|format| format.into())).and_then(|graph| {
+                let mut settings = RenderSettings::new()
+                    .set_split_depth(args.split_depth)
+                    .set_resolution(args.resolution)
+                    .set_target(args.output.to_string_lossy())
+                    .set_evaluation(args.evaluation.into())
+                    .set_imaginary_tolerance(args.imaginary_tolerance)
+                    .set_parametric_domain(args.t_domain)
+                    .set_polar_domain(args.theta_domain)
+                    .set_threads(args.threads)
+                    .set_colours(if (args.colours.is_empty()) {graph.colours} else {args.colours})
+                    .set_background(args.background)
+                    .set_size(args.size)
+                    .set_svg(SvgSettings::new()
+                        .set_stroke_width(args.stroke_width)
+                        .set_axes(args.axes)
+                        .set_grid(args.grid)
+                    )
+                    .set_hatch(HatchSettings::new()
+                        .set_spacing(args.hatch_spacing)
+                        .set_angle(args.hatch_angle)
+                        .set_cross(args.cross_hatch)
+                        .set_bidirectional(args.bidirectional)
+                        .set_closed(args.fill_closed)
+                    )
+                    .set_gcode(GcodeSettings::new()
+                        .set_feed(args.feed)
+                        .set_power(args.power)
+                        .set_laser_mode(args.laser_mode.into())
+                    );
+                if let Some(frame) = args.frame.or(graph.frame) {
+                    settings = settings.set_frame(frame);
+                }
+                render_file(&graph.nodes, &settings)
+            })
+        },
+        Command::Check(args) => {
+            log!(INFO, "Reading equations from `{}`.", args.input.display());
+            load_equations(&args.input, args.format.map(|format| format.into())).and_then(|graph| {
+                for equation in &graph.nodes {
+                    log!(INFO, "{}", equation.to_string());
+                }
+                check_equations(&graph.nodes, &RenderSettings::new().set_evaluation(args.evaluation.into()))
+            })
+        }
     };
-    render(equations, settings);
+    if let Err(error) = result {
+        log!(FATAL, "{}", error);
+        process::exit(1);
+    }
 
     log!(SUCCESS, "Finished.");
 }
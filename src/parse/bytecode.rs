@@ -0,0 +1,924 @@
+// Node trees compiled to a flat list of instructions, for evaluating the
+// same equations at many points.
+//
+// Each instruction writes a new register, a run of values in one shared
+// buffer, so a run allocates nothing once the buffer has grown. While
+// compiling, operations on constants are carried out straight away, and an
+// operation already emitted on the same registers is reused. Registers no
+// output reads are dropped when the program is finished.
+//
+// Values match `NodeBase::evaluate`, in the same order, repeats included.
+// Operations keep repeats, such as the two `1`s of `[1, -1]^2`, and
+// reading a variable drops them.
+
+use std::collections::HashMap;
+
+use loggerithm::{logger, log};
+use loggerithm::level::TRACE;
+logger!(super);
+
+use crate::error::Error;
+use crate::parse::node::{Node, NodeBase, Relation};
+use crate::parse::var;
+use crate::parse::list;
+use crate::parse::statistics::{self, Arithmetic};
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Unary {
+    AbsoluteValue,
+    SquareRoot,
+    Sine,
+    Cosine,
+    Tangent,
+    InverseSine,
+    InverseCosine,
+    InverseTangent,
+    HyperbolicSine,
+    HyperbolicCosine,
+    HyperbolicTangent,
+    Ceiling,
+    Floor,
+    Round,
+    Sign
+}
+impl Unary {
+    fn apply(&self, a : f64) -> Option<f64> {
+        return match (self) {
+            Unary::AbsoluteValue     => Some(a.abs()),
+            Unary::SquareRoot        => if (a >= 0.0) {Some(a.sqrt())} else {None},
+            Unary::Sine              => Some(a.sin()),
+            Unary::Cosine            => Some(a.cos()),
+            Unary::Tangent           => Some(a.tan()),
+            Unary::InverseSine       => Some(a.asin()),
+            Unary::InverseCosine     => Some(a.acos()),
+            Unary::InverseTangent    => Some(a.atan()),
+            Unary::HyperbolicSine    => Some(a.sinh()),
+            Unary::HyperbolicCosine  => Some(a.cosh()),
+            Unary::HyperbolicTangent => Some(a.tanh()),
+            Unary::Ceiling           => Some(a.ceil()),
+            Unary::Floor             => Some(a.floor()),
+            Unary::Round             => Some(a.round()),
+            Unary::Sign              => Some(if (a == 0.0) {0.0} else {a / a.abs()})
+        };
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Binary {
+    Addition,
+    Subtraction,
+    Multiplication,
+    Division,
+    Power,
+    NthRoot,   // Powered (a), Degree (n) : ⁿ√a
+    Logarithm, // Result (a), Base (b)    : logᵦ(a)
+    Modulo,
+    Minimum,
+    Maximum
+}
+impl Binary {
+    fn apply(&self, a : f64, b : f64) -> Option<f64> {
+        return match (self) {
+            Binary::Addition       => Some(a + b),
+            Binary::Subtraction    => Some(a - b),
+            Binary::Multiplication => Some(a * b),
+            Binary::Division       => if (b != 0.0) {Some(a / b)} else {None},
+            Binary::Power          => Some(a.powf(b)),
+            Binary::NthRoot        => {
+                if (b == 0.0) {
+                    None
+                } else if (a >= 0.0) {
+                    Some(a.powf(1.0 / b))
+                } else if (b.fract() == 0.0 && b % 2.0 != 0.0) {
+                    // Odd roots of negative numbers are real.
+                    Some(-(-a).powf(1.0 / b))
+                } else {
+                    None
+                }
+            },
+            Binary::Logarithm      => Some(a.log(b)),
+            Binary::Modulo         => Some(-b * (a / b).floor() + a),
+            Binary::Minimum        => Some(a.min(b)),
+            Binary::Maximum        => Some(a.max(b))
+        };
+    }
+}
+
+// Operands are registers, always written before the instruction reading them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Instruction {
+    Input   (usize),                   // Index in the inputs
+    Unary   (Unary, usize),            // Operation, Argument
+    Binary  (Binary, usize, usize),    // Operation, Left, Right : every pair of values
+    Union   (usize, usize),            // Left, Right : values of both, without repeats
+    Compare (Relation, usize, usize),  // Relation, Left, Right : a single `1` if any pair of values holds
+    Select  (usize, usize, usize)      // Condition, Then, Otherwise : values of Then if Condition has any
+}
+impl Instruction {
+    fn operands(&self) -> Vec<usize> {
+        return match (self) {
+            Instruction::Input   (_)       => vec![],
+            Instruction::Unary   (_, a)    => vec![*a],
+            Instruction::Binary  (_, a, b) |
+            Instruction::Union   (a, b)    |
+            Instruction::Compare (_, a, b) => vec![*a, *b],
+            Instruction::Select  (c, a, b) => vec![*c, *a, *b]
+        };
+    }
+    fn renumber(&self, registers : &[usize]) -> Instruction {
+        return match (self) {
+            Instruction::Input   (i)         => Instruction::Input(*i),
+            Instruction::Unary   (op, a)     => Instruction::Unary(*op, registers[*a]),
+            Instruction::Binary  (op, a, b)  => Instruction::Binary(*op, registers[*a], registers[*b]),
+            Instruction::Union   (a, b)      => Instruction::Union(registers[*a], registers[*b]),
+            Instruction::Compare (rel, a, b) => Instruction::Compare(*rel, registers[*a], registers[*b]),
+            Instruction::Select  (c, a, b)   => Instruction::Select(registers[*c], registers[*a], registers[*b])
+        };
+    }
+}
+
+
+pub struct Compiler {
+    // Values of every constant register, one after another.
+    constants    : Vec<f64>,
+    // Start, End in `constants` of each register's values. Unused for computed registers.
+    spans        : Vec<[usize; 2]>,
+    // Whether each register is computed on every run, instead of constant.
+    computed     : Vec<bool>,
+    // Register written, Instruction
+    instructions : Vec<(usize, Instruction)>,
+    // Register of each instruction already emitted.
+    emitted      : HashMap<Instruction, usize>,
+    // Register of each constant already made, keyed by the bits of its values.
+    folded       : HashMap<Vec<u64>, usize>,
+    variables    : HashMap<String, usize>,
+    inputs       : usize
+}
+impl Default for Compiler {
+    fn default() -> Compiler {
+        return Compiler::new();
+    }
+}
+impl Compiler {
+    pub fn new() -> Compiler {
+        return Compiler {
+            constants    : vec![],
+            spans        : vec![],
+            computed     : vec![],
+            instructions : vec![],
+            emitted      : HashMap::new(),
+            folded       : HashMap::new(),
+            variables    : HashMap::new(),
+            inputs       : 0
+        };
+    }
+
+    // Bind a variable to values known before compiling, such as `pi`.
+    pub fn set_constant(&mut self, name : &str, values : &[f64]) {
+        let register = self.constant(values);
+        self.variables.insert(String::from(name), register);
+    }
+
+    // Bind a variable to the next input of each run, such as `x`.
+    pub fn set_input(&mut self, name : &str) {
+        let register = self.emit(Instruction::Input(self.inputs));
+        self.inputs += 1;
+        self.variables.insert(String::from(name), register);
+    }
+
+    // Register holding the values of a node. A definition binds its
+    // variable for the nodes compiled after it, and gives its value. Other
+    // equations and inequalities give `left - right`.
+    pub fn compile(&mut self, node : &Node) -> Result<usize, Error> {
+        macro compile {
+            ($from:expr) => {self.compile($from)?}
+        }
+
+        let register = match (&node.base) {
+
+            NodeBase::Addition       (left, right) => {let (a, b) = (compile!(left), compile!(right)); self.binary(Binary::Addition,       a, b)},
+            NodeBase::Subtraction    (left, right) => {let (a, b) = (compile!(left), compile!(right)); self.binary(Binary::Subtraction,    a, b)},
+            NodeBase::Multiplication (left, right) => {let (a, b) = (compile!(left), compile!(right)); self.binary(Binary::Multiplication, a, b)},
+            NodeBase::Division       (left, right) => {let (a, b) = (compile!(left), compile!(right)); self.binary(Binary::Division,       a, b)},
+            NodeBase::Power          (left, right) => {let (a, b) = (compile!(left), compile!(right)); self.binary(Binary::Power,          a, b)},
+
+            NodeBase::AbsoluteValue       (arg)  => {let a = compile!(arg); self.unary(Unary::AbsoluteValue,     a)},
+            NodeBase::SquareRoot          (arg)  => {let a = compile!(arg); self.unary(Unary::SquareRoot,        a)},
+            NodeBase::NthRoot             (n, p) => {let (a, b) = (compile!(p), compile!(n)); self.binary(Binary::NthRoot, a, b)},
+            NodeBase::Sine                (arg)  => {let a = compile!(arg); self.unary(Unary::Sine,              a)},
+            NodeBase::Cosine              (arg)  => {let a = compile!(arg); self.unary(Unary::Cosine,            a)},
+            NodeBase::Tangent             (arg)  => {let a = compile!(arg); self.unary(Unary::Tangent,           a)},
+            NodeBase::Cosecant            (arg)  => {let a = compile!(arg); self.reciprocal_of(Unary::Sine,      a)},
+            NodeBase::Secant              (arg)  => {let a = compile!(arg); self.reciprocal_of(Unary::Cosine,    a)},
+            NodeBase::Cotangent           (arg)  => {let a = compile!(arg); self.reciprocal_of(Unary::Tangent,   a)},
+            NodeBase::InverseSine         (arg)  => {let a = compile!(arg); self.unary(Unary::InverseSine,       a)},
+            NodeBase::InverseCosine       (arg)  => {let a = compile!(arg); self.unary(Unary::InverseCosine,     a)},
+            NodeBase::InverseTangent      (arg)  => {let a = compile!(arg); self.unary(Unary::InverseTangent,    a)},
+            NodeBase::InverseCosecant     (arg)  => {let a = compile!(arg); self.of_reciprocal(Unary::InverseSine,    a)},
+            NodeBase::InverseSecant       (arg)  => {let a = compile!(arg); self.of_reciprocal(Unary::InverseCosine,  a)},
+            NodeBase::InverseCotangent    (arg)  => {let a = compile!(arg); self.of_reciprocal(Unary::InverseTangent, a)},
+            NodeBase::HyperbolicSine      (arg)  => {let a = compile!(arg); self.unary(Unary::HyperbolicSine,    a)},
+            NodeBase::HyperbolicCosine    (arg)  => {let a = compile!(arg); self.unary(Unary::HyperbolicCosine,  a)},
+            NodeBase::HyperbolicTangent   (arg)  => {let a = compile!(arg); self.unary(Unary::HyperbolicTangent, a)},
+            NodeBase::HyperbolicCosecant  (arg)  => {let a = compile!(arg); self.hyperbolic(a, None,      Some(-1.0))},
+            NodeBase::HyperbolicSecant    (arg)  => {let a = compile!(arg); self.hyperbolic(a, None,      Some(1.0))},
+            NodeBase::HyperbolicCotangent (arg)  => {let a = compile!(arg); self.hyperbolic(a, Some(1.0), Some(-1.0))},
+            NodeBase::Exponential         (arg)  => {let a = compile!(arg); let e = self.constant(&[var::E]); self.binary(Binary::Power, e, a)},
+            NodeBase::NaturalLogarithm    (arg)  => {let a = compile!(arg); let e = self.constant(&[var::E]); self.binary(Binary::Logarithm, a, e)},
+            NodeBase::Logartithm          (b, r) => {let (a, b) = (compile!(r), compile!(b)); self.binary(Binary::Logarithm, a, b)},
+            NodeBase::Modulo              (a, b) => {let (a, b) = (compile!(a), compile!(b)); self.binary(Binary::Modulo,    a, b)},
+            NodeBase::Ceiling             (arg)  => {let a = compile!(arg); self.unary(Unary::Ceiling,           a)},
+            NodeBase::Floor               (arg)  => {let a = compile!(arg); self.unary(Unary::Floor,             a)},
+            NodeBase::Round               (arg)  => {let a = compile!(arg); self.unary(Unary::Round,             a)},
+            NodeBase::Sign                (arg)  => {let a = compile!(arg); self.unary(Unary::Sign,              a)},
+
+            NodeBase::MultiValue        (values)      => {
+                let mut register = self.constant(&[]);
+                for value in values {
+                    let value = compile!(value);
+                    register = self.emit(Instruction::Union(register, value));
+                }
+                register
+            },
+            NodeBase::Number            (value)       => self.constant(&[*value]),
+            NodeBase::Variable          (name)        => {
+                match (self.variables.get(name)) {
+                    Some(register) => {let register = *register; self.distinct(register)},
+                    None           => return Err(Error::UndefinedVariable(String::from(name)))
+                }
+            },
+            // Calls are replaced on import, see `parse::function`.
+            NodeBase::Call              (name, _)     => return Err(Error::UndefinedFunction(String::from(name))),
+            // Points are drawn by their coordinates, see `render::parametric`.
+            NodeBase::Point             (_, _)        => return Err(Error::PointValue(node.to_string())),
+            // Every part is compiled, and the first branch that holds is picked on each run.
+            NodeBase::Piecewise         (branches, otherwise) => {
+                let mut parts = vec![];
+                for (condition, value) in branches {
+                    parts.push((compile!(condition), compile!(value)));
+                }
+                let mut register = match (otherwise) {
+                    Some(otherwise) => compile!(otherwise),
+                    None            => self.constant(&[])
+                };
+                for (condition, value) in parts.into_iter().rev() {
+                    register = self.select(condition, value, register);
+                }
+                register
+            },
+
+            NodeBase::Range   (_, _, _) |
+            NodeBase::For     (_, _, _) |
+            NodeBase::Join    (_)       |
+            NodeBase::Sort    (_)       |
+            NodeBase::Unique  (_)       |
+            NodeBase::Shuffle (_, _)    |
+            NodeBase::Stats   (_)       => {let list = self.elements(node)?; self.union(list)},
+            // Each element is picked where the index is its position.
+            NodeBase::Index   (list, index) => {
+                let (list, index) = (self.elements(list)?, compile!(index));
+                let none = self.constant(&[]);
+                let mut picks = vec![];
+                for (i, element) in list.into_iter().enumerate() {
+                    let position = self.constant(&[(i + 1) as f64]);
+                    let at       = self.emit(Instruction::Compare(Relation::Equal, index, position));
+                    picks.push(self.select(at, element, none));
+                }
+                self.union(picks)
+            },
+            NodeBase::Length  (list)    => {let list = self.elements(list)?; self.constant(&[list.len() as f64])},
+            NodeBase::Total   (list)    => {
+                let list = self.elements(list)?;
+                let mut total = self.constant(&[0.0]);
+                for element in list {
+                    total = self.binary(Binary::Addition, total, element);
+                }
+                total
+            },
+
+            NodeBase::Statistic (statistic, list) => {let list = self.elements(list)?; statistics::statistic_of(self, *statistic, list)},
+            // The fraction must be constant, so the elements to pick are known now.
+            NodeBase::Quantile  (list, fraction) |
+            NodeBase::Quartile  (list, fraction) => {
+                let (list, fraction) = (self.elements(list)?, compile!(fraction));
+                if (self.computed[fraction]) {
+                    return Err(Error::QuantileFraction(node.to_string()));
+                }
+                let scale     = if (matches!(node.base, NodeBase::Quartile(_, _))) {0.25} else {1.0};
+                let fractions = self.values(fraction).iter().map(|fraction| fraction * scale).collect::<Vec<f64>>();
+                statistics::quantiles(self, list, &fractions)
+            },
+            NodeBase::Paired    (paired, a, b)   => {
+                let (a, b) = (self.elements(a)?, self.elements(b)?);
+                if (a.len() != b.len()) {
+                    return Err(Error::PairedLengths(node.to_string()));
+                }
+                statistics::paired_of(self, *paired, a, b)
+            },
+
+            NodeBase::Compare (relation, left, right) => {let (a, b) = (compile!(left), compile!(right)); self.emit(Instruction::Compare(*relation, a, b))},
+            NodeBase::And     (left, right)           => {let (a, b) = (compile!(left), compile!(right)); let none = self.constant(&[]); self.select(a, b, none)},
+            NodeBase::Or      (left, right)           => {let (a, b) = (compile!(left), compile!(right)); let one = self.constant(&[1.0]); self.select(a, one, b)},
+            NodeBase::Not     (arg)                   => {let a = compile!(arg); let (one, none) = (self.constant(&[1.0]), self.constant(&[])); self.select(a, none, one)},
+
+            NodeBase::Equals (left, right) => {
+                match (&left.base) {
+                    NodeBase::Variable(name) => {
+                        let register = compile!(right);
+                        self.variables.insert(String::from(name), register);
+                        register
+                    },
+                    _ => return self.difference(left, right)
+                }
+            },
+            NodeBase::Less         (left, right) |
+            NodeBase::LessEqual    (left, right) |
+            NodeBase::Greater      (left, right) |
+            NodeBase::GreaterEqual (left, right) => return self.difference(left, right)
+
+        };
+        return Ok(register);
+    }
+
+    // Register holding `left - right`.
+    pub fn difference(&mut self, left : &Node, right : &Node) -> Result<usize, Error> {
+        let left  = self.compile(left)?;
+        let right = self.compile(right)?;
+        return Ok(self.binary(Binary::Subtraction, left, right));
+    }
+
+    // Keep only what the outputs read, and renumber the registers to match.
+    pub fn finish(self, outputs : Vec<usize>) -> Program {
+        let mut live = vec![false; self.spans.len()];
+        for output in &outputs {
+            live[*output] = true;
+        }
+        // Operands always come before the instructions reading them.
+        for (register, instruction) in self.instructions.iter().rev() {
+            if (live[*register]) {
+                for operand in instruction.operands() {
+                    live[operand] = true;
+                }
+            }
+        }
+        let mut registers = vec![0; self.spans.len()];
+        let mut spans     = vec![];
+        for i in 0..self.spans.len() {
+            if (live[i]) {
+                registers[i] = spans.len();
+                spans.push(if (self.computed[i]) {[0, 0]} else {self.spans[i]});
+            }
+        }
+        let instructions = self.instructions.iter()
+            .filter(|(register, _)| live[*register])
+            .map(|(register, instruction)| (registers[*register], instruction.renumber(&registers)))
+            .collect::<Vec<(usize, Instruction)>>();
+        log!(TRACE,
+            "Compiled {} instruction{} over {} register{}.",
+            instructions.len(), if (instructions.len() == 1) {""} else {"s"},
+            spans.len(), if (spans.len() == 1) {""} else {"s"}
+        );
+        return Program {
+            constants : self.constants,
+            spans,
+            instructions,
+            inputs    : self.inputs,
+            outputs   : outputs.iter().map(|output| registers[*output]).collect()
+        };
+    }
+
+
+    // Register of each element of a list, as `list::elements`. Range bounds,
+    // the elements of `unique` and the seed of `shuffle` must be constant.
+    fn elements(&mut self, node : &Node) -> Result<Vec<usize>, Error> {
+        let list = match (&node.base) {
+            NodeBase::MultiValue (values) => {
+                let mut list = vec![];
+                for value in values {
+                    list.append(&mut self.elements(value)?);
+                }
+                list
+            },
+            NodeBase::Range (first, second, last) => {
+                let first  = self.range_bound(node, first)?;
+                let second = match (second) {
+                    Some(second) => Some(self.range_bound(node, second)?),
+                    None         => None
+                };
+                let last   = self.range_bound(node, last)?;
+                list::range(&node.base, first, second, last)?.into_iter().map(|value| self.constant(&[value])).collect()
+            },
+            NodeBase::For (body, name, list) => {
+                let list     = self.elements(list)?;
+                let previous = self.variables.remove(name);
+                let mut body_elements = vec![];
+                let mut failure       = None;
+                for element in list {
+                    self.variables.insert(name.clone(), element);
+                    match (self.elements(body)) {
+                        Ok(mut each) => body_elements.append(&mut each),
+                        Err(error)   => {
+                            failure = Some(error);
+                            break;
+                        }
+                    };
+                }
+                // The variable is only bound inside the body.
+                match (previous) {
+                    Some(previous) => self.variables.insert(name.clone(), previous),
+                    None           => self.variables.remove(name)
+                };
+                if let Some(error) = failure {
+                    return Err(error);
+                }
+                body_elements
+            },
+            NodeBase::Join (lists) => {
+                let mut list = vec![];
+                for each in lists {
+                    list.append(&mut self.elements(each)?);
+                }
+                list
+            },
+            NodeBase::Sort (list) => {
+                let mut list = self.elements(list)?;
+                match (list.iter().map(|element| self.single_constant(*element)).collect::<Option<Vec<f64>>>()) {
+                    Some(mut numbers) => {
+                        numbers.sort_by(|a, b| a.total_cmp(b));
+                        numbers.into_iter().map(|value| self.constant(&[value])).collect()
+                    },
+                    // Computed elements are sorted on each run.
+                    None => {
+                        for (a, b) in list::sorting_network(list.len()) {
+                            let (lower, upper) = (self.binary(Binary::Minimum, list[a], list[b]), self.binary(Binary::Maximum, list[a], list[b]));
+                            list[a] = lower;
+                            list[b] = upper;
+                        }
+                        list
+                    }
+                }
+            },
+            NodeBase::Unique (list) => {
+                let list = self.elements(list)?;
+                if (list.iter().any(|element| self.computed[*element])) {
+                    return Err(Error::VaryingList(node.to_string()));
+                }
+                let mut unique : Vec<usize> = vec![];
+                for element in list {
+                    let values = self.values(element);
+                    let same   = |kept : &[f64]| values.iter().all(|value| kept.contains(value)) && kept.iter().all(|value| values.contains(value));
+                    if (! unique.iter().any(|kept| same(self.values(*kept)))) {
+                        unique.push(element);
+                    }
+                }
+                unique
+            },
+            NodeBase::Shuffle (list, seed) => {
+                let list = self.elements(list)?;
+                let seed = match (seed) {
+                    Some(seed) => {
+                        let seed = self.compile(seed)?;
+                        if (self.computed[seed]) {
+                            return Err(Error::VaryingList(node.to_string()));
+                        }
+                        match (self.single_constant(seed)) {
+                            Some(seed) => seed,
+                            None       => return Err(Error::ShuffleSeed(node.to_string()))
+                        }
+                    },
+                    None => 0.0
+                };
+                list::permutation(list.len(), seed).into_iter().map(|i| list[i]).collect()
+            },
+            NodeBase::Stats (list) => {
+                let list = self.elements(list)?;
+                statistics::summary(self, list)
+            },
+            _ => vec![self.compile(node)?]
+        };
+        if (list.len() > list::MAX_LENGTH) {
+            return Err(Error::ListLength(node.to_string()));
+        }
+        return Ok(list);
+    }
+
+    // Value of a range bound, which must be a single constant number.
+    fn range_bound(&mut self, range : &Node, bound : &Node) -> Result<f64, Error> {
+        let register = self.compile(bound)?;
+        if (self.computed[register]) {
+            return Err(Error::VaryingList(range.to_string()));
+        }
+        return match (self.single_constant(register)) {
+            Some(value) => Ok(value),
+            None        => Err(Error::RangeBounds(range.to_string()))
+        };
+    }
+
+    // Values of every register together, without repeats. Constant values
+    // are joined straight away, so long constant lists stay one register.
+    fn union(&mut self, registers : Vec<usize>) -> usize {
+        let mut values   = vec![];
+        let mut computed = vec![];
+        for register in registers {
+            if (self.computed[register]) {
+                computed.push(register);
+                continue;
+            }
+            for value in self.values(register) {
+                if (! values.contains(value)) {
+                    values.push(*value);
+                }
+            }
+        }
+        let mut register = self.constant(&values);
+        for each in computed {
+            register = self.emit(Instruction::Union(register, each));
+        }
+        return register;
+    }
+
+    // Values of a constant register.
+    fn values(&self, register : usize) -> &[f64] {
+        let [start, end] = self.spans[register];
+        return &self.constants[start..end];
+    }
+
+    // The value of a constant register, if it is a single finite number.
+    fn single_constant(&self, register : usize) -> Option<f64> {
+        if (self.computed[register]) {
+            return None;
+        }
+        return match (self.values(register)) {
+            [value] if (value.is_finite()) => Some(*value),
+            _                              => None
+        };
+    }
+
+    fn unary(&mut self, operation : Unary, a : usize) -> usize {
+        return self.emit(Instruction::Unary(operation, a));
+    }
+    fn binary(&mut self, operation : Binary, a : usize, b : usize) -> usize {
+        return self.emit(Instruction::Binary(operation, a, b));
+    }
+    // Values of `then` where the condition has any, otherwise of `otherwise`,
+    // picked now if the condition is constant.
+    fn select(&mut self, condition : usize, then : usize, otherwise : usize) -> usize {
+        if (! self.computed[condition]) {
+            let [start, end] = self.spans[condition];
+            return if (end > start) {then} else {otherwise};
+        }
+        return self.emit(Instruction::Select(condition, then, otherwise));
+    }
+    // `1 / f(a)`, as `csc`, `sec` and `cot`.
+    fn reciprocal_of(&mut self, operation : Unary, a : usize) -> usize {
+        let one   = self.constant(&[1.0]);
+        let value = self.unary(operation, a);
+        return self.binary(Binary::Division, one, value);
+    }
+    // `f(1 / a)`, as `acsc`, `asec` and `acot`.
+    fn of_reciprocal(&mut self, operation : Unary, a : usize) -> usize {
+        let one        = self.constant(&[1.0]);
+        let reciprocal = self.binary(Binary::Division, one, a);
+        return self.unary(operation, reciprocal);
+    }
+    // As `EvaluatedValues::hyperbolic_operation`.
+    fn hyperbolic(&mut self, a : usize, top : Option<f64>, bottom : Option<f64>) -> usize {
+        let top    = self.hyperbolic_side(a, top);
+        let bottom = self.hyperbolic_side(a, bottom);
+        return self.binary(Binary::Division, top, bottom);
+    }
+    // `sign * e^-a + e^a`, or `2` without a sign.
+    fn hyperbolic_side(&mut self, a : usize, value : Option<f64>) -> usize {
+        return match (value) {
+            Some(sign) => {
+                let e         = self.constant(&[var::E]);
+                let minus_one = self.constant(&[-1.0]);
+                let sign      = self.constant(&[sign]);
+                let negated   = self.binary(Binary::Multiplication, a, minus_one);
+                let falling   = self.binary(Binary::Power, e, negated);
+                let falling   = self.binary(Binary::Multiplication, falling, sign);
+                let rising    = self.binary(Binary::Power, e, a);
+                self.binary(Binary::Addition, falling, rising)
+            },
+            None => self.constant(&[2.0])
+        };
+    }
+
+    fn constant(&mut self, values : &[f64]) -> usize {
+        let key = values.iter().map(|value| value.to_bits()).collect::<Vec<u64>>();
+        if let Some(register) = self.folded.get(&key) {
+            return *register;
+        }
+        let start = self.constants.len();
+        self.constants.extend_from_slice(values);
+        return self.add_constant(key, start);
+    }
+
+    // Register holding the values of another without repeats. Inputs hold
+    // a single value, so are read as they are.
+    fn distinct(&mut self, register : usize) -> usize {
+        if (self.instructions.iter().any(|(input, instruction)| *input == register && matches!(instruction, Instruction::Input(_)))) {
+            return register;
+        }
+        return self.emit(Instruction::Union(register, register));
+    }
+
+    // Register written by an instruction, reusing one already emitted, and
+    // carrying it out now if all of its operands are constant.
+    fn emit(&mut self, instruction : Instruction) -> usize {
+        if let Some(register) = self.emitted.get(&instruction) {
+            return *register;
+        }
+        let operands = instruction.operands();
+        if (! operands.is_empty() && operands.iter().all(|operand| ! self.computed[*operand])) {
+            let start = self.constants.len();
+            execute(&instruction, &mut self.constants, &self.spans, &[]);
+            let key = self.constants[start..].iter().map(|value| value.to_bits()).collect::<Vec<u64>>();
+            let register = match (self.folded.get(&key)) {
+                Some(register) => {
+                    self.constants.truncate(start);
+                    *register
+                },
+                None => self.add_constant(key, start)
+            };
+            self.emitted.insert(instruction, register);
+            return register;
+        }
+        let register = self.spans.len();
+        self.spans.push([0, 0]);
+        self.computed.push(true);
+        self.instructions.push((register, instruction));
+        self.emitted.insert(instruction, register);
+        return register;
+    }
+
+    // Register for the constant values from `start` to the end of `constants`.
+    fn add_constant(&mut self, key : Vec<u64>, start : usize) -> usize {
+        let register = self.spans.len();
+        self.spans.push([start, self.constants.len()]);
+        self.computed.push(false);
+        self.folded.insert(key, register);
+        return register;
+    }
+}
+
+// Statistics compiled to instructions, see `parse::statistics`.
+impl Arithmetic for Compiler {
+    type Value = usize;
+    fn empty(&mut self) -> usize {
+        return self.constant(&[]);
+    }
+    fn number(&mut self, value : f64) -> usize {
+        return self.constant(&[value]);
+    }
+    fn single(&self, register : &usize) -> Option<f64> {
+        return self.single_constant(*register);
+    }
+    fn union(&mut self, a : &usize, b : &usize) -> usize {
+        return Compiler::union(self, vec![*a, *b]);
+    }
+    fn addition(&mut self, a : &usize, b : &usize) -> usize {
+        return self.binary(Binary::Addition, *a, *b);
+    }
+    fn subtraction(&mut self, a : &usize, b : &usize) -> usize {
+        return self.binary(Binary::Subtraction, *a, *b);
+    }
+    fn multiplication(&mut self, a : &usize, b : &usize) -> usize {
+        return self.binary(Binary::Multiplication, *a, *b);
+    }
+    fn division(&mut self, a : &usize, b : &usize) -> usize {
+        return self.binary(Binary::Division, *a, *b);
+    }
+    fn power(&mut self, a : &usize, b : &usize) -> usize {
+        return self.binary(Binary::Power, *a, *b);
+    }
+    fn square_root(&mut self, a : &usize) -> usize {
+        return self.unary(Unary::SquareRoot, *a);
+    }
+    fn absolute_value(&mut self, a : &usize) -> usize {
+        return self.unary(Unary::AbsoluteValue, *a);
+    }
+    fn sign(&mut self, a : &usize) -> usize {
+        return self.unary(Unary::Sign, *a);
+    }
+    fn minimum(&mut self, a : &usize, b : &usize) -> usize {
+        return self.binary(Binary::Minimum, *a, *b);
+    }
+    fn maximum(&mut self, a : &usize, b : &usize) -> usize {
+        return self.binary(Binary::Maximum, *a, *b);
+    }
+}
+
+
+pub struct Program {
+    // Values of every constant register, one after another.
+    constants    : Vec<f64>,
+    // Start, End of each register's values. Only constant registers are filled in.
+    spans        : Vec<[usize; 2]>,
+    // Register written, Instruction
+    instructions : Vec<(usize, Instruction)>,
+    inputs       : usize,
+    // Register of each output.
+    outputs      : Vec<usize>
+}
+impl Program {
+    // Somewhere to run the program. A machine can be reused for many runs.
+    pub fn machine(&self) -> Machine {
+        return Machine {
+            values : self.constants.clone(),
+            spans  : self.spans.clone()
+        };
+    }
+
+    // Run the program with a value for each input, in the order they were set.
+    pub fn run(&self, machine : &mut Machine, inputs : &[f64]) {
+        machine.values.truncate(self.constants.len());
+        for (register, instruction) in &self.instructions {
+            let start = machine.values.len();
+            execute(instruction, &mut machine.values, &machine.spans, inputs);
+            machine.spans[*register] = [start, machine.values.len()];
+        }
+    }
+
+    // Values of an output after the last run.
+    pub fn output<'l>(&self, machine : &'l Machine, index : usize) -> &'l [f64] {
+        let [start, end] = machine.spans[self.outputs[index]];
+        return &machine.values[start..end];
+    }
+
+    pub fn inputs(&self) -> usize {
+        return self.inputs;
+    }
+
+    pub fn outputs(&self) -> usize {
+        return self.outputs.len();
+    }
+}
+
+pub struct Machine {
+    // Constants, then the values written by the last run.
+    values : Vec<f64>,
+    // Start, End in `values` of each register's values.
+    spans  : Vec<[usize; 2]>
+}
+
+
+// Carry out an instruction, adding its values to the end of `values`.
+fn execute(instruction : &Instruction, values : &mut Vec<f64>, spans : &[[usize; 2]], inputs : &[f64]) {
+    match (instruction) {
+        Instruction::Input(i) => values.push(inputs[*i]),
+        Instruction::Unary(operation, a) => {
+            for i in spans[*a][0]..spans[*a][1] {
+                if let Some(value) = operation.apply(values[i]) {
+                    values.push(value);
+                }
+            }
+        },
+        Instruction::Binary(operation, a, b) => {
+            for i in spans[*a][0]..spans[*a][1] {
+                for j in spans[*b][0]..spans[*b][1] {
+                    if let Some(value) = operation.apply(values[i], values[j]) {
+                        values.push(value);
+                    }
+                }
+            }
+        },
+        Instruction::Union(a, b) => {
+            let start = values.len();
+            for i in (spans[*a][0]..spans[*a][1]).chain(spans[*b][0]..spans[*b][1]) {
+                let value = values[i];
+                if (! values[start..].contains(&value)) {
+                    values.push(value);
+                }
+            }
+        },
+        Instruction::Compare(relation, a, b) => {
+            let holds = (spans[*a][0]..spans[*a][1]).any(|i| (spans[*b][0]..spans[*b][1]).any(|j| relation.holds(values[i] - values[j])));
+            if (holds) {
+                values.push(1.0);
+            }
+        },
+        Instruction::Select(condition, a, b) => {
+            let [start, end] = if (spans[*condition][1] > spans[*condition][0]) {spans[*a]} else {spans[*b]};
+            values.extend_from_within(start..end);
+        }
+    };
+}
+
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::parse::import::{self, Format};
+    use crate::parse::values::EvaluatedValues;
+
+    // The equations in `benches/evaluate.rs`.
+    const BENCHED : [&str; 4] = [
+        "y = 3*x^4 - 2*x^3 + x^2 - 5*x + 7",
+        "y = sin(x)^2 + cos(x)^2 * tan(x / 2) - sin(x) * cos(x)",
+        "a = 2 * pi\nb = sqrt(a) + 1\nc = sin(a * x) + b\ny = c^2 - c + cosh(x / 4)",
+        "y = [1, -1] * sqrt(25 - x^2)"
+    ];
+
+    // Values of `y` at `x`, walking the node trees, then running them
+    // compiled, set up the same way as the benchmarks.
+    pub(crate) fn evaluate_both(source : &str, x : f64) -> (Vec<f64>, Vec<f64>) {
+        let nodes = import::parse(source, Format::Text).unwrap().nodes;
+
+        let mut variables = HashMap::new();
+        variables.insert(String::from("pi"), EvaluatedValues::from(vec![var::PI]));
+        variables.insert(String::from("x"), EvaluatedValues::from(vec![x]));
+        for node in &nodes {
+            node.evaluate("y", &mut variables).unwrap();
+        }
+        let tree = variables["y"].get_values().clone();
+
+        let mut compiler = Compiler::new();
+        compiler.set_constant("pi", &[var::PI]);
+        compiler.set_input("x");
+        let mut output = 0;
+        for node in &nodes {
+            output = compiler.compile(node).unwrap();
+        }
+        let program     = compiler.finish(vec![output]);
+        let mut machine = program.machine();
+        program.run(&mut machine, &[x]);
+        return (tree, program.output(&machine, 0).to_vec());
+    }
+
+    #[test]
+    fn benchmarks_compare_the_same_values() {
+        for source in BENCHED {
+            for x in [-4.5, -1.0, 0.0, 0.3, 2.0, 4.9] {
+                let (tree, compiled) = evaluate_both(source, x);
+                assert!(! tree.is_empty(), "`{}` at {}", source, x);
+                assert_eq!(tree, compiled, "`{}` at {}", source, x);
+            }
+        }
+    }
+
+    #[test]
+    fn repeats_match_the_tree() {
+        let cases = [
+            // Operations keep repeats.
+            ("y = [1, -1]^2 + x",                           vec![3.0, 3.0]),
+            ("a = [2, -2] * x\ny = a^2",                    vec![16.0, 16.0]),
+            // Reading a variable drops them, whether it is constant or not.
+            ("a = [1, -1]^2\ny = a * x",                    vec![2.0]),
+            ("a = [2, -2] * x\nb = a^2\ny = b + 1",         vec![17.0]),
+            ("a = [2, -2] * x\nb = a^2\ny = [b, b, x] - 1", vec![15.0, 1.0]),
+        ];
+        for (source, expected) in cases {
+            let (tree, compiled) = evaluate_both(source, 2.0);
+            assert_eq!(tree, expected, "`{}`", source);
+            assert_eq!(compiled, expected, "`{}`", source);
+        }
+    }
+
+    #[test]
+    fn piecewise_and_restrictions_match_the_tree() {
+        let cases = [
+            ("y = {x < 0: -x, x}",                 -2.0, vec![2.0]),
+            ("y = {x < 0: -x, x}",                  3.0, vec![3.0]),
+            // The first branch that holds is taken.
+            ("y = {x > 0: 1, x > 1: 2}",            2.0, vec![1.0]),
+            // A bare condition is worth `1`.
+            ("y = {x > 0}",                         1.0, vec![1.0]),
+            // Without a last value, nothing is left where no branch holds.
+            ("y = {x < 0: -1, x > 0: 1}",           0.0, vec![]),
+            ("y = x^2 {0 < x < 2}",                 1.0, vec![1.0]),
+            ("y = x^2 {0 < x < 2}",                 2.0, vec![]),
+            ("y = x^2 {0 < x < 2}",                -1.0, vec![]),
+            ("y = {-1 < x <= 1: 5}",                1.0, vec![5.0]),
+            ("y = {x < 0 or x > 2: 1, 0}",          3.0, vec![1.0]),
+            ("y = {x < 0 or x > 2: 1, 0}",          1.0, vec![0.0]),
+            ("y = {x >= 1 and x <= 1: 5, 0}",       1.0, vec![5.0]),
+            ("y = {not (x < 1): x, 0}",             0.5, vec![0.0]),
+            ("y = {not (x < 1): x, 0}",             1.5, vec![1.5]),
+            ("y = {x < 0: [1, 2], 3}",             -1.0, vec![1.0, 2.0]),
+            ("a = {x < 0: 2, 3}\ny = a * x {x < 2}", 1.0, vec![3.0]),
+        ];
+        for (source, x, expected) in cases {
+            let (tree, compiled) = evaluate_both(source, x);
+            assert_eq!(tree, expected, "`{}` at {}", source, x);
+            assert_eq!(compiled, expected, "`{}` at {}", source, x);
+        }
+    }
+
+    #[test]
+    fn constants_are_folded() {
+        let mut compiler = Compiler::new();
+        compiler.set_input("x");
+        let node = import::parse("y = 2 * 3 + x", Format::Text).unwrap().nodes;
+        let output  = compiler.compile(&node[0]).unwrap();
+        let program = compiler.finish(vec![output]);
+        // Only the input and the addition are left.
+        assert_eq!(program.instructions.len(), 2);
+        let mut machine = program.machine();
+        program.run(&mut machine, &[1.5]);
+        assert_eq!(program.output(&machine, 0), [7.5]);
+    }
+}
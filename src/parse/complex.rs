@@ -0,0 +1,540 @@
+use std::fmt;
+use std::ops;
+
+use crate::parse::var;
+
+
+// Largest denominator checked for when looking for the real branch of a
+// fractional power of a negative number.
+const MAX_DENOMINATOR : i64 = 64;
+// Furthest from real a value may be and still be ordered, as the default
+// `--imaginary-tolerance`.
+pub const ORDER_TOLERANCE : f64 = 1e-9;
+
+
+// A complex number : Real, Imaginary
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Complex {
+    pub re : f64,
+    pub im : f64
+}
+impl Complex {
+    pub fn new(re : f64, im : f64) -> Complex {
+        return Complex {re, im};
+    }
+    pub fn real(re : f64) -> Complex {
+        return Complex::new(re, 0.0);
+    }
+    pub fn i() -> Complex {
+        return Complex::new(0.0, 1.0);
+    }
+    pub fn is_real(&self) -> bool {
+        return self.im == 0.0;
+    }
+    pub fn is_zero(&self) -> bool {
+        return self.re == 0.0 && self.im == 0.0;
+    }
+    pub fn is_finite(&self) -> bool {
+        return self.re.is_finite() && self.im.is_finite();
+    }
+    // Whether the imaginary part is within `tolerance` of zero, relative
+    // to the size of the real part once that is over one.
+    pub fn is_nearly_real(&self, tolerance : f64) -> bool {
+        return self.im.abs() <= tolerance * self.re.abs().max(1.0);
+    }
+
+    pub fn abs(self) -> f64 {
+        return self.re.hypot(self.im);
+    }
+    pub fn arg(self) -> f64 {
+        return self.im.atan2(self.re);
+    }
+
+    pub fn exp(self) -> Complex {
+        let scale = self.re.exp();
+        if (self.is_real()) {
+            return Complex::real(scale);
+        }
+        return Complex::new(scale * self.im.cos(), scale * self.im.sin());
+    }
+    // Principal value, with the imaginary part in (-π, π].
+    pub fn ln(self) -> Complex {
+        if (self.is_real() && self.re > 0.0) {
+            return Complex::real(self.re.ln());
+        }
+        return Complex::new(self.abs().ln(), self.arg());
+    }
+    // Principal value.
+    pub fn sqrt(self) -> Complex {
+        if (self.is_real()) {
+            return if (self.re >= 0.0) {
+                Complex::real(self.re.sqrt())
+            } else {
+                Complex::new(0.0, (-self.re).sqrt())
+            };
+        }
+        let r = self.abs();
+        return Complex::new(((r + self.re) / 2.0).sqrt(), ((r - self.re) / 2.0).sqrt().copysign(self.im));
+    }
+    // Principal value. Whole exponents are multiplied out, so `(-2)^2` is exactly `4`.
+    pub fn pow(self, exponent : Complex) -> Option<Complex> {
+        if (self.is_zero()) {
+            return if (exponent.re > 0.0) {Some(Complex::real(0.0))} else {None};
+        }
+        if (exponent.is_real()) {
+            if (self.is_real() && self.re > 0.0) {
+                return Some(Complex::real(self.re.powf(exponent.re)));
+            }
+            if (exponent.re.fract() == 0.0 && exponent.re.abs() <= 64.0) {
+                return Some(self.powi(exponent.re as i32));
+            }
+        }
+        return Some((exponent * self.ln()).exp());
+    }
+    fn powi(self, exponent : i32) -> Complex {
+        let mut result = Complex::real(1.0);
+        let mut base   = self;
+        let mut n      = exponent.unsigned_abs();
+        while (n > 0) {
+            if (n % 2 == 1) {
+                result = result * base;
+            }
+            base = base * base;
+            n   /= 2;
+        }
+        return if (exponent < 0) {Complex::real(1.0) / result} else {result};
+    }
+
+    pub fn sin(self) -> Complex {
+        return Complex::new(self.re.sin() * self.im.cosh(), self.re.cos() * self.im.sinh());
+    }
+    pub fn cos(self) -> Complex {
+        return Complex::new(self.re.cos() * self.im.cosh(), -self.re.sin() * self.im.sinh());
+    }
+    pub fn tan(self) -> Complex {
+        if (self.is_real()) {
+            return Complex::real(self.re.tan());
+        }
+        return self.sin() / self.cos();
+    }
+    // asin(z) = -i ln(iz + sqrt(1 - z²))
+    pub fn asin(self) -> Complex {
+        if (self.is_real() && self.re.abs() <= 1.0) {
+            return Complex::real(self.re.asin());
+        }
+        let one = Complex::real(1.0);
+        let i   = Complex::i();
+        return (i * self + (one - self * self).sqrt()).ln() * -i;
+    }
+    // acos(z) = π/2 - asin(z)
+    pub fn acos(self) -> Complex {
+        if (self.is_real() && self.re.abs() <= 1.0) {
+            return Complex::real(self.re.acos());
+        }
+        return Complex::real(var::PI / 2.0) - self.asin();
+    }
+    // atan(z) = i/2 (ln(1 - iz) - ln(1 + iz))
+    pub fn atan(self) -> Complex {
+        if (self.is_real()) {
+            return Complex::real(self.re.atan());
+        }
+        let one = Complex::real(1.0);
+        let iz  = Complex::i() * self;
+        return ((one - iz).ln() - (one + iz).ln()) * Complex::new(0.0, 0.5);
+    }
+    pub fn sinh(self) -> Complex {
+        return Complex::new(self.re.sinh() * self.im.cos(), self.re.cosh() * self.im.sin());
+    }
+    pub fn cosh(self) -> Complex {
+        return Complex::new(self.re.cosh() * self.im.cos(), self.re.sinh() * self.im.sin());
+    }
+    pub fn tanh(self) -> Complex {
+        if (self.is_real()) {
+            return Complex::real(self.re.tanh());
+        }
+        return self.sinh() / self.cosh();
+    }
+}
+impl ops::Add for Complex {
+    type Output = Complex;
+    fn add(self, other : Complex) -> Complex {
+        return Complex::new(self.re + other.re, self.im + other.im);
+    }
+}
+impl ops::Sub for Complex {
+    type Output = Complex;
+    fn sub(self, other : Complex) -> Complex {
+        return Complex::new(self.re - other.re, self.im - other.im);
+    }
+}
+impl ops::Mul for Complex {
+    type Output = Complex;
+    fn mul(self, other : Complex) -> Complex {
+        return Complex::new(
+            self.re * other.re - self.im * other.im,
+            self.re * other.im + self.im * other.re
+        );
+    }
+}
+impl ops::Div for Complex {
+    type Output = Complex;
+    fn div(self, other : Complex) -> Complex {
+        if (other.is_real()) {
+            return Complex::new(self.re / other.re, self.im / other.re);
+        }
+        let denominator = other.re * other.re + other.im * other.im;
+        return Complex::new(
+            (self.re * other.re + self.im * other.im) / denominator,
+            (self.im * other.re - self.re * other.im) / denominator
+        );
+    }
+}
+impl ops::Neg for Complex {
+    type Output = Complex;
+    fn neg(self) -> Complex {
+        return Complex::new(-self.re, -self.im);
+    }
+}
+impl fmt::Display for Complex {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
+        if (self.is_real()) {
+            return write!(f, "{}", self.re);
+        }
+        return write!(f, "{}{}{}i", self.re, if (self.im < 0.0) {"-"} else {"+"}, self.im.abs());
+    }
+}
+
+
+// Values of an expression over the complex numbers. Operations take the
+// principal value, as `EvaluatedValues` does over the reals, except that
+// fractional powers of negative numbers also keep their real branch, so
+// `(-8)^(1/3)` has both `1+1.732i` and `-2`.
+#[derive(Debug, Clone)]
+pub struct EvaluatedComplex {
+    values : Vec<Complex>
+}
+impl Default for EvaluatedComplex {
+    fn default() -> EvaluatedComplex {
+        return EvaluatedComplex::new();
+    }
+}
+impl EvaluatedComplex {
+    pub fn new() -> EvaluatedComplex {
+        return EvaluatedComplex {values: vec![]};
+    }
+    pub fn from(values : Vec<Complex>) -> EvaluatedComplex {
+        return EvaluatedComplex {values: values};
+    }
+    pub fn copy(values : &EvaluatedComplex) -> EvaluatedComplex {
+        return EvaluatedComplex::from(values.values.clone());
+    }
+    pub fn real(value : f64) -> EvaluatedComplex {
+        return EvaluatedComplex::from(vec![Complex::real(value)]);
+    }
+
+    pub fn add(&self, values : &EvaluatedComplex) -> EvaluatedComplex {
+        let mut new_values = EvaluatedComplex::copy(self);
+        for value in &values.values {
+            new_values.insert(*value);
+        }
+        return new_values;
+    }
+    pub fn push(&self, value : Complex) -> EvaluatedComplex {
+        let mut new_values = EvaluatedComplex::copy(self);
+        new_values.insert(value);
+        return new_values;
+    }
+    pub fn get_values(&self) -> &Vec<Complex> {
+        return &self.values;
+    }
+    // Real parts of the finite values whose imaginary part is nearly zero,
+    // see `Complex::is_nearly_real`.
+    pub fn real_values(&self, tolerance : f64) -> Vec<f64> {
+        let mut values = vec![];
+        for value in &self.values {
+            if (value.is_finite() && value.is_nearly_real(tolerance) && ! values.contains(&value.re)) {
+                values.push(value.re);
+            }
+        }
+        return values;
+    }
+
+    pub fn addition(&self, other : &EvaluatedComplex) -> EvaluatedComplex {
+        return self.binary_operation(other, |a, b, new_values| new_values.insert(a + b));
+    }
+    pub fn subtraction(&self, other : &EvaluatedComplex) -> EvaluatedComplex {
+        return self.binary_operation(other, |a, b, new_values| new_values.insert(a - b));
+    }
+    pub fn multiplication(&self, other : &EvaluatedComplex) -> EvaluatedComplex {
+        return self.binary_operation(other, |a, b, new_values| new_values.insert(a * b));
+    }
+    pub fn division(&self, other : &EvaluatedComplex) -> EvaluatedComplex {
+        return self.binary_operation(other, |a, b, new_values| {
+            if (! b.is_zero()) {
+                new_values.insert(a / b);
+            }
+        });
+    }
+    pub fn power(&self, other : &EvaluatedComplex) -> EvaluatedComplex {
+        return self.binary_operation(other, power);
+    }
+
+
+    pub fn absolute_value(&self) -> EvaluatedComplex {
+        return self.unary_operation(|a, new_values| new_values.insert(Complex::real(a.abs())));
+    }
+    pub fn square_root(&self) -> EvaluatedComplex {
+        return self.unary_operation(|a, new_values| new_values.insert(a.sqrt()));
+    }
+    pub fn nth_root(&self, degree : &EvaluatedComplex) -> EvaluatedComplex {
+        return self.binary_operation(degree, |a, n, new_values| {
+            if (! n.is_zero()) {
+                power(a, Complex::real(1.0) / n, new_values);
+            }
+        });
+    }
+    pub fn sine(&self) -> EvaluatedComplex {
+        return self.unary_operation(|a, new_values| new_values.insert(a.sin()));
+    }
+    pub fn cosine(&self) -> EvaluatedComplex {
+        return self.unary_operation(|a, new_values| new_values.insert(a.cos()));
+    }
+    pub fn tangent(&self) -> EvaluatedComplex {
+        return self.unary_operation(|a, new_values| new_values.insert(a.tan()));
+    }
+    pub fn cosecant(&self) -> EvaluatedComplex {
+        return EvaluatedComplex::real(1.0).division(&self.sine());
+    }
+    pub fn secant(&self) -> EvaluatedComplex {
+        return EvaluatedComplex::real(1.0).division(&self.cosine());
+    }
+    pub fn cotangent(&self) -> EvaluatedComplex {
+        return EvaluatedComplex::real(1.0).division(&self.tangent());
+    }
+    pub fn inverse_sine(&self) -> EvaluatedComplex {
+        return self.unary_operation(|a, new_values| new_values.insert(a.asin()));
+    }
+    pub fn inverse_cosine(&self) -> EvaluatedComplex {
+        return self.unary_operation(|a, new_values| new_values.insert(a.acos()));
+    }
+    pub fn inverse_tangent(&self) -> EvaluatedComplex {
+        return self.unary_operation(|a, new_values| new_values.insert(a.atan()));
+    }
+    pub fn inverse_cosecant(&self) -> EvaluatedComplex {
+        return EvaluatedComplex::real(1.0).division(self).inverse_sine();
+    }
+    pub fn inverse_secant(&self) -> EvaluatedComplex {
+        return EvaluatedComplex::real(1.0).division(self).inverse_cosine();
+    }
+    pub fn inverse_cotangent(&self) -> EvaluatedComplex {
+        return EvaluatedComplex::real(1.0).division(self).inverse_tangent();
+    }
+    pub fn hyperbolic_sine(&self) -> EvaluatedComplex {
+        return self.unary_operation(|a, new_values| new_values.insert(a.sinh()));
+    }
+    pub fn hyperbolic_cosine(&self) -> EvaluatedComplex {
+        return self.unary_operation(|a, new_values| new_values.insert(a.cosh()));
+    }
+    pub fn hyperbolic_tangent(&self) -> EvaluatedComplex {
+        return self.unary_operation(|a, new_values| new_values.insert(a.tanh()));
+    }
+    pub fn hyperbolic_cosecant(&self) -> EvaluatedComplex {
+        return EvaluatedComplex::real(1.0).division(&self.hyperbolic_sine());
+    }
+    pub fn hyperbolic_secant(&self) -> EvaluatedComplex {
+        return EvaluatedComplex::real(1.0).division(&self.hyperbolic_cosine());
+    }
+    pub fn hyperbolic_cotangent(&self) -> EvaluatedComplex {
+        return EvaluatedComplex::real(1.0).division(&self.hyperbolic_tangent());
+    }
+    pub fn exponential(&self) -> EvaluatedComplex {
+        return self.unary_operation(|a, new_values| new_values.insert(a.exp()));
+    }
+    pub fn natural_logarithm(&self) -> EvaluatedComplex {
+        return self.unary_operation(|a, new_values| {
+            if (! a.is_zero()) {
+                new_values.insert(a.ln());
+            }
+        });
+    }
+    pub fn logarithm(&self, other : &EvaluatedComplex) -> EvaluatedComplex {
+        return self.natural_logarithm().division(&other.natural_logarithm());
+    }
+    // Only defined for real numbers.
+    pub fn modulo(&self, other : &EvaluatedComplex) -> EvaluatedComplex {
+        return self.binary_operation(other, |a, b, new_values| {
+            if (a.is_real() && b.is_real()) {
+                new_values.insert(Complex::real(-b.re * (a.re / b.re).floor() + a.re));
+            }
+        });
+    }
+    // Rounding is applied to both parts.
+    pub fn ceiling(&self) -> EvaluatedComplex {
+        return self.unary_operation(|a, new_values| new_values.insert(Complex::new(a.re.ceil(), a.im.ceil())));
+    }
+    pub fn floor(&self) -> EvaluatedComplex {
+        return self.unary_operation(|a, new_values| new_values.insert(Complex::new(a.re.floor(), a.im.floor())));
+    }
+    pub fn round(&self) -> EvaluatedComplex {
+        return self.unary_operation(|a, new_values| new_values.insert(Complex::new(a.re.round(), a.im.round())));
+    }
+    // The point on the unit circle in the same direction.
+    pub fn sign(&self) -> EvaluatedComplex {
+        return self.unary_operation(|a, new_values| {
+            new_values.insert(if (a.is_zero()) {a} else {a / Complex::real(a.abs())})
+        });
+    }
+    // Only values that are nearly real are ordered, see `ORDER_TOLERANCE`.
+    pub fn minimum(&self, other : &EvaluatedComplex) -> EvaluatedComplex {
+        return self.binary_operation(other, |a, b, new_values| {
+            if (a.is_nearly_real(ORDER_TOLERANCE) && b.is_nearly_real(ORDER_TOLERANCE)) {
+                new_values.insert(Complex::real(a.re.min(b.re)));
+            }
+        });
+    }
+    pub fn maximum(&self, other : &EvaluatedComplex) -> EvaluatedComplex {
+        return self.binary_operation(other, |a, b, new_values| {
+            if (a.is_nearly_real(ORDER_TOLERANCE) && b.is_nearly_real(ORDER_TOLERANCE)) {
+                new_values.insert(Complex::real(a.re.max(b.re)));
+            }
+        });
+    }
+
+
+    fn insert(&mut self, value : Complex) {
+        if (! self.values.contains(&value)) {
+            self.values.push(value);
+        }
+    }
+    fn unary_operation<T>(&self, target : T) -> EvaluatedComplex
+        where T : Fn(Complex, &mut EvaluatedComplex)
+    {
+        let mut new_values = EvaluatedComplex::new();
+        for a in &self.values {
+            target(*a, &mut new_values);
+        }
+        return new_values;
+    }
+    fn binary_operation<T>(&self, other : &EvaluatedComplex, target : T) -> EvaluatedComplex
+        where T : Fn(Complex, Complex, &mut EvaluatedComplex)
+    {
+        let mut new_values = EvaluatedComplex::new();
+        for a in &self.values {
+            for b in &other.values {
+                target(*a, *b, &mut new_values);
+            }
+        }
+        return new_values;
+    }
+}
+impl fmt::Display for EvaluatedComplex {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(f, "{}", self.values
+            .iter().map(|v|v.to_string())
+            .collect::<Vec<String>>()
+            .join(",")
+        );
+    }
+}
+
+
+// The principal value, and for a negative real base raised to a fraction
+// with an odd denominator, the real branch too.
+fn power(base : Complex, exponent : Complex, new_values : &mut EvaluatedComplex) {
+    if let Some(value) = base.pow(exponent) {
+        new_values.insert(value);
+    }
+    if (base.is_real() && base.re < 0.0 && exponent.is_real()) {
+        if let Some((numerator, denominator)) = as_fraction(exponent.re) {
+            if (denominator % 2 != 0) {
+                let magnitude = (-base.re).powf(exponent.re);
+                new_values.insert(Complex::real(if (numerator % 2 == 0) {magnitude} else {-magnitude}));
+            }
+        }
+    }
+}
+
+// Numerator, Denominator of the simplest fraction equal to `value`, if its
+// denominator is at most `MAX_DENOMINATOR`.
+fn as_fraction(value : f64) -> Option<(i64, i64)> {
+    if (! value.is_finite()) {
+        return None;
+    }
+    for denominator in 1..=MAX_DENOMINATOR {
+        let numerator = (value * denominator as f64).round();
+        if ((numerator - value * denominator as f64).abs() <= 1e-9 * denominator as f64) {
+            return Some((numerator as i64, denominator));
+        }
+    }
+    return None;
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::parse::import::{self, Format};
+
+    // Real values of `y = ...` at `x`, as drawn with the default tolerance.
+    fn real_values(source : &str, x : f64) -> Vec<f64> {
+        let node = &import::parse(source, Format::Text).unwrap().nodes[0];
+        let mut variables = HashMap::new();
+        variables.insert(String::from("i"), EvaluatedComplex::from(vec![Complex::i()]));
+        variables.insert(String::from("x"), EvaluatedComplex::real(x));
+        node.evaluate_complex("y", &mut variables).unwrap();
+        return variables["y"].real_values(ORDER_TOLERANCE);
+    }
+
+    fn assert_close(found : &[f64], expected : &[f64]) {
+        assert_eq!(found.len(), expected.len(), "{:?}, expected {:?}", found, expected);
+        for (found, expected) in found.iter().zip(expected) {
+            assert!((found - expected).abs() < 1e-12, "{:?}, expected {:?}", found, expected);
+        }
+    }
+
+    #[test]
+    fn fractional_powers_keep_their_real_branch() {
+        assert_close(&real_values("y = x^(1/3)", -8.0),        &[-2.0]);
+        assert_close(&real_values("y = x^(1/3)", 8.0),         &[2.0]);
+        assert_close(&real_values("y = x^(2/3)", -8.0),        &[4.0]);
+        assert_close(&real_values("y = nthroot(5, x)", -32.0), &[-2.0]);
+        // Even denominators have no real branch.
+        assert_close(&real_values("y = x^(1/2)", -4.0),        &[]);
+        assert_close(&real_values("y = x^(3/4)", -16.0),       &[]);
+        // The principal value is still there, just not real.
+        let node = &import::parse("y = x^(1/3)", Format::Text).unwrap().nodes[0];
+        let mut variables = HashMap::new();
+        variables.insert(String::from("x"), EvaluatedComplex::real(-8.0));
+        node.evaluate_complex("y", &mut variables).unwrap();
+        assert_eq!(variables["y"].get_values().len(), 2);
+    }
+
+    #[test]
+    fn imaginary_parts_cancel() {
+        assert_close(&real_values("y = (x + i)(x - i)", 2.0), &[5.0]);
+        assert_close(&real_values("y = sqrt(x)^2", -4.0),     &[-4.0]);
+        assert_close(&real_values("y = x + i", 2.0),          &[]);
+    }
+
+    #[test]
+    fn tolerance_is_relative_to_large_real_parts() {
+        assert!(Complex::new(1.0, 1e-10).is_nearly_real(1e-9));
+        assert!(! Complex::new(1.0, 1e-8).is_nearly_real(1e-9));
+        assert!(Complex::new(0.001, 5e-10).is_nearly_real(1e-9));
+        assert!(Complex::new(1e6, 1e-4).is_nearly_real(1e-9));
+        assert!(! Complex::new(1e6, 1e-2).is_nearly_real(1e-9));
+        assert!(! Complex::new(0.0, 1.0).is_nearly_real(0.5));
+    }
+
+    #[test]
+    fn real_values_drop_infinities_and_repeats() {
+        let values = EvaluatedComplex::from(vec![
+            Complex::real(1.0), Complex::new(1.0, 1e-12), Complex::new(2.0, 1e-3),
+            Complex::real(f64::INFINITY), Complex::new(f64::NAN, 0.0), Complex::real(-3.0)
+        ]);
+        assert_eq!(values.real_values(1e-9), [1.0, -3.0]);
+        assert_eq!(values.real_values(1e-2), [1.0, 2.0, -3.0]);
+    }
+}
@@ -0,0 +1,256 @@
+// User defined functions, such as `f(x) = x^2 + 1` and `g(a, b) = a*b`.
+//
+// On import every call is replaced by the body of its function, with the
+// arguments put in place of the parameters, so the renderer only ever sees
+// plain expressions. Parameters hide variables of the same name inside the
+// body, any other name in the body is read from the equation list, as in
+// Desmos. Definitions stay in the list, but are not drawn.
+
+use std::collections::HashMap;
+
+use loggerithm::{logger, log};
+use loggerithm::level::DEBUG;
+logger!(super);
+
+use crate::error::Error;
+use crate::parse::node::{Node, NodeBase};
+
+
+struct Function {
+    parameters : Vec<String>,
+    body       : Node
+}
+
+
+// Replace every call to a user defined function with the function's body.
+pub fn inline(mut nodes : Vec<Node>) -> Result<Vec<Node>, Error> {
+    let mut functions = HashMap::new();
+    for node in &nodes {
+        if let Some((name, parameters, body)) = definition(node) {
+            for (i, parameter) in parameters.iter().enumerate() {
+                if (parameters[..i].contains(parameter)) {
+                    return Err(Error::DuplicateParameter {name: String::from(name), parameter: String::from(*parameter)});
+                }
+            }
+            let function = Function {
+                parameters : parameters.into_iter().map(String::from).collect(),
+                body       : body.clone()
+            };
+            if (functions.insert(String::from(name), function).is_some()) {
+                return Err(Error::FunctionRedefined(String::from(name)));
+            }
+        }
+    }
+    if (functions.is_empty()) {
+        return Ok(nodes);
+    }
+    check_recursion(&functions)?;
+    for node in &mut nodes {
+        if (definition(node).is_none()) {
+            inline_calls(node, &functions)?;
+        }
+    }
+    log!(DEBUG, "Inlined {} function{}.", functions.len(), if (functions.len() == 1) {""} else {"s"});
+    return Ok(nodes);
+}
+
+// Name, Parameters, Body of a function definition, `f(a, b) = ...` with
+// only names between the parentheses. Other equations with a call on the
+// left, such as `f(x + 1) = 2`, are not definitions.
+pub fn definition(node : &Node) -> Option<(&str, Vec<&str>, &Node)> {
+    if let NodeBase::Equals(left, body) = &node.base {
+        if let NodeBase::Call(name, arguments) = &left.base {
+            let parameters = arguments.iter()
+                .map(|argument| match (&argument.base) {
+                    NodeBase::Variable(parameter) => Some(parameter.as_str()),
+                    _                             => None
+                })
+                .collect::<Option<Vec<&str>>>()?;
+            return Some((name, parameters, body));
+        }
+    }
+    return None;
+}
+
+
+// Fail if any function calls itself, directly or through others.
+fn check_recursion(functions : &HashMap<String, Function>) -> Result<(), Error> {
+    let mut names = functions.keys().collect::<Vec<&String>>();
+    names.sort();
+    let mut finished = vec![];
+    for name in names {
+        visit(name, functions, &mut vec![], &mut finished)?;
+    }
+    return Ok(());
+}
+
+// Depth first through the calls, with `path` holding the functions being visited.
+fn visit<'l>(name : &'l str, functions : &'l HashMap<String, Function>, path : &mut Vec<&'l str>, finished : &mut Vec<&'l str>) -> Result<(), Error> {
+    if (finished.contains(&name)) {
+        return Ok(());
+    }
+    if let Some(start) = path.iter().position(|visiting| *visiting == name) {
+        let mut names = path[start..].iter().map(|name| String::from(*name)).collect::<Vec<String>>();
+        names.push(String::from(name));
+        return Err(Error::RecursiveFunction(names));
+    }
+    // Undefined functions are reported when their calls are inlined.
+    let function = match (functions.get(name)) {
+        Some(function) => function,
+        None           => return Ok(())
+    };
+    path.push(name);
+    let mut calls = vec![];
+    find_calls(&function.body, &mut calls);
+    for call in calls {
+        visit(call, functions, path, finished)?;
+    }
+    path.pop();
+    finished.push(name);
+    return Ok(());
+}
+
+fn find_calls<'l>(node : &'l Node, calls : &mut Vec<&'l str>) {
+    if let NodeBase::Call(name, _) = &node.base {
+        calls.push(name);
+    }
+    for child in node.base.children() {
+        find_calls(child, calls);
+    }
+}
+
+fn inline_calls(node : &mut Node, functions : &HashMap<String, Function>) -> Result<(), Error> {
+    for child in node.base.children_mut() {
+        inline_calls(child, functions)?;
+    }
+    let mut body = match (&node.base) {
+        NodeBase::Call(name, arguments) => {
+            let function = match (functions.get(name)) {
+                Some(function) => function,
+                None           => return Err(Error::UndefinedFunction(name.clone()))
+            };
+            if (arguments.len() != function.parameters.len()) {
+                return Err(Error::ArgumentCount {name: name.clone(), expected: function.parameters.len(), found: arguments.len()});
+            }
+            let mut body = function.body.clone();
+            substitute(&mut body, &function.parameters, arguments);
+            body
+        },
+        _ => return Ok(())
+    };
+    // The body may call other functions.
+    inline_calls(&mut body, functions)?;
+    *node = body;
+    return Ok(());
+}
+
+// Put the arguments in place of the parameters, all at once, so an
+// argument that names another parameter is left alone. A comprehension
+// variable hides a parameter of the same name in its body, and is renamed
+// when an argument put in its body reads a variable of the same name.
+fn substitute(node : &mut Node, parameters : &[String], arguments : &[Box<Node>]) {
+    match (&mut node.base) {
+        NodeBase::Variable(name) => {
+            if let Some(i) = parameters.iter().position(|parameter| parameter == name) {
+                *node = (*arguments[i]).clone();
+            }
+        },
+        NodeBase::For(body, name, list) => {
+            substitute(list, parameters, arguments);
+            let hidden = parameters.iter()
+                .map(|parameter| if (parameter == name) {String::new()} else {parameter.clone()})
+                .collect::<Vec<String>>();
+            if (hidden.iter().zip(arguments).any(|(parameter, argument)| ! parameter.is_empty() && argument.contains_variable(name))) {
+                let renamed = unused_name(name, body, arguments);
+                substitute(body, std::slice::from_ref(name), &[Node::new(NodeBase::Variable(renamed.clone()))]);
+                *name = renamed;
+            }
+            substitute(body, &hidden, arguments);
+        },
+        base => {
+            for child in base.children_mut() {
+                substitute(child, parameters, arguments);
+            }
+        }
+    };
+}
+
+// `name` with primes added until nothing in `body` or `arguments` reads
+// it. Parsed names never have primes.
+fn unused_name(name : &str, body : &Node, arguments : &[Box<Node>]) -> String {
+    let mut renamed = format!("{}'", name);
+    while (body.contains_variable(&renamed) || arguments.iter().any(|argument| argument.contains_variable(&renamed))) {
+        renamed.push('\'');
+    }
+    return renamed;
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::parse::import::text;
+    use crate::parse::list;
+
+    // Each equation in `source` after inlining, written back out fully bracketed.
+    fn inlined(source : &str) -> Vec<String> {
+        return inline(text::parse(source).unwrap()).unwrap().iter().map(|node| node.to_string()).collect();
+    }
+
+    fn error(source : &str) -> Error {
+        return inline(text::parse(source).unwrap()).unwrap_err();
+    }
+
+    #[test]
+    fn calls_are_replaced_by_the_body() {
+        assert_eq!(inlined("f(x) = x^2 + 1\ny = f(2x)"), ["(f(x) = ((x ^ 2) + 1))", "(y = (((2 * x) ^ 2) + 1))"]);
+        assert_eq!(inlined("g(a, b) = a - b\ny = g(b, a)"), ["(g(a, b) = (a - b))", "(y = (b - a))"]);
+        // Calls in the body and in arguments are inlined too.
+        assert_eq!(inlined("f(x) = 2x\ng(x) = f(x) + 1\ny = g(f(x))"), [
+            "(f(x) = (2 * x))",
+            "(g(x) = (f(x) + 1))",
+            "(y = ((2 * (2 * x)) + 1))"
+        ]);
+    }
+
+    #[test]
+    fn parameters_hide_variables_and_comprehensions_hide_parameters() {
+        assert_eq!(inlined("a = 3\nf(a) = a + b\ny = f(x)")[2], "(y = (x + b))");
+        assert_eq!(inlined("f(k) = [k for k = [1, 2]] + k\ny = f(x)")[1], "(y = ([k for k = [1, 2]] + x))");
+    }
+
+    #[test]
+    fn comprehensions_do_not_capture_arguments() {
+        assert_eq!(inlined("f(a) = [a k for k = [1...3]]\ny = f(k)")[1], "(y = [(k * k') for k' = [1...3]])");
+        // Renamed variables are not captured either.
+        assert_eq!(
+            inlined("f(a) = [a k for k = [1, 2]]\ng(b) = [f(b k) for k = [3, 4]]\ny = g(k)")[2],
+            "(y = [[((k * k') * k'') for k'' = [1, 2]] for k' = [3, 4]])"
+        );
+        // Arguments that do not read the bound name leave it alone.
+        assert_eq!(inlined("f(a) = [a k for k = [1...3]]\ny = f(x)")[1], "(y = [(x * k) for k = [1...3]])");
+
+        let nodes = list::inline(inline(text::parse("f(a) = [a k for k = [1...3]]\nk = 1\ny = mean(f(k))").unwrap()).unwrap());
+        let mut variables = HashMap::new();
+        for node in &nodes {
+            node.evaluate("y", &mut variables).unwrap();
+        }
+        assert_eq!(variables["y"].get_values(), &[2.0]);
+    }
+
+    #[test]
+    fn recursion_is_an_error() {
+        assert!(matches!(error("f(x) = f(x) + 1\ny = f(x)"), Error::RecursiveFunction(names) if names == ["f", "f"]));
+        assert!(matches!(error("f(x) = g(x)\ng(x) = f(x)\ny = f(x)"), Error::RecursiveFunction(names) if names == ["f", "g", "f"]));
+    }
+
+    #[test]
+    fn definitions_and_calls_are_checked() {
+        assert!(matches!(error("f(a, b) = a b\ny = f(x)"),    Error::ArgumentCount {expected: 2, found: 1, ..}));
+        assert!(matches!(error("f(a) = a\ny = f(x, 1)"),      Error::ArgumentCount {expected: 1, found: 2, ..}));
+        assert!(matches!(error("f(a, a) = a\ny = x"),         Error::DuplicateParameter {..}));
+        assert!(matches!(error("f(a) = a\nf(b) = b\ny = x"), Error::FunctionRedefined(_)));
+    }
+}
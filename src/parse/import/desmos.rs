@@ -0,0 +1,365 @@
+// Saved Desmos graph states, the JSON returned by `Calc.getState()`.
+//
+//     {"expressions": {"list": [
+//         {"type": "folder", "id": "1", "title": "Art"},
+//         {"type": "expression", "id": "2", "folderId": "1", "color": "#c74440", "latex": "y=\\sin\\left(x\\right)"},
+//         {"type": "expression", "id": "3", "latex": "a=2", "sliderBounds": {"min": "0", "max": "10", "step": "1"}}
+//     ]}}
+//
+// Each expression's `latex` is imported with `parse::import::latex`.
+// Text notes are skipped, tables and images are skipped with a warning.
+
+use std::collections::HashSet;
+use std::fmt;
+
+use serde::Deserialize;
+use loggerithm::{logger, log};
+use loggerithm::level::{TRACE, WARN};
+logger!(super);
+
+use super::node::{Node, NodeBase};
+use crate::parse::function;
+use super::latex;
+use super::text::ParseError;
+
+
+#[derive(Debug)]
+pub enum DesmosError {
+    Json       (serde_json::Error),
+    Expression {id : String, error : ParseError},
+    Colour     {id : String, colour : String}
+}
+impl fmt::Display for DesmosError {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match (self) {
+            DesmosError::Json       (error)         => write!(f, "invalid graph state: {}", error),
+            DesmosError::Expression {id, error}     => write!(f, "expression `{}`: {}", id, error),
+            DesmosError::Colour     {id, colour}    => write!(f, "expression `{}`: invalid colour `{}`", id, colour)
+        };
+    }
+}
+
+
+#[derive(Debug)]
+pub struct DesmosGraph {
+    // Corners of the saved view : Left, Bottom, Right, Top
+    pub viewport    : Option<[f64; 4]>,
+    pub folders     : Vec<DesmosFolder>,
+    pub expressions : Vec<DesmosExpression>
+}
+impl DesmosGraph {
+    pub fn folder(&self, id : &str) -> Option<&DesmosFolder> {
+        return self.folders.iter().find(|folder| folder.id == id);
+    }
+    // Whether an expression should be drawn, taking its folder into account.
+    pub fn is_visible(&self, expression : &DesmosExpression) -> bool {
+        if (expression.hidden) {
+            return false;
+        }
+        return match (&expression.folder_id) {
+            Some(id) => ! self.folder(id).is_some_and(|folder| folder.hidden),
+            None     => true
+        };
+    }
+    // Expressions to render, in graph order. Hidden expressions are kept
+    // only when they define a variable or function, as other expressions
+    // may depend on them.
+    pub fn into_drawn(self) -> Vec<DesmosExpression> {
+        let visible = self.expressions.iter()
+            .map(|expression| self.is_visible(expression) || expression.is_definition())
+            .collect::<Vec<bool>>();
+        return self.expressions.into_iter()
+            .zip(visible)
+            .filter(|(_, keep)| *keep)
+            .map(|(expression, _)| expression)
+            .collect();
+    }
+    // Nodes of the expressions `into_drawn` keeps.
+    pub fn into_nodes(self) -> Vec<Node> {
+        return self.into_drawn().into_iter().map(|expression| expression.node).collect();
+    }
+}
+
+
+#[derive(Debug)]
+pub struct DesmosFolder {
+    pub id        : String,
+    pub title     : String,
+    pub hidden    : bool,
+    pub collapsed : bool
+}
+
+
+#[derive(Debug)]
+pub struct DesmosExpression {
+    pub id        : String,
+    pub latex     : String,
+    pub node      : Node,
+    pub hidden    : bool,
+    // Red, Green, Blue
+    pub colour    : Option<[u8; 3]>,
+    pub folder_id : Option<String>,
+    pub slider    : Option<DesmosSlider>
+}
+impl DesmosExpression {
+    // `a = ...` for any variable other than the coordinates `x`, `y`, `r`
+    // and `θ`, or a function, `f(a, b) = ...`.
+    pub fn is_definition(&self) -> bool {
+        return match (&self.node.base) {
+            NodeBase::Equals(left, _) => {
+                matches!(&left.base, NodeBase::Variable(name) if (! matches!(name.as_str(), "x" | "y" | "r" | "θ" | "theta")))
+                    || function::definition(&self.node).is_some()
+            },
+            _ => false
+        };
+    }
+}
+
+
+// Slider bounds are kept as the LaTeX Desmos stored, as they may be expressions.
+#[derive(Debug)]
+pub struct DesmosSlider {
+    pub value : Option<f64>,
+    pub min   : Option<String>,
+    pub max   : Option<String>,
+    pub step  : Option<String>
+}
+
+
+pub fn parse(source : &str) -> Result<DesmosGraph, DesmosError> {
+    let state = match (serde_json::from_str::<RawState>(source)) {
+        Ok(state)  => state,
+        Err(error) => return Err(DesmosError::Json(error))
+    };
+
+    let mut graph = DesmosGraph {
+        viewport    : state.graph.and_then(|graph| graph.viewport).map(|viewport| [viewport.xmin, viewport.ymin, viewport.xmax, viewport.ymax]),
+        folders     : vec![],
+        expressions : vec![]
+    };
+    // Functions may be called before they are defined.
+    let functions = state.expressions.list.iter()
+        .filter(|item| item.kind == "expression")
+        .filter_map(|item| item.latex.as_deref().and_then(latex::defined_function))
+        .collect::<HashSet<String>>();
+    let lists = latex::defined_lists(state.expressions.list.iter()
+        .filter(|item| item.kind == "expression")
+        .filter_map(|item| item.latex.as_deref()));
+    for item in state.expressions.list {
+        match (item.kind.as_str()) {
+            "expression" => {
+                let latex = match (item.latex) {
+                    Some(latex) if (! latex.trim().is_empty()) => latex,
+                    _                                          => continue
+                };
+                let node = match (latex::parse_line(&latex, 1, &functions, &lists)) {
+                    Ok(node)   => *node,
+                    Err(error) => return Err(DesmosError::Expression {id: item.id, error})
+                };
+                let colour = match (item.color) {
+                    Some(colour) => match (parse_colour(&colour)) {
+                        Some(colour) => Some(colour),
+                        None         => return Err(DesmosError::Colour {id: item.id, colour})
+                    },
+                    None => None
+                };
+                let slider = item.slider_bounds.map(|bounds| DesmosSlider {
+                    value : slider_value(&node),
+                    min   : bounds.min.filter(|text| ! text.is_empty()),
+                    max   : bounds.max.filter(|text| ! text.is_empty()),
+                    step  : bounds.step.filter(|text| ! text.is_empty())
+                });
+                log!(TRACE, "Imported expression `{}` as `{}`.", item.id, node.to_string());
+                graph.expressions.push(DesmosExpression {
+                    id        : item.id,
+                    latex,
+                    node,
+                    hidden    : item.hidden,
+                    colour,
+                    folder_id : item.folder_id.filter(|id| ! id.is_empty()),
+                    slider
+                });
+            },
+            "folder" => {
+                graph.folders.push(DesmosFolder {
+                    id        : item.id,
+                    title     : item.title.unwrap_or_default(),
+                    hidden    : item.hidden,
+                    collapsed : item.collapsed
+                });
+            },
+            "text" => (),
+            kind   => {
+                log!(WARN, "Skipping unsupported {} `{}`.", kind, item.id);
+            }
+        };
+    }
+    return Ok(graph);
+}
+
+// `#rrggbb` or `#rgb`.
+fn parse_colour(text : &str) -> Option<[u8; 3]> {
+    let digits = text.strip_prefix('#')?;
+    if (! digits.chars().all(|ch| ch.is_ascii_hexdigit())) {
+        return None;
+    }
+    return match (digits.len()) {
+        6 => Some([
+            u8::from_str_radix(&digits[0..2], 16).ok()?,
+            u8::from_str_radix(&digits[2..4], 16).ok()?,
+            u8::from_str_radix(&digits[4..6], 16).ok()?
+        ]),
+        3 => Some([
+            u8::from_str_radix(&digits[0..1], 16).ok()? * 17,
+            u8::from_str_radix(&digits[1..2], 16).ok()? * 17,
+            u8::from_str_radix(&digits[2..3], 16).ok()? * 17
+        ]),
+        _ => None
+    };
+}
+
+// The number a slider is set to, from `a=2`.
+fn slider_value(node : &Node) -> Option<f64> {
+    return match (&node.base) {
+        NodeBase::Equals(_, right) => match (right.base) {
+            NodeBase::Number(value) => Some(value),
+            _                       => None
+        },
+        _ => None
+    };
+}
+
+
+#[derive(Deserialize)]
+struct RawState {
+    graph       : Option<RawGraph>,
+    expressions : RawExpressions
+}
+
+#[derive(Deserialize)]
+struct RawGraph {
+    viewport : Option<RawViewport>
+}
+
+#[derive(Deserialize)]
+struct RawViewport {
+    xmin : f64,
+    ymin : f64,
+    xmax : f64,
+    ymax : f64
+}
+
+#[derive(Deserialize)]
+struct RawExpressions {
+    list : Vec<RawItem>
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawItem {
+    #[serde(rename = "type", default = "default_kind")]
+    kind          : String,
+    id            : String,
+    latex         : Option<String>,
+    #[serde(default)]
+    hidden        : bool,
+    color         : Option<String>,
+    folder_id     : Option<String>,
+    slider_bounds : Option<RawSliderBounds>,
+    title         : Option<String>,
+    #[serde(default)]
+    collapsed     : bool
+}
+fn default_kind() -> String {
+    return String::from("expression");
+}
+
+#[derive(Deserialize)]
+struct RawSliderBounds {
+    min  : Option<String>,
+    max  : Option<String>,
+    step : Option<String>
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::import::{self, Format};
+
+    const STATE : &str = include_str!("../../../samples/desmos_state.json");
+
+    fn expression<'l>(graph : &'l DesmosGraph, id : &str) -> &'l DesmosExpression {
+        return graph.expressions.iter().find(|expression| expression.id == id).unwrap();
+    }
+
+    #[test]
+    fn reads_folders_and_skips_text_and_empty_expressions() {
+        let graph = parse(STATE).unwrap();
+        assert_eq!(graph.folders.iter().map(|folder| folder.id.as_str()).collect::<Vec<&str>>(), ["1", "5"]);
+        assert_eq!(graph.folder("1").unwrap().title, "Waves");
+        assert!(graph.folder("1").unwrap().collapsed);
+        assert!(! graph.folder("1").unwrap().hidden);
+        assert!(graph.folder("5").unwrap().hidden);
+        assert_eq!(graph.expressions.iter().map(|expression| expression.id.as_str()).collect::<Vec<&str>>(), ["2", "3", "4", "6"]);
+        assert_eq!(graph.viewport, Some([-10.0, -7.0, 10.0, 7.0]));
+    }
+
+    #[test]
+    fn visibility_follows_expressions_and_folders() {
+        let graph = parse(STATE).unwrap();
+        assert!(graph.is_visible(expression(&graph, "2")));
+        assert!(! graph.is_visible(expression(&graph, "3")));
+        assert!(graph.is_visible(expression(&graph, "4")));
+        // Hidden by its folder.
+        assert!(! expression(&graph, "6").hidden);
+        assert!(! graph.is_visible(expression(&graph, "6")));
+    }
+
+    #[test]
+    fn reads_colours_folders_and_sliders() {
+        let graph = parse(STATE).unwrap();
+        assert_eq!(expression(&graph, "2").colour, Some([0xc7, 0x44, 0x40]));
+        assert_eq!(expression(&graph, "3").colour, Some([0x2d, 0x70, 0xb3]));
+        assert_eq!(expression(&graph, "2").folder_id.as_deref(), Some("1"));
+        assert_eq!(expression(&graph, "4").folder_id, None);
+        assert_eq!(expression(&graph, "6").folder_id.as_deref(), Some("5"));
+        let slider = expression(&graph, "4").slider.as_ref().unwrap();
+        assert_eq!(slider.value, Some(2.0));
+        assert_eq!(slider.min.as_deref(), Some("0"));
+        assert_eq!(slider.max.as_deref(), Some("5"));
+        assert_eq!(slider.step.as_deref(), Some("0.5"));
+        assert!(expression(&graph, "2").slider.is_none());
+    }
+
+    #[test]
+    fn keeps_visible_expressions_and_definitions() {
+        let graph = parse(STATE).unwrap();
+        assert_eq!(graph.into_drawn().iter().map(|expression| expression.id.as_str()).collect::<Vec<&str>>(), ["2", "4"]);
+        let nodes = parse(STATE).unwrap().into_nodes();
+        assert_eq!(nodes.iter().map(|node| node.to_string()).collect::<Vec<String>>(), ["(y = (a * sin(x)))", "(a = 2)"]);
+    }
+
+    #[test]
+    fn import_keeps_colours_and_viewport() {
+        let graph = import::parse(STATE, Format::Desmos).unwrap();
+        assert_eq!(graph.nodes.len(), 2);
+        assert_eq!(graph.colours, [[0xc7, 0x44, 0x40, 255], [0x38, 0x8c, 0x46, 255]]);
+        assert_eq!(graph.frame, Some([-10.0, -7.0, 10.0, 7.0]));
+    }
+
+    #[test]
+    fn parses_colours() {
+        assert_eq!(parse_colour("#c74440"), Some([0xc7, 0x44, 0x40]));
+        assert_eq!(parse_colour("#0f8"), Some([0x00, 0xff, 0x88]));
+        assert_eq!(parse_colour("c74440"), None);
+        assert_eq!(parse_colour("#c7444"), None);
+        assert_eq!(parse_colour("#gg0000"), None);
+    }
+
+    #[test]
+    fn reports_invalid_colours() {
+        let source = r##"{"expressions": {"list": [{"id": "1", "color": "red", "latex": "y=x"}]}}"##;
+        assert!(matches!(parse(source), Err(DesmosError::Colour {id, colour}) if (id == "1" && colour == "red")));
+    }
+}
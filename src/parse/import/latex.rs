@@ -0,0 +1,1048 @@
+// Desmos flavoured LaTeX.
+//
+// One expression per line, `%` starts a comment.
+//
+//     y=\sin\left(x\right)
+//     y=\frac{1}{2}\sqrt[3]{x}+\operatorname{sech}\left(x\right)
+//     x^{2}+y^{2}\le4
+//     f_{1}\left(a,b\right)=ab+1
+//     \left(\cos t,\sin\left(2t\right)\right)
+//     y=\left\{x<0:-x,x\right\}
+//     y=x^{2}\left\{0<x<2\right\}
+//     y=\left[1...5\right]x
+//     y=\left[kx^{2}\operatorname{for}k=\left[0,0.5,...,5\right]\right]
+//     y=a\left[2\right]+\operatorname{length}\left(a\right)
+//     y=\operatorname{mean}\left(a\right)+\max\left(a\right)x
+//
+// Letters are single character variables, optionally with a subscript
+// (`a_{1}` is the variable `a_1`), so `xy` is `x * y`. As in Desmos, a
+// letter followed by parentheses is a call if the file defines a function
+// of that name. Anything this importer does not understand is reported,
+// never skipped.
+//
+// Piecewise expressions and their conditions read as in `text`, with
+// `\land` (`\wedge`), `\lor` (`\vee`) and `\lnot` (`\neg`) for `and`,
+// `or` and `not`.
+//
+// Lists read as in `text`, with `\ldots`, `\dots` or `\cdots` also
+// standing for `...`, and `\operatorname{for}` for `for`.
+
+use std::collections::HashSet;
+
+use loggerithm::{logger, log};
+use loggerithm::level::TRACE;
+logger!(super);
+
+use super::node::{Node, NodeBase, Relation};
+use super::text::{self, ParseError, BinaryOperator, PRECEDENCE_ADDITION, PRECEDENCE_MULTIPLICATION};
+use crate::parse::list;
+
+
+// Parse every expression in a file.
+pub fn parse(source : &str) -> Result<Vec<Node>, ParseError> {
+    let lines = source.lines().enumerate()
+        .map(|(i, line)| (i, strip_comment(line)))
+        .filter(|(_, line)| ! line.trim().is_empty())
+        .collect::<Vec<(usize, &str)>>();
+    let functions = lines.iter()
+        .filter_map(|(_, line)| defined_function(line))
+        .collect::<HashSet<String>>();
+    let lists = defined_lists(lines.iter().map(|(_, line)| *line));
+    let mut equations = vec![];
+    for (i, line) in lines {
+        let equation = parse_line(line, i + 1, &functions, &lists)?;
+        log!(TRACE, "Parsed line {} as `{}`.", i + 1, equation.to_string());
+        equations.push(*equation);
+    }
+    return Ok(equations);
+}
+
+// Parse a single LaTeX equation or expression, with the names of the
+// user defined functions it may call and of the lists it may index.
+pub fn parse_line(line : &str, line_number : usize, functions : &HashSet<String>, lists : &HashSet<String>) -> Result<Box<Node>, ParseError> {
+    let tokens = tokenize(line, line_number)?;
+    let mut parser = Parser::new(tokens, line_number, functions, lists);
+    return parser.parse_relation();
+}
+
+// The name of the function a line defines, as in `f\left(a,b\right)=...`.
+// Lines that fail to parse are reported when parsed for real.
+pub fn defined_function(line : &str) -> Option<String> {
+    let tokens = tokenize(line, 0).ok()?;
+    return Parser::new(tokens, 0, &HashSet::new(), &HashSet::new()).defined_function();
+}
+
+// Names of the lists the lines define, as in `a=\left[1...5\right]`. A list
+// may be defined from another, so look until no more are found.
+pub fn defined_lists<'l>(lines : impl Iterator<Item = &'l str> + Clone) -> HashSet<String> {
+    let mut lists = HashSet::new();
+    loop {
+        let found = lines.clone()
+            .filter_map(|line| {
+                let tokens = tokenize(line, 0).ok()?;
+                return Parser::new(tokens, 0, &HashSet::new(), &lists).defined_list();
+            })
+            .collect::<HashSet<String>>();
+        if (found.len() == lists.len()) {
+            return lists;
+        }
+        lists = found;
+    }
+}
+
+// Remove a `%` comment, leaving escaped `\%` alone.
+fn strip_comment(line : &str) -> &str {
+    let mut previous = ' ';
+    for (index, ch) in line.char_indices() {
+        if (ch == '%' && previous != '\\') {
+            return &line[..index];
+        }
+        previous = ch;
+    }
+    return line;
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+enum TokenKind {
+    Number  (String), // Kept as text so `x^23` can take only the `2`.
+    Letter  (char),
+    Command (String), // Without the backslash.
+    Symbol  (char),
+    End
+}
+impl TokenKind {
+    fn describe(&self) -> String {
+        return match (self) {
+            TokenKind::Number  (text) => format!("number `{}`", text),
+            TokenKind::Letter  (ch)   => format!("letter `{}`", ch),
+            TokenKind::Command (name) => format!("`\\{}`", name),
+            TokenKind::Symbol  (ch)   => format!("`{}`", ch),
+            TokenKind::End            => String::from("end of expression")
+        };
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    kind   : TokenKind,
+    column : usize
+}
+
+
+fn tokenize(line : &str, line_number : usize) -> Result<Vec<Token>, ParseError> {
+    let     chars  = line.chars().collect::<Vec<char>>();
+    let mut tokens = vec![];
+    let mut i      = 0;
+    while (i < chars.len()) {
+        let ch     = chars[i];
+        let column = i + 1;
+        if (ch.is_whitespace()) {
+            i += 1;
+            continue;
+        }
+
+        if (ch == '\\') {
+            i += 1;
+            let start = i;
+            while (i < chars.len() && chars[i].is_ascii_alphabetic()) {
+                i += 1;
+            }
+            if (i == start) {
+                if (i >= chars.len()) {
+                    return Err(ParseError::new(line_number, column, String::from("Expected a command after `\\`.")));
+                }
+                i += 1;
+            }
+            let name = chars[start..i].iter().collect::<String>();
+            match (name.as_str()) {
+                // Spacing and delimiter sizing carry no meaning.
+                "," | ";" | ":" | "!" | " " | "quad" | "qquad" |
+                "left" | "right" | "big" | "Big" | "bigg" | "Bigg" => (),
+                "ldots" | "dots" | "cdots" => tokens.push(Token {kind: TokenKind::Symbol('…'), column}),
+                // The keyword of a comprehension, not a function.
+                "operatorname" if (chars[i..].starts_with(&['{', 'f', 'o', 'r', '}'])) => {
+                    tokens.push(Token {kind: TokenKind::Command(String::from("for")), column});
+                    i += 5;
+                },
+                _ => tokens.push(Token {kind: TokenKind::Command(name), column})
+            };
+            continue;
+        }
+
+        if (chars[i..].starts_with(&['.', '.', '.'])) {
+            tokens.push(Token {kind: TokenKind::Symbol('…'), column});
+            i += 3;
+            continue;
+        }
+
+        if (ch.is_ascii_digit() || (ch == '.' && chars.get(i + 1).is_some_and(|c| c.is_ascii_digit()))) {
+            let start = i;
+            while (i < chars.len() && chars[i].is_ascii_digit()) {
+                i += 1;
+            }
+            // A `.` starting an ellipsis, as in `1...5`, ends the number.
+            if (i < chars.len() && chars[i] == '.' && chars.get(i + 1) != Some(&'.')) {
+                i += 1;
+                while (i < chars.len() && chars[i].is_ascii_digit()) {
+                    i += 1;
+                }
+            }
+            tokens.push(Token {kind: TokenKind::Number(chars[start..i].iter().collect()), column});
+            continue;
+        }
+
+        if (ch.is_alphabetic()) {
+            tokens.push(Token {kind: TokenKind::Letter(ch), column});
+        } else {
+            tokens.push(Token {kind: TokenKind::Symbol(ch), column});
+        }
+        i += 1;
+    }
+    tokens.push(Token {kind: TokenKind::End, column: chars.len() + 1});
+    return Ok(tokens);
+}
+
+
+// Text importer name of a LaTeX function, from either `\name` or `\operatorname{name}`.
+fn function_name(name : &str) -> Option<&'static str> {
+    return Some(match (name) {
+        "sin"      => "sin",
+        "cos"      => "cos",
+        "tan"      => "tan",
+        "csc"      => "csc",
+        "sec"      => "sec",
+        "cot"      => "cot",
+        "arcsin"   => "asin",
+        "arccos"   => "acos",
+        "arctan"   => "atan",
+        "arccsc"   => "acsc",
+        "arcsec"   => "asec",
+        "arccot"   => "acot",
+        "sinh"     => "sinh",
+        "cosh"     => "cosh",
+        "tanh"     => "tanh",
+        "csch"     => "csch",
+        "sech"     => "sech",
+        "coth"     => "coth",
+        "exp"      => "exp",
+        "ln"       => "ln",
+        "log"      => "log",
+        "mod"      => "mod",
+        "abs"      => "abs",
+        "sign"     => "sign",
+        "floor"    => "floor",
+        "ceil"     => "ceil",
+        "round"    => "round",
+        "length"   => "length",
+        "total"    => "total",
+        "join"     => "join",
+        "sort"     => "sort",
+        "unique"   => "unique",
+        "shuffle"  => "shuffle",
+        "mean"     => "mean",
+        "median"   => "median",
+        "min"      => "min",
+        "max"      => "max",
+        "quantile" => "quantile",
+        "quartile" => "quartile",
+        "var"      => "var",
+        "stdev"    => "stdev",
+        "stdevp"   => "stdevp",
+        "mad"      => "mad",
+        "cov"      => "cov",
+        "covp"     => "covp",
+        "corr"     => "corr",
+        "spearman" => "spearman",
+        "stats"    => "stats",
+        _          => return None
+    });
+}
+
+// Inverse of a trigonometric function, for `\sin^{-1}`.
+fn inverse_function_name(name : &str) -> Option<&'static str> {
+    return Some(match (name) {
+        "sin" => "asin",
+        "cos" => "acos",
+        "tan" => "atan",
+        "csc" => "acsc",
+        "sec" => "asec",
+        "cot" => "acot",
+        _     => return None
+    });
+}
+
+// Greek letters and other named variables.
+fn is_named_variable(name : &str) -> bool {
+    return matches!(name,
+        "alpha" | "beta" | "gamma" | "delta" | "epsilon" | "zeta" | "eta" | "theta" |
+        "iota" | "kappa" | "lambda" | "mu" | "nu" | "xi" | "pi" | "rho" | "sigma" |
+        "tau" | "upsilon" | "phi" | "chi" | "psi" | "omega" |
+        "varepsilon" | "vartheta" | "varphi" | "varrho" | "varsigma" |
+        "Gamma" | "Delta" | "Theta" | "Lambda" | "Xi" | "Pi" | "Sigma" | "Upsilon" | "Phi" | "Psi" | "Omega"
+    );
+}
+
+// Explain why a command that no rule accepted is rejected.
+fn unsupported_command(name : &str) -> String {
+    return match (name) {
+        "ne" | "neq"
+            => format!("`\\{}` is not supported, only `<`, `\\le`, `>` and `\\ge`.", name),
+        "le" | "ge" | "lt" | "gt" | "leq" | "geq"
+            => String::from("Inequalities can only compare whole expressions."),
+        "}"
+            => String::from("`\\}` without a matching `\\{`."),
+        "land" | "wedge" | "lor" | "vee" | "lnot" | "neg"
+            => String::from("Conditions can only appear in piecewise expressions."),
+        "for"
+            => String::from("`\\operatorname{for}` can only follow the body of a list comprehension."),
+        "sum" | "prod" | "int"
+            => format!("`\\{}` is not supported.", name),
+        "infty"
+            => String::from("Infinity is not supported."),
+        _   => format!("Unsupported LaTeX command `\\{}`.", name)
+    };
+}
+
+
+struct Parser<'l> {
+    tokens        : Vec<Token>,
+    index         : usize,
+    line_number   : usize,
+    // Number of `|` currently open, so a `|` in operator position closes instead of multiplying.
+    abs_depth     : usize,
+    // Parsing the unbracketed argument of `\sin x`, which stops at the next function.
+    bare_argument : bool,
+    // Names of user defined functions.
+    functions     : &'l HashSet<String>,
+    // Names of the variables defined as lists.
+    lists         : &'l HashSet<String>
+}
+impl<'l> Parser<'l> {
+    fn new(tokens : Vec<Token>, line_number : usize, functions : &'l HashSet<String>, lists : &'l HashSet<String>) -> Parser<'l> {
+        return Parser {
+            tokens,
+            index         : 0,
+            line_number,
+            abs_depth     : 0,
+            bare_argument : false,
+            functions,
+            lists
+        };
+    }
+
+    fn peek(&self) -> &Token {
+        return &self.tokens[self.index];
+    }
+    fn advance(&mut self) -> Token {
+        let token = self.tokens[self.index].clone();
+        if (self.index < self.tokens.len() - 1) {
+            self.index += 1;
+        }
+        return token;
+    }
+    fn error_at<T>(&self, column : usize, message : String) -> Result<T, ParseError> {
+        return Err(ParseError::new(self.line_number, column, message));
+    }
+    fn error<T>(&self, message : String) -> Result<T, ParseError> {
+        return self.error_at(self.peek().column, message);
+    }
+    fn is_symbol(&self, symbol : char) -> bool {
+        return self.peek().kind == TokenKind::Symbol(symbol);
+    }
+    fn expect_symbol(&mut self, symbol : char) -> Result<Token, ParseError> {
+        if (self.is_symbol(symbol)) {
+            return Ok(self.advance());
+        }
+        return self.error(format!("Expected `{}`, found {}.", symbol, self.peek().kind.describe()));
+    }
+    // Like `expect_symbol`, but explains unsupported syntax found in place of the bracket.
+    fn expect_close(&mut self, close : char) -> Result<Token, ParseError> {
+        if (matches!(self.peek().kind, TokenKind::Command(_)) || self.is_symbol('<') || self.is_symbol('>') || self.is_symbol('!')) {
+            return self.unexpected();
+        }
+        return self.expect_symbol(close);
+    }
+    fn unexpected<T>(&self) -> Result<T, ParseError> {
+        let token = self.peek();
+        return match (&token.kind) {
+            TokenKind::Command(name)                     => self.error(unsupported_command(name)),
+            TokenKind::Symbol('<') | TokenKind::Symbol('>') => self.error(String::from("Inequalities can only compare whole expressions.")),
+            TokenKind::Symbol('!')                       => self.error(String::from("Factorials are not supported.")),
+            TokenKind::Symbol('\'')                      => self.error(String::from("Derivatives are not supported.")),
+            kind                                         => self.error(format!("Unexpected {}.", kind.describe()))
+        };
+    }
+
+    // relation := expression (('=' | '<' | '\le' | '>' | '\ge') expression)?
+    // letter subscript? '(' name (',' name)* ')' '='
+    fn defined_function(&mut self) -> Option<String> {
+        if (! matches!(self.peek().kind, TokenKind::Letter(_))) {
+            return None;
+        }
+        let name = match (self.parse_operand().ok()?.base) {
+            NodeBase::Variable(name) => name,
+            _                        => return None
+        };
+        if (! self.is_symbol('(')) {
+            return None;
+        }
+        let parameters = self.parse_arguments('(', ')').ok()?;
+        let is_definition = self.is_symbol('=')
+            && parameters.iter().all(|parameter| matches!(parameter.base, NodeBase::Variable(_)));
+        return if (is_definition) {Some(name)} else {None};
+    }
+
+    // letter subscript? '=' expression, where the expression is a list.
+    fn defined_list(&mut self) -> Option<String> {
+        if (! matches!(self.peek().kind, TokenKind::Letter(_))) {
+            return None;
+        }
+        let name = match (self.parse_operand().ok()?.base) {
+            NodeBase::Variable(name) => name,
+            _                        => return None
+        };
+        self.expect_symbol('=').ok()?;
+        let value   = self.parse_expression(PRECEDENCE_ADDITION).ok()?;
+        let is_list = self.peek().kind == TokenKind::End && list::is_list(&value, &|name| self.lists.contains(name));
+        return if (is_list) {Some(name)} else {None};
+    }
+
+    fn parse_relation(&mut self) -> Result<Box<Node>, ParseError> {
+        let left = self.parse_expression(PRECEDENCE_ADDITION)?;
+        let node = match (self.parse_relation_symbol()?) {
+            Some(relation) => {
+                let right = self.parse_expression(PRECEDENCE_ADDITION)?;
+                text::build_relation(relation, left, right)
+            },
+            None => left
+        };
+        if (self.peek().kind != TokenKind::End) {
+            return self.unexpected();
+        }
+        return Ok(node);
+    }
+
+    // The relation at the current token, consumed if there is one.
+    fn parse_relation_symbol(&mut self) -> Result<Option<Relation>, ParseError> {
+        let relation = match (&self.peek().kind) {
+            TokenKind::Symbol('=') => Some(Relation::Equal),
+            TokenKind::Symbol('<') => Some(Relation::Less),
+            TokenKind::Symbol('>') => Some(Relation::Greater),
+            TokenKind::Command(name) => match (name.as_str()) {
+                "lt"         => Some(Relation::Less),
+                "le" | "leq" => Some(Relation::LessEqual),
+                "gt"         => Some(Relation::Greater),
+                "ge" | "geq" => Some(Relation::GreaterEqual),
+                _            => None
+            },
+            _ => None
+        };
+        let Some(mut relation) = relation else {
+            return Ok(None);
+        };
+        self.advance();
+        // `<=` and `>=` written out.
+        if (self.is_symbol('=')) {
+            relation = match (relation) {
+                Relation::Less    => Relation::LessEqual,
+                Relation::Greater => Relation::GreaterEqual,
+                _                 => return self.unexpected()
+            };
+            self.advance();
+        }
+        return Ok(Some(relation));
+    }
+
+    fn parse_expression(&mut self, min_precedence : u8) -> Result<Box<Node>, ParseError> {
+        let mut left = self.parse_prefix()?;
+        while let Some((operator, implicit)) = self.peek_operator() {
+            if (operator.precedence() < min_precedence) {
+                break;
+            }
+            if (! implicit) {
+                self.advance();
+            }
+            let right = self.parse_expression(operator.precedence() + 1)?;
+            left = operator.build(left, right);
+        }
+        return Ok(left);
+    }
+
+    // The operator at the current token, and whether it is an implicit multiplication.
+    // Powers are handled by `parse_power`, as LaTeX scripts are not ordinary operands.
+    fn peek_operator(&self) -> Option<(BinaryOperator, bool)> {
+        return match (&self.peek().kind) {
+            TokenKind::Symbol('+') => Some((BinaryOperator::Addition,       false)),
+            TokenKind::Symbol('-') => Some((BinaryOperator::Subtraction,    false)),
+            TokenKind::Symbol('*') => Some((BinaryOperator::Multiplication, false)),
+            TokenKind::Symbol('/') => Some((BinaryOperator::Division,       false)),
+            TokenKind::Command(name) if (name == "cdot" || name == "times") => Some((BinaryOperator::Multiplication, false)),
+            TokenKind::Command(name) if (name == "div")                     => Some((BinaryOperator::Division,       false)),
+            TokenKind::Command(name) => {
+                let is_function = name == "operatorname" || function_name(name).is_some();
+                let starts      = is_function || name == "frac" || name == "sqrt" || name == "{" || is_named_variable(name);
+                if (starts && ! (self.bare_argument && is_function)) {
+                    Some((BinaryOperator::Multiplication, true))
+                } else {
+                    None
+                }
+            },
+            TokenKind::Number(_) | TokenKind::Letter(_) |
+            TokenKind::Symbol('(') | TokenKind::Symbol('[') | TokenKind::Symbol('{')
+                => Some((BinaryOperator::Multiplication, true)),
+            TokenKind::Symbol('|') if (self.abs_depth == 0)
+                => Some((BinaryOperator::Multiplication, true)),
+            _ => None
+        };
+    }
+
+    fn parse_prefix(&mut self) -> Result<Box<Node>, ParseError> {
+        if (self.is_symbol('-')) {
+            self.advance();
+            let operand = self.parse_power()?;
+            return Ok(match (operand.base) {
+                NodeBase::Number(value) => Node::new(NodeBase::Number(-value)),
+                _ => Node::new(NodeBase::Multiplication(Node::new(NodeBase::Number(-1.0)), operand))
+            });
+        }
+        if (self.is_symbol('+')) {
+            self.advance();
+            return self.parse_power();
+        }
+        return self.parse_power();
+    }
+
+    // primary ('^' script)*
+    fn parse_power(&mut self) -> Result<Box<Node>, ParseError> {
+        let mut base = self.parse_primary()?;
+        while (self.is_symbol('^')) {
+            self.advance();
+            let degree = self.parse_script()?;
+            base = Node::new(NodeBase::Power(base, degree));
+        }
+        return Ok(base);
+    }
+
+    // The argument of `^` or `_`: a braced group or a single character.
+    fn parse_script(&mut self) -> Result<Box<Node>, ParseError> {
+        let token = self.peek().clone();
+        return match (token.kind) {
+            TokenKind::Symbol('{') => self.parse_group(),
+            TokenKind::Number(text) => {
+                let mut chars = text.chars();
+                let     first = chars.next().unwrap();
+                let     rest  = chars.collect::<String>();
+                if (first == '.') {
+                    return self.error(format!("Expected a braced exponent, found number `{}`.", text));
+                }
+                if (rest.is_empty()) {
+                    self.advance();
+                } else {
+                    self.tokens[self.index].kind = TokenKind::Number(rest);
+                    self.tokens[self.index].column += 1;
+                }
+                Ok(Node::new(NodeBase::Number(first.to_digit(10).unwrap() as f64)))
+            },
+            TokenKind::Letter(_) | TokenKind::Command(_) => self.parse_primary(),
+            _ => self.error(format!("Expected an exponent, found {}.", token.kind.describe()))
+        };
+    }
+
+    // '{' expression '}'
+    fn parse_group(&mut self) -> Result<Box<Node>, ParseError> {
+        self.expect_symbol('{')?;
+        let (abs_depth, bare_argument) = (self.abs_depth, self.bare_argument);
+        self.abs_depth     = 0;
+        self.bare_argument = false;
+        let node = self.parse_expression(PRECEDENCE_ADDITION)?;
+        self.expect_close('}')?;
+        self.abs_depth     = abs_depth;
+        self.bare_argument = bare_argument;
+        return Ok(node);
+    }
+
+    // Comma separated expressions between `open` and `close`.
+    #[allow(clippy::vec_box)]
+    fn parse_arguments(&mut self, open : char, close : char) -> Result<Vec<Box<Node>>, ParseError> {
+        self.expect_symbol(open)?;
+        let (abs_depth, bare_argument) = (self.abs_depth, self.bare_argument);
+        self.abs_depth     = 0;
+        self.bare_argument = false;
+        let mut arguments = vec![];
+        if (! self.is_symbol(close)) {
+            loop {
+                arguments.push(self.parse_expression(PRECEDENCE_ADDITION)?);
+                if (self.is_symbol(',')) {
+                    self.advance();
+                } else {
+                    break;
+                }
+            }
+        }
+        self.expect_close(close)?;
+        self.abs_depth     = abs_depth;
+        self.bare_argument = bare_argument;
+        return Ok(arguments);
+    }
+
+    // primary := operand ('[' expression ']')*, where the operand is a list
+    fn parse_primary(&mut self) -> Result<Box<Node>, ParseError> {
+        let mut node = self.parse_operand()?;
+        while (self.is_symbol('[') && list::is_list(&node, &|name| self.lists.contains(name))) {
+            let column = self.peek().column;
+            let mut arguments = self.parse_arguments('[', ']')?;
+            if (arguments.len() != 1) {
+                return self.error_at(column, format!("Expected one index between brackets, found {}.", arguments.len()));
+            }
+            node = Node::new(NodeBase::Index(node, arguments.remove(0)));
+        }
+        return Ok(node);
+    }
+
+    fn parse_operand(&mut self) -> Result<Box<Node>, ParseError> {
+        let token = self.peek().clone();
+        return match (token.kind) {
+            TokenKind::Number(text) => {
+                self.advance();
+                match (text.parse::<f64>()) {
+                    Ok(value) => Ok(Node::new(NodeBase::Number(value))),
+                    Err(_)    => self.error_at(token.column, format!("Invalid number `{}`.", text))
+                }
+            },
+            TokenKind::Letter(letter) => {
+                self.advance();
+                let mut name = letter.to_string();
+                if (self.is_symbol('_')) {
+                    self.advance();
+                    name = format!("{}_{}", name, self.parse_subscript()?);
+                }
+                if (self.functions.contains(&name) && self.is_symbol('(')) {
+                    let arguments = self.parse_arguments('(', ')')?;
+                    return Ok(Node::new(NodeBase::Call(name, arguments)));
+                }
+                Ok(Node::new(NodeBase::Variable(name)))
+            },
+            TokenKind::Symbol('(') => {
+                let mut arguments = self.parse_arguments('(', ')')?;
+                match (arguments.len()) {
+                    1 => Ok(arguments.remove(0)),
+                    2 => {
+                        let y = arguments.remove(1);
+                        Ok(Node::new(NodeBase::Point(arguments.remove(0), y)))
+                    },
+                    _ => self.error_at(token.column, String::from("Points must have two coordinates."))
+                }
+            },
+            TokenKind::Symbol('[') => self.parse_list(),
+            TokenKind::Symbol('{') => self.parse_group(),
+            TokenKind::Symbol('|') => {
+                self.advance();
+                self.abs_depth += 1;
+                let bare_argument = self.bare_argument;
+                self.bare_argument = false;
+                let argument = self.parse_expression(PRECEDENCE_ADDITION)?;
+                self.expect_symbol('|')?;
+                self.abs_depth    -= 1;
+                self.bare_argument = bare_argument;
+                Ok(Node::new(NodeBase::AbsoluteValue(argument)))
+            },
+            TokenKind::Command(name) => self.parse_command(&name, token.column),
+            _ => self.unexpected()
+        };
+    }
+
+    // '[' ']' | '[' expression '\operatorname{for}' name '=' expression (',' name '=' expression)* ']'
+    //     | '[' expression (',' expression)* ']', with '...' as in `text::Parser::parse_list`.
+    fn parse_list(&mut self) -> Result<Box<Node>, ParseError> {
+        let start = self.peek().column;
+        self.expect_symbol('[')?;
+        let (abs_depth, bare_argument) = (self.abs_depth, self.bare_argument);
+        self.abs_depth     = 0;
+        self.bare_argument = false;
+        let mut values   = vec![];
+        // Number of elements before the `...`.
+        let mut ellipsis = None;
+        while (! self.is_symbol(']')) {
+            if (self.is_symbol('…')) {
+                if (ellipsis.is_some() || values.is_empty()) {
+                    return self.error(String::from("Expected a range such as `[1...10]` or `[0, 0.5, ..., 5]`."));
+                }
+                ellipsis = Some(values.len());
+                self.advance();
+                if (self.is_symbol(',')) {
+                    self.advance();
+                }
+                continue;
+            }
+            values.push(self.parse_expression(PRECEDENCE_ADDITION)?);
+            if (values.len() == 1 && ellipsis.is_none() && self.is_command(&["for"])) {
+                let node = self.parse_comprehension(values.remove(0))?;
+                self.abs_depth     = abs_depth;
+                self.bare_argument = bare_argument;
+                return Ok(node);
+            }
+            if (self.is_symbol(',')) {
+                self.advance();
+                if (self.is_symbol(']')) {
+                    return self.error(String::from("Expected an expression, found `]`."));
+                }
+            } else if (! self.is_symbol('…')) {
+                break;
+            }
+        }
+        self.expect_close(']')?;
+        self.abs_depth     = abs_depth;
+        self.bare_argument = bare_argument;
+        return match (ellipsis) {
+            None => Ok(Node::new(NodeBase::MultiValue(values))),
+            Some(before) if (values.len() == before + 1 && (before == 1 || before == 2)) => {
+                let last   = values.pop().unwrap();
+                let second = if (before == 2) {values.pop()} else {None};
+                Ok(Node::new(NodeBase::Range(values.pop().unwrap(), second, last)))
+            },
+            Some(_) => self.error_at(start, String::from("Expected a range such as `[1...10]` or `[0, 0.5, ..., 5]`."))
+        };
+    }
+
+    // The rest of a comprehension after its body, up to the closing `]`.
+    // The first variable is outermost.
+    fn parse_comprehension(&mut self, body : Box<Node>) -> Result<Box<Node>, ParseError> {
+        self.advance();
+        let mut bindings = vec![];
+        loop {
+            if (! matches!(self.peek().kind, TokenKind::Letter(_))) {
+                return self.error(format!("Expected a variable name after `\\operatorname{{for}}`, found {}.", self.peek().kind.describe()));
+            }
+            let name = match (self.parse_operand()?.base) {
+                NodeBase::Variable(name) => name,
+                _                        => return self.error(String::from("Expected `=` after the variable of a comprehension."))
+            };
+            self.expect_symbol('=')?;
+            bindings.push((name, self.parse_expression(PRECEDENCE_ADDITION)?));
+            if (self.is_symbol(',')) {
+                self.advance();
+            } else {
+                break;
+            }
+        }
+        self.expect_close(']')?;
+        let mut node = body;
+        for (name, list) in bindings.into_iter().rev() {
+            node = Node::new(NodeBase::For(node, name, list));
+        }
+        return Ok(node);
+    }
+
+    // '\{' (branch (',' branch)* (',' expression)? | expression) '\}', as `text::Parser::parse_piecewise`.
+    fn parse_piecewise(&mut self) -> Result<Box<Node>, ParseError> {
+        self.advance();
+        let (abs_depth, bare_argument) = (self.abs_depth, self.bare_argument);
+        self.abs_depth     = 0;
+        self.bare_argument = false;
+        let mut branches  = vec![];
+        let mut otherwise = None;
+        loop {
+            let column = self.peek().column;
+            let item   = self.parse_or()?;
+            if (item.base.is_condition()) {
+                let value = if (self.is_symbol(':')) {
+                    self.advance();
+                    self.parse_expression(PRECEDENCE_ADDITION)?
+                } else {
+                    Node::new(NodeBase::Number(1.0))
+                };
+                branches.push((item, value));
+            } else if (self.is_symbol(':')) {
+                return self.error_at(column, String::from("Expected a condition before `:`."));
+            } else {
+                otherwise = Some(item);
+                break;
+            }
+            if (self.is_symbol(',')) {
+                self.advance();
+            } else {
+                break;
+            }
+        }
+        if (otherwise.is_some() && self.is_symbol(',')) {
+            return self.error(String::from("Only the last part of a piecewise expression may be without a condition."));
+        }
+        if (self.peek().kind != TokenKind::Command(String::from("}"))) {
+            return self.unexpected();
+        }
+        self.advance();
+        self.abs_depth     = abs_depth;
+        self.bare_argument = bare_argument;
+        return Ok(Node::new(NodeBase::Piecewise(branches, otherwise)));
+    }
+
+    // or := and (('\lor' | '\vee') and)*
+    fn parse_or(&mut self) -> Result<Box<Node>, ParseError> {
+        let mut left = self.parse_and()?;
+        while (self.is_command(&["lor", "vee"])) {
+            self.advance();
+            let right = self.parse_and()?;
+            left = self.join(left, right, NodeBase::Or)?;
+        }
+        return Ok(left);
+    }
+
+    // and := not (('\land' | '\wedge') not)*
+    fn parse_and(&mut self) -> Result<Box<Node>, ParseError> {
+        let mut left = self.parse_not()?;
+        while (self.is_command(&["land", "wedge"])) {
+            self.advance();
+            let right = self.parse_not()?;
+            left = self.join(left, right, NodeBase::And)?;
+        }
+        return Ok(left);
+    }
+
+    // not := ('\lnot' | '\neg') not | comparison
+    fn parse_not(&mut self) -> Result<Box<Node>, ParseError> {
+        if (self.is_command(&["lnot", "neg"])) {
+            self.advance();
+            let column = self.peek().column;
+            let arg    = self.parse_not()?;
+            if (! arg.base.is_condition()) {
+                return self.error_at(column, String::from("Expected a condition after `\\lnot`."));
+            }
+            return Ok(Node::new(NodeBase::Not(arg)));
+        }
+        return self.parse_comparison();
+    }
+
+    // comparison := '(' or ')' | expression (relation expression)*
+    fn parse_comparison(&mut self) -> Result<Box<Node>, ParseError> {
+        // A condition in parentheses, or else an expression starting with one.
+        if (self.is_symbol('(')) {
+            let (index, abs_depth) = (self.index, self.abs_depth);
+            self.advance();
+            self.abs_depth = 0;
+            if let Ok(condition) = self.parse_or() {
+                if (condition.base.is_condition() && self.is_symbol(')')) {
+                    self.advance();
+                    self.abs_depth = abs_depth;
+                    return Ok(condition);
+                }
+            }
+            self.index     = index;
+            self.abs_depth = abs_depth;
+        }
+        let mut left      = self.parse_expression(PRECEDENCE_ADDITION)?;
+        let mut condition = None;
+        while let Some(relation) = self.parse_relation_symbol()? {
+            let right   = self.parse_expression(PRECEDENCE_ADDITION)?;
+            let compare = Node::new(NodeBase::Compare(relation, left, right.clone()));
+            condition = Some(match (condition) {
+                Some(condition) => Node::new(NodeBase::And(condition, compare)),
+                None            => compare
+            });
+            left = right;
+        }
+        return Ok(condition.unwrap_or(left));
+    }
+
+    fn is_command(&self, names : &[&str]) -> bool {
+        return matches!(&self.peek().kind, TokenKind::Command(name) if (names.contains(&name.as_str())));
+    }
+
+    // Two conditions joined by `\land` or `\lor`.
+    fn join(&self, left : Box<Node>, right : Box<Node>, base : fn(Box<Node>, Box<Node>) -> NodeBase) -> Result<Box<Node>, ParseError> {
+        if (! left.base.is_condition() || ! right.base.is_condition()) {
+            return self.error(String::from("Expected conditions on both sides of `\\land` and `\\lor`."));
+        }
+        return Ok(Node::new(base(left, right)));
+    }
+
+    // Letters and digits of a variable subscript, `_{12}` or `_1`.
+    fn parse_subscript(&mut self) -> Result<String, ParseError> {
+        let single = ! self.is_symbol('{');
+        if (! single) {
+            self.advance();
+        }
+        let mut subscript = String::new();
+        loop {
+            let token = self.peek().clone();
+            match (token.kind) {
+                TokenKind::Letter(ch) => {
+                    self.advance();
+                    subscript.push(ch);
+                },
+                TokenKind::Number(text) if (! text.contains('.')) => {
+                    if (single && text.len() > 1) {
+                        subscript.push_str(&text[..1]);
+                        self.tokens[self.index].kind = TokenKind::Number(text[1..].to_string());
+                        self.tokens[self.index].column += 1;
+                    } else {
+                        self.advance();
+                        subscript.push_str(&text);
+                    }
+                },
+                TokenKind::Symbol('}') if (! single) => {
+                    self.advance();
+                    break;
+                },
+                kind => return self.error(format!("Expected a letter or digit in subscript, found {}.", kind.describe()))
+            };
+            if (single) {
+                break;
+            }
+        }
+        if (subscript.is_empty()) {
+            return self.error(String::from("Empty subscript."));
+        }
+        return Ok(subscript);
+    }
+
+    fn parse_command(&mut self, name : &str, column : usize) -> Result<Box<Node>, ParseError> {
+        if (name == "{") {
+            return self.parse_piecewise();
+        }
+        if (name == "frac") {
+            self.advance();
+            let top    = self.parse_group()?;
+            let bottom = self.parse_group()?;
+            if let (NodeBase::Variable(t), NodeBase::Multiplication(d, _)) = (&top.base, &bottom.base) {
+                if (t == "d" && matches!(&d.base, NodeBase::Variable(n) if (n == "d"))) {
+                    return self.error_at(column, String::from("Derivatives are not supported."));
+                }
+            }
+            return Ok(Node::new(NodeBase::Division(top, bottom)));
+        }
+        if (name == "sqrt") {
+            self.advance();
+            if (self.is_symbol('[')) {
+                let mut degree = self.parse_arguments('[', ']')?;
+                if (degree.len() != 1) {
+                    return self.error_at(column, String::from("Expected one root degree."));
+                }
+                let powered = self.parse_group()?;
+                return Ok(Node::new(NodeBase::NthRoot(degree.remove(0), powered)));
+            }
+            return Ok(Node::new(NodeBase::SquareRoot(self.parse_group()?)));
+        }
+        if (name == "operatorname") {
+            self.advance();
+            self.expect_symbol('{')?;
+            let mut operator = String::new();
+            while let TokenKind::Letter(ch) = self.peek().kind {
+                operator.push(ch);
+                self.advance();
+            }
+            self.expect_symbol('}')?;
+            return match (function_name(&operator)) {
+                Some(function) => self.parse_function(function, column),
+                None           => self.error_at(column, format!("Unsupported function `\\operatorname{{{}}}`.", operator))
+            };
+        }
+        if let Some(function) = function_name(name) {
+            self.advance();
+            return self.parse_function(function, column);
+        }
+        if (is_named_variable(name)) {
+            self.advance();
+            return Ok(Node::new(NodeBase::Variable(name.to_string())));
+        }
+        return self.error_at(column, unsupported_command(name));
+    }
+
+    // Arguments of a function whose name has already been consumed.
+    // Accepts `\log_{b}`, `\sin^{2}`, `\sin^{-1}`, `\sin\left(x\right)` and `\sin x`.
+    fn parse_function(&mut self, name : &'static str, column : usize) -> Result<Box<Node>, ParseError> {
+        let mut name = name;
+        let mut base = None;
+        if (self.is_symbol('_')) {
+            if (name != "log") {
+                return self.error(format!("Function `{}` does not take a subscript.", name));
+            }
+            self.advance();
+            base = Some(self.parse_script()?);
+        }
+        let mut degree = None;
+        if (self.is_symbol('^')) {
+            self.advance();
+            let script = self.parse_script()?;
+            if (matches!(script.base, NodeBase::Number(value) if (value == -1.0))) {
+                name = match (inverse_function_name(name)) {
+                    Some(inverse) => inverse,
+                    None          => return self.error_at(column, format!("Function `{}` has no inverse.", name))
+                };
+            } else {
+                degree = Some(script);
+            }
+        }
+
+        let mut arguments = if (self.is_symbol('(')) {
+            self.parse_arguments('(', ')')?
+        } else {
+            let bare_argument = self.bare_argument;
+            self.bare_argument = true;
+            let argument = self.parse_expression(PRECEDENCE_MULTIPLICATION)?;
+            self.bare_argument = bare_argument;
+            vec![argument]
+        };
+        if let Some(base) = base {
+            if (arguments.len() != 1) {
+                return self.error_at(column, format!("Function `log` with a base takes 1 argument, found {}.", arguments.len()));
+            }
+            arguments.insert(0, base);
+        }
+
+        let mut node = match (text::build_function(name, arguments)) {
+            Ok(base)     => Node::new(base),
+            Err(message) => return self.error_at(column, message)
+        };
+        if let Some(degree) = degree {
+            node = Node::new(NodeBase::Power(node, degree));
+        }
+        return Ok(node);
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Each equation in `source`, parsed and written back out fully bracketed.
+    fn parsed(source : &str) -> Vec<String> {
+        return parse(source).unwrap().iter().map(|node| node.to_string()).collect();
+    }
+
+    #[test]
+    fn reads_fractions_and_roots() {
+        assert_eq!(parsed("y=\\frac{x}{2}+\\sqrt[3]{x}"), ["(y = ((x / 2) + nthroot(3, x)))"]);
+        assert_eq!(parsed("y=\\sqrt{x+1}"),               ["(y = sqrt((x + 1)))"]);
+    }
+
+    #[test]
+    fn reads_functions_with_and_without_brackets() {
+        assert_eq!(parsed("y=2\\sin\\left(x\\right)\\cos x"),         ["(y = ((2 * sin(x)) * cos(x)))"]);
+        assert_eq!(parsed("y=\\sin^{2}x"),                            ["(y = (sin(x) ^ 2))"]);
+        assert_eq!(parsed("y=e^{x}+\\ln\\left(x\\right)+\\log_{2}x"), ["(y = (((e ^ x) + ln(x)) + log(2, x)))"]);
+        assert_eq!(parsed("y=\\operatorname{sech}\\left(x\\right)"),  ["(y = sech(x))"]);
+    }
+
+    #[test]
+    fn reads_operators_and_grouping() {
+        assert_eq!(parsed("y=\\left|x-1\\right|^{2}"), ["(y = (|(x - 1)| ^ 2))"]);
+        assert_eq!(parsed("y=x^{2}\\cdot3"),           ["(y = ((x ^ 2) * 3))"]);
+        assert_eq!(parsed("y=2\\left(x+1\\right)"),    ["(y = (2 * (x + 1)))"]);
+        assert_eq!(parsed("y=\\pi x"),                 ["(y = (pi * x))"]);
+    }
+
+    #[test]
+    fn reads_subscripts_and_piecewise_expressions() {
+        assert_eq!(parsed("a_{1}=3\ny=a_{1}x"),             ["(a_1 = 3)", "(y = (a_1 * x))"]);
+        assert_eq!(parsed("y=\\left\\{x<0:-x,x\\right\\}"), ["(y = {(x < 0): (-1 * x), x})"]);
+    }
+
+    #[test]
+    fn rejects_unsupported_commands() {
+        let error = parse("y=\\int_0^x t dt").unwrap_err();
+        assert_eq!((error.line, error.column), (1, 3));
+        assert_eq!(error.message, "`\\int` is not supported.");
+    }
+}
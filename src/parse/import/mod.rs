@@ -1,3 +1,102 @@
+use std::fs;
+use std::path::Path;
+
+use loggerithm::{logger, log};
+use loggerithm::level::{DEBUG, WARN};
+logger!(super);
+
 pub mod text;
+pub mod latex;
+pub mod desmos;
+
+pub use super::node;
+use node::Node;
+use crate::error::Error;
+use crate::parse::{function, list};
+use crate::render::settings::DEFAULT_COLOURS;
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    // Plain text, see `text`.
+    Text,
+    // Desmos flavoured LaTeX, see `latex`.
+    Latex,
+    // Saved Desmos graph state JSON, see `desmos`.
+    Desmos
+}
+impl Format {
+    // `.tex` and `.latex` files are LaTeX, `.json` files are Desmos graph states, anything else is plain text.
+    pub fn from_path(path : &Path) -> Format {
+        return match (path.extension().and_then(|extension| extension.to_str())) {
+            Some("tex") | Some("latex") => Format::Latex,
+            Some("json")                => Format::Desmos,
+            _                           => Format::Text
+        };
+    }
+}
+
+
+// Equations read from source text, with how the source says to draw them.
+#[derive(Debug)]
+pub struct LoadedGraph {
+    pub nodes   : Vec<Node>,
+    // Colour of each node by index, see `RenderSettings::colours`. Only
+    // Desmos graphs save colours, other formats leave this empty.
+    pub colours : Vec<[u8; 4]>,
+    // Saved view of Desmos graphs : Left, Bottom, Right, Top
+    pub frame   : Option<[f64; 4]>
+}
+
+
+// Parse equations from source text. Desmos graphs keep the nodes to render,
+// their colours and the viewport. Slider values are kept as the definitions
+// they are set by, slider bounds are dropped. Calls to user defined functions
+// are inlined, see `function`, and so are lists read element by element, see
+// `list`. Neither changes the number or order of the nodes.
+pub fn parse(source : &str, format : Format) -> Result<LoadedGraph, Error> {
+    let (equations, colours, frame) = match (format) {
+        Format::Text   => (text::parse(source)?, vec![], None),
+        Format::Latex  => (latex::parse(source)?, vec![], None),
+        Format::Desmos => {
+            let graph = desmos::parse(source)?;
+            log!(DEBUG,
+                "Read {} expression{} in {} folder{}.",
+                graph.expressions.len(), if (graph.expressions.len() == 1) {""} else {"s"},
+                graph.folders.len(), if (graph.folders.len() == 1) {""} else {"s"}
+            );
+            let frame = graph.viewport.filter(|viewport| {
+                let valid = viewport.iter().all(|corner| corner.is_finite()) && viewport[0] < viewport[2] && viewport[1] < viewport[3];
+                if (! valid) {
+                    log!(WARN, "Skipping empty viewport {:?}.", viewport);
+                }
+                return valid;
+            });
+            let drawn   = graph.into_drawn();
+            // Expressions Desmos saved without a colour get the default for their place.
+            let colours = drawn.iter().enumerate()
+                .map(|(i, expression)| match (expression.colour) {
+                    Some([red, green, blue]) => [red, green, blue, 255],
+                    None                     => DEFAULT_COLOURS[i % DEFAULT_COLOURS.len()]
+                })
+                .collect::<Vec<[u8; 4]>>();
+            (drawn.into_iter().map(|expression| expression.node).collect(), colours, frame)
+        }
+    };
+    return Ok(LoadedGraph {
+        nodes   : list::inline(function::inline(equations)?),
+        colours : colours,
+        frame   : frame
+    });
+}
 
-pub use super::node;
\ No newline at end of file
+// Read and parse an equation file. The format is picked from the extension if not given.
+pub fn load(path : &Path, format : Option<Format>) -> Result<LoadedGraph, Error> {
+    let source = match (fs::read_to_string(path)) {
+        Ok(source) => source,
+        Err(error) => return Err(Error::Read {path: path.display().to_string(), error})
+    };
+    let graph = parse(&source, format.unwrap_or_else(|| Format::from_path(path)))?;
+    log!(DEBUG, "Loaded {} equation{}.", graph.nodes.len(), if (graph.nodes.len() == 1) {""} else {"s"});
+    return Ok(graph);
+}
@@ -0,0 +1,967 @@
+// Plain text equation files.
+//
+// One equation per line, `#` starts a comment.
+//
+//     y = sin(x) + x*0
+//     x^2 + y^2 = 2.5^2   # Circle.
+//     y <= x^2            # Region.
+//     f(a, b) = a*b + 1   # Function.
+//     (cos(t), sin(2t))   # Parametric curve.
+//     y = {x < 0: -x, x}  # Piecewise.
+//     y = x^2 {0 < x < 2} # Restricted to part of the graph.
+//     y = [1...5] x       # A family of lines.
+//     a = [0, 0.5, ..., 5]
+//     y = [k x^2 for k = a]
+//     y = a[2] + length(a)
+//     y = mean(a) + stdev(a) x
+//
+// Identifiers are whole words (`pi`, `x1`, `theta`), so `2x` is `2 * x`
+// but `ex` is the variable `ex`. Use a space or `*` to multiply names.
+// A name followed by parentheses is a call only if the file defines a
+// function of that name, anywhere, otherwise `a(x + 1)` is `a * (x + 1)`.
+//
+// Between braces, conditions are comparisons joined with `and`, `or` and
+// `not`. A branch is `condition: value`, or a bare condition for `1`, and
+// a last bare expression is the value where no branch holds. Without one,
+// a piecewise expression has no value there, so a restriction multiplied
+// onto an expression leaves it undrawn.
+//
+// A list is written between brackets, with `...` (or `…`) for a range and
+// `for` for a comprehension, see `parse::list`. Brackets after a list are
+// an index, after anything else they multiply, so `a[2]` only picks an
+// element when `a` is defined as a list somewhere in the file.
+
+use std::collections::HashSet;
+use std::fmt;
+
+use loggerithm::{logger, log};
+use loggerithm::level::TRACE;
+logger!(super);
+
+use super::node::{Node, NodeBase, Relation};
+use crate::parse::list;
+use crate::parse::statistics::{Statistic, Paired};
+
+
+#[derive(Debug, Clone)]
+pub struct ParseError {
+    pub line    : usize,
+    pub column  : usize,
+    pub message : String
+}
+impl ParseError {
+    pub fn new(line : usize, column : usize, message : String) -> ParseError {
+        return ParseError {line, column, message};
+    }
+}
+impl fmt::Display for ParseError {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(f, "line {}, column {}: {}", self.line, self.column, self.message);
+    }
+}
+
+
+// Parse every equation in a file.
+pub fn parse(source : &str) -> Result<Vec<Node>, ParseError> {
+    let lines = source.lines().enumerate()
+        .map(|(i, line)| (i, match (line.find('#')) {
+            Some(index) => &line[..index],
+            None        => line
+        }))
+        .filter(|(_, line)| ! line.trim().is_empty())
+        .collect::<Vec<(usize, &str)>>();
+    let functions = lines.iter()
+        .filter_map(|(_, line)| defined_function(line))
+        .collect::<HashSet<String>>();
+    // A list may be defined from another, so look until no more are found.
+    let mut lists = HashSet::new();
+    loop {
+        let found = lines.iter()
+            .filter_map(|(_, line)| defined_list(line, &lists))
+            .collect::<HashSet<String>>();
+        if (found.len() == lists.len()) {
+            break;
+        }
+        lists = found;
+    }
+    let mut equations = vec![];
+    for (i, line) in lines {
+        let equation = parse_line(line, i + 1, &functions, &lists)?;
+        log!(TRACE, "Parsed line {} as `{}`.", i + 1, equation.to_string());
+        equations.push(*equation);
+    }
+    return Ok(equations);
+}
+
+// Parse a single equation or expression, with the names of the user
+// defined functions it may call and of the lists it may index.
+pub fn parse_line(line : &str, line_number : usize, functions : &HashSet<String>, lists : &HashSet<String>) -> Result<Box<Node>, ParseError> {
+    let tokens = tokenize(line, line_number)?;
+    let mut parser = Parser::new(tokens, line_number, functions, lists);
+    return parser.parse_relation();
+}
+
+// The name of the function a line defines, as in `f(a, b) = ...`. Lines
+// that fail to parse are reported when parsed for real.
+pub fn defined_function(line : &str) -> Option<String> {
+    let tokens = tokenize(line, 0).ok()?;
+    return Parser::new(tokens, 0, &HashSet::new(), &HashSet::new()).defined_function();
+}
+
+// The name of the list a line defines, as in `a = [1...5]` or `b = 2 a`,
+// given the lists already found.
+pub fn defined_list(line : &str, lists : &HashSet<String>) -> Option<String> {
+    let tokens = tokenize(line, 0).ok()?;
+    return Parser::new(tokens, 0, &HashSet::new(), lists).defined_list();
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenKind {
+    Number     (f64),
+    Identifier (String),
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    Caret,
+    Pipe,
+    Comma,
+    Equals,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    LeftParen,
+    RightParen,
+    LeftBracket,
+    RightBracket,
+    LeftBrace,
+    RightBrace,
+    Colon,
+    Ellipsis,
+    And,
+    Or,
+    Not,
+    For,
+    End
+}
+impl TokenKind {
+    fn describe(&self) -> String {
+        return match (self) {
+            TokenKind::Number     (value) => format!("number `{}`", value),
+            TokenKind::Identifier (name)  => format!("name `{}`", name),
+            TokenKind::Plus               => String::from("`+`"),
+            TokenKind::Minus              => String::from("`-`"),
+            TokenKind::Star               => String::from("`*`"),
+            TokenKind::Slash              => String::from("`/`"),
+            TokenKind::Caret              => String::from("`^`"),
+            TokenKind::Pipe               => String::from("`|`"),
+            TokenKind::Comma              => String::from("`,`"),
+            TokenKind::Equals             => String::from("`=`"),
+            TokenKind::Less               => String::from("`<`"),
+            TokenKind::LessEqual          => String::from("`<=`"),
+            TokenKind::Greater            => String::from("`>`"),
+            TokenKind::GreaterEqual       => String::from("`>=`"),
+            TokenKind::LeftParen          => String::from("`(`"),
+            TokenKind::RightParen         => String::from("`)`"),
+            TokenKind::LeftBracket        => String::from("`[`"),
+            TokenKind::RightBracket       => String::from("`]`"),
+            TokenKind::LeftBrace          => String::from("`{`"),
+            TokenKind::RightBrace         => String::from("`}`"),
+            TokenKind::Colon              => String::from("`:`"),
+            TokenKind::Ellipsis           => String::from("`...`"),
+            TokenKind::And                => String::from("`and`"),
+            TokenKind::Or                 => String::from("`or`"),
+            TokenKind::Not                => String::from("`not`"),
+            TokenKind::For                => String::from("`for`"),
+            TokenKind::End                => String::from("end of line")
+        };
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Token {
+    pub kind   : TokenKind,
+    pub column : usize
+}
+
+
+pub fn tokenize(line : &str, line_number : usize) -> Result<Vec<Token>, ParseError> {
+    let     chars  = line.chars().collect::<Vec<char>>();
+    let mut tokens = vec![];
+    let mut i      = 0;
+    while (i < chars.len()) {
+        let ch     = chars[i];
+        let column = i + 1;
+        if (ch.is_whitespace()) {
+            i += 1;
+            continue;
+        }
+
+        if (ch == '…' || chars[i..].starts_with(&['.', '.', '.'])) {
+            tokens.push(Token {kind: TokenKind::Ellipsis, column});
+            i += if (ch == '…') {1} else {3};
+            continue;
+        }
+
+        if (ch.is_ascii_digit() || (ch == '.' && chars.get(i + 1).is_some_and(|c| c.is_ascii_digit()))) {
+            let start = i;
+            while (i < chars.len() && chars[i].is_ascii_digit()) {
+                i += 1;
+            }
+            // A `.` starting an ellipsis, as in `1...5`, ends the number.
+            if (i < chars.len() && chars[i] == '.' && chars.get(i + 1) != Some(&'.')) {
+                i += 1;
+                while (i < chars.len() && chars[i].is_ascii_digit()) {
+                    i += 1;
+                }
+            }
+            // Only treat `e` as an exponent when digits follow, so `2e` stays `2 * e`.
+            if (i < chars.len() && (chars[i] == 'e' || chars[i] == 'E')) {
+                let mut j = i + 1;
+                if (j < chars.len() && (chars[j] == '+' || chars[j] == '-')) {
+                    j += 1;
+                }
+                if (j < chars.len() && chars[j].is_ascii_digit()) {
+                    i = j;
+                    while (i < chars.len() && chars[i].is_ascii_digit()) {
+                        i += 1;
+                    }
+                }
+            }
+            let text = chars[start..i].iter().collect::<String>();
+            let value = match (text.parse::<f64>()) {
+                Ok(value) => value,
+                Err(_)    => return Err(ParseError::new(line_number, column, format!("Invalid number `{}`.", text)))
+            };
+            tokens.push(Token {kind: TokenKind::Number(value), column});
+            continue;
+        }
+
+        if (ch.is_alphabetic() || ch == '_') {
+            let start = i;
+            while (i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_')) {
+                i += 1;
+            }
+            let name = chars[start..i].iter().collect::<String>();
+            let kind = match (name.as_str()) {
+                "and" => TokenKind::And,
+                "or"  => TokenKind::Or,
+                "not" => TokenKind::Not,
+                "for" => TokenKind::For,
+                _     => TokenKind::Identifier(name)
+            };
+            tokens.push(Token {kind, column});
+            continue;
+        }
+
+        if ((ch == '<' || ch == '>') && chars.get(i + 1) == Some(&'=')) {
+            tokens.push(Token {kind: if (ch == '<') {TokenKind::LessEqual} else {TokenKind::GreaterEqual}, column});
+            i += 2;
+            continue;
+        }
+
+        let kind = match (ch) {
+            '+' => TokenKind::Plus,
+            '-' => TokenKind::Minus,
+            '*' => TokenKind::Star,
+            '/' => TokenKind::Slash,
+            '^' => TokenKind::Caret,
+            '|' => TokenKind::Pipe,
+            ',' => TokenKind::Comma,
+            '=' => TokenKind::Equals,
+            '<' => TokenKind::Less,
+            '≤' => TokenKind::LessEqual,
+            '>' => TokenKind::Greater,
+            '≥' => TokenKind::GreaterEqual,
+            '(' => TokenKind::LeftParen,
+            ')' => TokenKind::RightParen,
+            '[' => TokenKind::LeftBracket,
+            ']' => TokenKind::RightBracket,
+            '{' => TokenKind::LeftBrace,
+            '}' => TokenKind::RightBrace,
+            ':' => TokenKind::Colon,
+            _   => return Err(ParseError::new(line_number, column, format!("Unexpected character `{}`.", ch)))
+        };
+        tokens.push(Token {kind, column});
+        i += 1;
+    }
+    tokens.push(Token {kind: TokenKind::End, column: chars.len() + 1});
+    return Ok(tokens);
+}
+
+
+// Binding strength of each operator, loosest first.
+pub(crate) const PRECEDENCE_ADDITION       : u8 = 1;
+pub(crate) const PRECEDENCE_MULTIPLICATION : u8 = 2;
+pub(crate) const PRECEDENCE_POWER          : u8 = 3;
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum BinaryOperator {
+    Addition,
+    Subtraction,
+    Multiplication,
+    Division,
+    Power
+}
+impl BinaryOperator {
+    pub(crate) fn precedence(&self) -> u8 {
+        return match (self) {
+            BinaryOperator::Addition       => PRECEDENCE_ADDITION,
+            BinaryOperator::Subtraction    => PRECEDENCE_ADDITION,
+            BinaryOperator::Multiplication => PRECEDENCE_MULTIPLICATION,
+            BinaryOperator::Division       => PRECEDENCE_MULTIPLICATION,
+            BinaryOperator::Power          => PRECEDENCE_POWER
+        };
+    }
+    pub(crate) fn right_associative(&self) -> bool {
+        return matches!(self, BinaryOperator::Power);
+    }
+    pub(crate) fn build(&self, left : Box<Node>, right : Box<Node>) -> Box<Node> {
+        return Node::new(match (self) {
+            BinaryOperator::Addition       => NodeBase::Addition       (left, right),
+            BinaryOperator::Subtraction    => NodeBase::Subtraction    (left, right),
+            BinaryOperator::Multiplication => NodeBase::Multiplication (left, right),
+            BinaryOperator::Division       => NodeBase::Division       (left, right),
+            BinaryOperator::Power          => NodeBase::Power          (left, right)
+        });
+    }
+}
+
+
+// Precedence climbing parser over the tokens of one line.
+pub struct Parser<'l> {
+    tokens      : Vec<Token>,
+    index       : usize,
+    line_number : usize,
+    // Number of `|` currently open, so a `|` in operator position closes instead of multiplying.
+    abs_depth   : usize,
+    // Names of user defined functions.
+    functions   : &'l HashSet<String>,
+    // Names of the variables defined as lists.
+    lists       : &'l HashSet<String>
+}
+impl<'l> Parser<'l> {
+    pub fn new(tokens : Vec<Token>, line_number : usize, functions : &'l HashSet<String>, lists : &'l HashSet<String>) -> Parser<'l> {
+        return Parser {
+            tokens,
+            index     : 0,
+            line_number,
+            abs_depth : 0,
+            functions,
+            lists
+        };
+    }
+
+    fn peek(&self) -> &Token {
+        return &self.tokens[self.index];
+    }
+    fn advance(&mut self) -> Token {
+        let token = self.tokens[self.index].clone();
+        if (self.index < self.tokens.len() - 1) {
+            self.index += 1;
+        }
+        return token;
+    }
+    fn error<T>(&self, message : String) -> Result<T, ParseError> {
+        return Err(ParseError::new(self.line_number, self.peek().column, message));
+    }
+    fn expect(&mut self, kind : TokenKind) -> Result<Token, ParseError> {
+        if (self.peek().kind == kind) {
+            return Ok(self.advance());
+        }
+        return self.error(format!("Expected {}, found {}.", kind.describe(), self.peek().kind.describe()));
+    }
+
+    // name '(' name (',' name)* ')' '='
+    fn defined_function(&mut self) -> Option<String> {
+        let name = match (&self.peek().kind) {
+            TokenKind::Identifier(name) if (! is_function(name)) => name.clone(),
+            _                                                    => return None
+        };
+        self.advance();
+        if (self.peek().kind != TokenKind::LeftParen) {
+            return None;
+        }
+        let parameters = self.parse_arguments(TokenKind::LeftParen, TokenKind::RightParen).ok()?;
+        let is_definition = self.peek().kind == TokenKind::Equals
+            && parameters.iter().all(|parameter| matches!(parameter.base, NodeBase::Variable(_)));
+        return if (is_definition) {Some(name)} else {None};
+    }
+
+    // name '=' expression, where the expression is a list.
+    fn defined_list(&mut self) -> Option<String> {
+        let name = match (&self.peek().kind) {
+            TokenKind::Identifier(name) => name.clone(),
+            _                           => return None
+        };
+        self.advance();
+        self.expect(TokenKind::Equals).ok()?;
+        let value   = self.parse_expression(PRECEDENCE_ADDITION).ok()?;
+        let is_list = self.peek().kind == TokenKind::End && list::is_list(&value, &|name| self.lists.contains(name));
+        return if (is_list) {Some(name)} else {None};
+    }
+
+    // relation := expression (('=' | '<' | '<=' | '>' | '>=') expression)?
+    pub fn parse_relation(&mut self) -> Result<Box<Node>, ParseError> {
+        let left = self.parse_expression(PRECEDENCE_ADDITION)?;
+        let node = match (relation_at(&self.peek().kind)) {
+            Some(relation) => {
+                self.advance();
+                let right = self.parse_expression(PRECEDENCE_ADDITION)?;
+                build_relation(relation, left, right)
+            },
+            None => left
+        };
+        self.expect(TokenKind::End)?;
+        return Ok(node);
+    }
+
+    fn parse_expression(&mut self, min_precedence : u8) -> Result<Box<Node>, ParseError> {
+        let mut left = self.parse_prefix()?;
+        while let Some((operator, implicit)) = self.peek_operator() {
+            if (operator.precedence() < min_precedence) {
+                break;
+            }
+            if (! implicit) {
+                self.advance();
+            }
+            let next_precedence = if (operator.right_associative()) {operator.precedence()} else {operator.precedence() + 1};
+            let right = self.parse_expression(next_precedence)?;
+            left = operator.build(left, right);
+        }
+        return Ok(left);
+    }
+
+    // The operator at the current token, and whether it is an implicit multiplication.
+    fn peek_operator(&self) -> Option<(BinaryOperator, bool)> {
+        return match (&self.peek().kind) {
+            TokenKind::Plus  => Some((BinaryOperator::Addition,       false)),
+            TokenKind::Minus => Some((BinaryOperator::Subtraction,    false)),
+            TokenKind::Star  => Some((BinaryOperator::Multiplication, false)),
+            TokenKind::Slash => Some((BinaryOperator::Division,       false)),
+            TokenKind::Caret => Some((BinaryOperator::Power,          false)),
+            TokenKind::Number(_) | TokenKind::Identifier(_) | TokenKind::LeftParen | TokenKind::LeftBracket | TokenKind::LeftBrace
+                => Some((BinaryOperator::Multiplication, true)),
+            TokenKind::Pipe if (self.abs_depth == 0)
+                => Some((BinaryOperator::Multiplication, true)),
+            _ => None
+        };
+    }
+
+    fn parse_prefix(&mut self) -> Result<Box<Node>, ParseError> {
+        return match (self.peek().kind) {
+            TokenKind::Minus => {
+                self.advance();
+                let operand = self.parse_expression(PRECEDENCE_POWER)?;
+                Ok(match (operand.base) {
+                    NodeBase::Number(value) => Node::new(NodeBase::Number(-value)),
+                    _ => Node::new(NodeBase::Multiplication(Node::new(NodeBase::Number(-1.0)), operand))
+                })
+            },
+            TokenKind::Plus => {
+                self.advance();
+                self.parse_expression(PRECEDENCE_POWER)
+            },
+            _ => self.parse_primary()
+        };
+    }
+
+    // primary := operand ('[' expression ']')*, where the operand is a list
+    fn parse_primary(&mut self) -> Result<Box<Node>, ParseError> {
+        let mut node = self.parse_operand()?;
+        while (self.peek().kind == TokenKind::LeftBracket && list::is_list(&node, &|name| self.lists.contains(name))) {
+            let column = self.peek().column;
+            let mut arguments = self.parse_arguments(TokenKind::LeftBracket, TokenKind::RightBracket)?;
+            if (arguments.len() != 1) {
+                return Err(ParseError::new(self.line_number, column, format!("Expected one index between brackets, found {}.", arguments.len())));
+            }
+            node = Node::new(NodeBase::Index(node, arguments.remove(0)));
+        }
+        return Ok(node);
+    }
+
+    fn parse_operand(&mut self) -> Result<Box<Node>, ParseError> {
+        let token = self.peek().clone();
+        return match (token.kind) {
+            TokenKind::Number(value) => {
+                self.advance();
+                Ok(Node::new(NodeBase::Number(value)))
+            },
+            TokenKind::Identifier(name) => {
+                self.advance();
+                if (is_function(&name)) {
+                    if (self.peek().kind != TokenKind::LeftParen) {
+                        return Err(ParseError::new(self.line_number, token.column,
+                            format!("Function `{}` must be followed by arguments in parentheses.", name)
+                        ));
+                    }
+                    let arguments = self.parse_arguments(TokenKind::LeftParen, TokenKind::RightParen)?;
+                    match (build_function(&name, arguments)) {
+                        Ok(base)     => Ok(Node::new(base)),
+                        Err(message) => Err(ParseError::new(self.line_number, token.column, message))
+                    }
+                } else if (self.functions.contains(&name) && self.peek().kind == TokenKind::LeftParen) {
+                    let arguments = self.parse_arguments(TokenKind::LeftParen, TokenKind::RightParen)?;
+                    Ok(Node::new(NodeBase::Call(name, arguments)))
+                } else {
+                    Ok(Node::new(NodeBase::Variable(name)))
+                }
+            },
+            TokenKind::LeftParen => {
+                let mut arguments = self.parse_arguments(TokenKind::LeftParen, TokenKind::RightParen)?;
+                match (arguments.len()) {
+                    1 => Ok(arguments.remove(0)),
+                    2 => {
+                        let y = arguments.remove(1);
+                        Ok(Node::new(NodeBase::Point(arguments.remove(0), y)))
+                    },
+                    _ => Err(ParseError::new(self.line_number, token.column,
+                        format!("Expected one expression or a point in parentheses, found {} expressions.", arguments.len())
+                    ))
+                }
+            },
+            TokenKind::LeftBracket => self.parse_list(),
+            TokenKind::LeftBrace => self.parse_piecewise(),
+            TokenKind::Pipe => {
+                self.advance();
+                self.abs_depth += 1;
+                let argument = self.parse_expression(PRECEDENCE_ADDITION)?;
+                self.expect(TokenKind::Pipe)?;
+                self.abs_depth -= 1;
+                Ok(Node::new(NodeBase::AbsoluteValue(argument)))
+            },
+            _ => self.error(format!("Expected an expression, found {}.", token.kind.describe()))
+        };
+    }
+
+    // Comma separated expressions between `open` and `close`.
+    #[allow(clippy::vec_box)]
+    fn parse_arguments(&mut self, open : TokenKind, close : TokenKind) -> Result<Vec<Box<Node>>, ParseError> {
+        self.expect(open)?;
+        // Bars inside brackets always open a new absolute value.
+        let abs_depth = self.abs_depth;
+        self.abs_depth = 0;
+        let mut arguments = vec![];
+        if (self.peek().kind != close) {
+            loop {
+                arguments.push(self.parse_expression(PRECEDENCE_ADDITION)?);
+                if (self.peek().kind == TokenKind::Comma) {
+                    self.advance();
+                } else {
+                    break;
+                }
+            }
+        }
+        self.expect(close)?;
+        self.abs_depth = abs_depth;
+        return Ok(arguments);
+    }
+
+    // list := '[' ']'
+    //       | '[' expression 'for' name '=' expression (',' name '=' expression)* ']'
+    //       | '[' expression (',' expression)* ']'
+    //
+    // A `...` before the last element makes a range of the one or two before it.
+    fn parse_list(&mut self) -> Result<Box<Node>, ParseError> {
+        let start = self.peek().column;
+        self.expect(TokenKind::LeftBracket)?;
+        let abs_depth = self.abs_depth;
+        self.abs_depth = 0;
+        let mut values   = vec![];
+        // Number of elements before the `...`.
+        let mut ellipsis = None;
+        while (self.peek().kind != TokenKind::RightBracket) {
+            if (self.peek().kind == TokenKind::Ellipsis) {
+                if (ellipsis.is_some() || values.is_empty()) {
+                    return self.error(String::from("Expected a range such as `[1...10]` or `[0, 0.5, ..., 5]`."));
+                }
+                ellipsis = Some(values.len());
+                self.advance();
+                if (self.peek().kind == TokenKind::Comma) {
+                    self.advance();
+                }
+                continue;
+            }
+            values.push(self.parse_expression(PRECEDENCE_ADDITION)?);
+            if (values.len() == 1 && ellipsis.is_none() && self.peek().kind == TokenKind::For) {
+                let node = self.parse_comprehension(values.remove(0))?;
+                self.abs_depth = abs_depth;
+                return Ok(node);
+            }
+            match (self.peek().kind) {
+                TokenKind::Comma    => {
+                    self.advance();
+                    if (self.peek().kind == TokenKind::RightBracket) {
+                        return self.error(String::from("Expected an expression, found `]`."));
+                    }
+                },
+                TokenKind::Ellipsis => (),
+                _                   => break
+            };
+        }
+        self.expect(TokenKind::RightBracket)?;
+        self.abs_depth = abs_depth;
+        return match (ellipsis) {
+            None => Ok(Node::new(NodeBase::MultiValue(values))),
+            Some(before) if (values.len() == before + 1 && (before == 1 || before == 2)) => {
+                let last   = values.pop().unwrap();
+                let second = if (before == 2) {values.pop()} else {None};
+                Ok(Node::new(NodeBase::Range(values.pop().unwrap(), second, last)))
+            },
+            Some(_) => Err(ParseError::new(self.line_number, start, String::from("Expected a range such as `[1...10]` or `[0, 0.5, ..., 5]`.")))
+        };
+    }
+
+    // The rest of a comprehension after its body, up to the closing `]`.
+    // The first variable is outermost.
+    fn parse_comprehension(&mut self, body : Box<Node>) -> Result<Box<Node>, ParseError> {
+        self.expect(TokenKind::For)?;
+        let mut bindings = vec![];
+        loop {
+            let name = match (&self.peek().kind) {
+                TokenKind::Identifier(name) if (! is_function(name)) => name.clone(),
+                kind => return self.error(format!("Expected a variable name after `for`, found {}.", kind.describe()))
+            };
+            self.advance();
+            self.expect(TokenKind::Equals)?;
+            bindings.push((name, self.parse_expression(PRECEDENCE_ADDITION)?));
+            if (self.peek().kind == TokenKind::Comma) {
+                self.advance();
+            } else {
+                break;
+            }
+        }
+        self.expect(TokenKind::RightBracket)?;
+        let mut node = body;
+        for (name, list) in bindings.into_iter().rev() {
+            node = Node::new(NodeBase::For(node, name, list));
+        }
+        return Ok(node);
+    }
+
+    // piecewise := '{' (branch (',' branch)* (',' expression)? | expression) '}'
+    // branch    := condition (':' expression)?
+    fn parse_piecewise(&mut self) -> Result<Box<Node>, ParseError> {
+        self.expect(TokenKind::LeftBrace)?;
+        let abs_depth = self.abs_depth;
+        self.abs_depth = 0;
+        let mut branches  = vec![];
+        let mut otherwise = None;
+        loop {
+            let column = self.peek().column;
+            let item   = self.parse_or()?;
+            if (item.base.is_condition()) {
+                let value = if (self.peek().kind == TokenKind::Colon) {
+                    self.advance();
+                    self.parse_expression(PRECEDENCE_ADDITION)?
+                } else {
+                    Node::new(NodeBase::Number(1.0))
+                };
+                branches.push((item, value));
+            } else if (self.peek().kind == TokenKind::Colon) {
+                return Err(ParseError::new(self.line_number, column, String::from("Expected a condition before `:`.")));
+            } else {
+                otherwise = Some(item);
+                break;
+            }
+            if (self.peek().kind == TokenKind::Comma) {
+                self.advance();
+            } else {
+                break;
+            }
+        }
+        if (otherwise.is_some() && self.peek().kind == TokenKind::Comma) {
+            return self.error(String::from("Only the last part of a piecewise expression may be without a condition."));
+        }
+        self.expect(TokenKind::RightBrace)?;
+        self.abs_depth = abs_depth;
+        return Ok(Node::new(NodeBase::Piecewise(branches, otherwise)));
+    }
+
+    // or := and ('or' and)*
+    fn parse_or(&mut self) -> Result<Box<Node>, ParseError> {
+        let mut left = self.parse_and()?;
+        while (self.peek().kind == TokenKind::Or) {
+            self.advance();
+            let right = self.parse_and()?;
+            left = self.join(left, right, NodeBase::Or)?;
+        }
+        return Ok(left);
+    }
+
+    // and := not ('and' not)*
+    fn parse_and(&mut self) -> Result<Box<Node>, ParseError> {
+        let mut left = self.parse_not()?;
+        while (self.peek().kind == TokenKind::And) {
+            self.advance();
+            let right = self.parse_not()?;
+            left = self.join(left, right, NodeBase::And)?;
+        }
+        return Ok(left);
+    }
+
+    // not := 'not' not | comparison
+    fn parse_not(&mut self) -> Result<Box<Node>, ParseError> {
+        if (self.peek().kind == TokenKind::Not) {
+            self.advance();
+            let column = self.peek().column;
+            let arg    = self.parse_not()?;
+            if (! arg.base.is_condition()) {
+                return Err(ParseError::new(self.line_number, column, String::from("Expected a condition after `not`.")));
+            }
+            return Ok(Node::new(NodeBase::Not(arg)));
+        }
+        return self.parse_comparison();
+    }
+
+    // comparison := '(' or ')' | expression (('=' | '<' | '<=' | '>' | '>=') expression)*
+    //
+    // A chain such as `0 < x < 2` holds where every link does. Without a
+    // relation this is a bare expression.
+    fn parse_comparison(&mut self) -> Result<Box<Node>, ParseError> {
+        // A condition in parentheses, or else an expression starting with one.
+        if (self.peek().kind == TokenKind::LeftParen) {
+            let (index, abs_depth) = (self.index, self.abs_depth);
+            self.advance();
+            self.abs_depth = 0;
+            if let Ok(condition) = self.parse_or() {
+                if (condition.base.is_condition() && self.peek().kind == TokenKind::RightParen) {
+                    self.advance();
+                    self.abs_depth = abs_depth;
+                    return Ok(condition);
+                }
+            }
+            self.index     = index;
+            self.abs_depth = abs_depth;
+        }
+        let mut left      = self.parse_expression(PRECEDENCE_ADDITION)?;
+        let mut condition = None;
+        while let Some(relation) = relation_at(&self.peek().kind) {
+            self.advance();
+            let right   = self.parse_expression(PRECEDENCE_ADDITION)?;
+            let compare = Node::new(NodeBase::Compare(relation, left, right.clone()));
+            condition = Some(match (condition) {
+                Some(condition) => Node::new(NodeBase::And(condition, compare)),
+                None            => compare
+            });
+            left = right;
+        }
+        return Ok(condition.unwrap_or(left));
+    }
+
+    // Two conditions joined by `and` or `or`.
+    fn join(&self, left : Box<Node>, right : Box<Node>, base : fn(Box<Node>, Box<Node>) -> NodeBase) -> Result<Box<Node>, ParseError> {
+        if (! left.base.is_condition() || ! right.base.is_condition()) {
+            return self.error(String::from("Expected conditions on both sides of `and` and `or`."));
+        }
+        return Ok(Node::new(base(left, right)));
+    }
+}
+
+
+// The relation of a comparison token.
+fn relation_at(kind : &TokenKind) -> Option<Relation> {
+    return match (kind) {
+        TokenKind::Equals       => Some(Relation::Equal),
+        TokenKind::Less         => Some(Relation::Less),
+        TokenKind::LessEqual    => Some(Relation::LessEqual),
+        TokenKind::Greater      => Some(Relation::Greater),
+        TokenKind::GreaterEqual => Some(Relation::GreaterEqual),
+        _                       => None
+    };
+}
+
+
+// Build the node for an equation or inequality. Shared with the other importers.
+pub(crate) fn build_relation(relation : Relation, left : Box<Node>, right : Box<Node>) -> Box<Node> {
+    return Node::new(match (relation) {
+        Relation::Equal        => NodeBase::Equals       (left, right),
+        Relation::Less         => NodeBase::Less         (left, right),
+        Relation::LessEqual    => NodeBase::LessEqual    (left, right),
+        Relation::Greater      => NodeBase::Greater      (left, right),
+        Relation::GreaterEqual => NodeBase::GreaterEqual (left, right)
+    });
+}
+
+// Built in function names. Shared with the other importers.
+pub(crate) fn is_function(name : &str) -> bool {
+    return matches!(name,
+        "abs" | "sqrt" | "nthroot" |
+        "sin" | "cos" | "tan" | "csc" | "sec" | "cot" |
+        "asin" | "acos" | "atan" | "acsc" | "asec" | "acot" |
+        "sinh" | "cosh" | "tanh" | "csch" | "sech" | "coth" |
+        "exp" | "ln" | "log" | "mod" |
+        "ceil" | "floor" | "round" | "sign" |
+        "length" | "total" | "join" | "sort" | "unique" | "shuffle" |
+        "mean" | "median" | "min" | "max" | "quantile" | "quartile" |
+        "var" | "stdev" | "stdevp" | "mad" | "cov" | "covp" | "corr" | "spearman" | "stats"
+    );
+}
+
+// Build the node for a call to a built in function.
+#[allow(clippy::vec_box)]
+pub(crate) fn build_function(name : &str, arguments : Vec<Box<Node>>) -> Result<NodeBase, String> {
+    let count = arguments.len();
+    let mut arguments = arguments.into_iter();
+    macro unary($variant:ident) {
+        if (count == 1) {
+            Ok(NodeBase::$variant(arguments.next().unwrap()))
+        } else {
+            Err(format!("Function `{}` takes 1 argument, found {}.", name, count))
+        }
+    }
+    macro binary($variant:ident) {
+        if (count == 2) {
+            Ok(NodeBase::$variant(arguments.next().unwrap(), arguments.next().unwrap()))
+        } else {
+            Err(format!("Function `{}` takes 2 arguments, found {}.", name, count))
+        }
+    }
+    // Several arguments are read as one list, so `max(a, 0)` is `max(join(a, 0))`.
+    macro statistic($statistic:ident) {
+        match (count) {
+            0 => Err(format!("Function `{}` takes at least 1 argument, found 0.", name)),
+            1 => Ok(NodeBase::Statistic(Statistic::$statistic, arguments.next().unwrap())),
+            _ => Ok(NodeBase::Statistic(Statistic::$statistic, Node::new(NodeBase::Join(arguments.collect()))))
+        }
+    }
+    macro paired($paired:ident) {
+        if (count == 2) {
+            Ok(NodeBase::Paired(Paired::$paired, arguments.next().unwrap(), arguments.next().unwrap()))
+        } else {
+            Err(format!("Function `{}` takes 2 arguments, found {}.", name, count))
+        }
+    }
+    return match (name) {
+        "abs"      => unary!(AbsoluteValue),
+        "sqrt"     => unary!(SquareRoot),
+        "nthroot"  => binary!(NthRoot),
+        "sin"      => unary!(Sine),
+        "cos"      => unary!(Cosine),
+        "tan"      => unary!(Tangent),
+        "csc"      => unary!(Cosecant),
+        "sec"      => unary!(Secant),
+        "cot"      => unary!(Cotangent),
+        "asin"     => unary!(InverseSine),
+        "acos"     => unary!(InverseCosine),
+        "atan"     => unary!(InverseTangent),
+        "acsc"     => unary!(InverseCosecant),
+        "asec"     => unary!(InverseSecant),
+        "acot"     => unary!(InverseCotangent),
+        "sinh"     => unary!(HyperbolicSine),
+        "cosh"     => unary!(HyperbolicCosine),
+        "tanh"     => unary!(HyperbolicTangent),
+        "csch"     => unary!(HyperbolicCosecant),
+        "sech"     => unary!(HyperbolicSecant),
+        "coth"     => unary!(HyperbolicCotangent),
+        "exp"      => unary!(Exponential),
+        "ln"       => unary!(NaturalLogarithm),
+        // `log(r)` is base 10, `log(b, r)` is base `b`.
+        "log"      => match (count) {
+            1 => Ok(NodeBase::Logartithm(Node::new(NodeBase::Number(10.0)), arguments.next().unwrap())),
+            2 => binary!(Logartithm),
+            _ => Err(format!("Function `log` takes 1 or 2 arguments, found {}.", count))
+        },
+        "mod"      => binary!(Modulo),
+        "ceil"     => unary!(Ceiling),
+        "floor"    => unary!(Floor),
+        "round"    => unary!(Round),
+        "sign"     => unary!(Sign),
+        "length"   => unary!(Length),
+        "total"    => unary!(Total),
+        "join"     => {
+            if (count >= 1) {
+                Ok(NodeBase::Join(arguments.collect()))
+            } else {
+                Err(String::from("Function `join` takes at least 1 argument, found 0."))
+            }
+        },
+        "sort"     => unary!(Sort),
+        "unique"   => unary!(Unique),
+        // `shuffle(a)` uses the seed `0`.
+        "shuffle"  => match (count) {
+            1 => Ok(NodeBase::Shuffle(arguments.next().unwrap(), None)),
+            2 => Ok(NodeBase::Shuffle(arguments.next().unwrap(), arguments.next())),
+            _ => Err(format!("Function `shuffle` takes 1 or 2 arguments, found {}.", count))
+        },
+        "mean"     => statistic!(Mean),
+        "median"   => statistic!(Median),
+        "min"      => statistic!(Minimum),
+        "max"      => statistic!(Maximum),
+        "var"      => statistic!(Variance),
+        "stdev"    => statistic!(Deviation),
+        "stdevp"   => statistic!(PopulationDeviation),
+        "mad"      => statistic!(MeanAbsoluteDeviation),
+        "quantile" => binary!(Quantile),
+        "quartile" => binary!(Quartile),
+        "cov"      => paired!(Covariance),
+        "covp"     => paired!(PopulationCovariance),
+        "corr"     => paired!(Correlation),
+        "spearman" => paired!(RankCorrelation),
+        "stats"    => unary!(Stats),
+        _          => Err(format!("Unknown function `{}`.", name))
+    };
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Each equation in `source`, parsed and written back out fully bracketed.
+    fn parsed(source : &str) -> Vec<String> {
+        return parse(source).unwrap().iter().map(|node| node.to_string()).collect();
+    }
+
+    fn error(source : &str) -> (usize, usize, String) {
+        let error = parse(source).unwrap_err();
+        return (error.line, error.column, error.message);
+    }
+
+    #[test]
+    fn operators_follow_precedence() {
+        assert_eq!(parsed("y = 1 + 2 * 3"),   ["(y = (1 + (2 * 3)))"]);
+        assert_eq!(parsed("y = 1 * 2 + 3"),   ["(y = ((1 * 2) + 3))"]);
+        assert_eq!(parsed("y = 2 * 3 ^ 2"),   ["(y = (2 * (3 ^ 2)))"]);
+        assert_eq!(parsed("y = (1 + 2) * 3"), ["(y = ((1 + 2) * 3))"]);
+        assert_eq!(parsed("y = -x^2"),        ["(y = (-1 * (x ^ 2)))"]);
+    }
+
+    #[test]
+    fn powers_group_right_and_others_left() {
+        assert_eq!(parsed("y = 2 ^ 3 ^ 2"), ["(y = (2 ^ (3 ^ 2)))"]);
+        assert_eq!(parsed("y = 1 - 2 - 3"), ["(y = ((1 - 2) - 3))"]);
+        assert_eq!(parsed("y = x / 2 * 3"), ["(y = ((x / 2) * 3))"]);
+    }
+
+    #[test]
+    fn juxtaposition_multiplies() {
+        assert_eq!(parsed("y = 2x"),              ["(y = (2 * x))"]);
+        assert_eq!(parsed("y = 2 sin(x)"),        ["(y = (2 * sin(x)))"]);
+        assert_eq!(parsed("y = 3(x + 1)"),        ["(y = (3 * (x + 1)))"]);
+        assert_eq!(parsed("y = (x + 1)(x - 1)"),  ["(y = ((x + 1) * (x - 1)))"]);
+        assert_eq!(parsed("y = 2pi x"),           ["(y = ((2 * pi) * x))"]);
+        // Only known functions are called, other names multiply.
+        assert_eq!(parsed("y = a(x)"),            ["(y = (a * x))"]);
+    }
+
+    #[test]
+    fn reads_functions_and_absolute_values() {
+        assert_eq!(parsed("y = |x - 1|"),          ["(y = |(x - 1)|)"]);
+        assert_eq!(parsed("y = sqrt(x) + abs(x)"), ["(y = (sqrt(x) + |x|))"]);
+    }
+
+    #[test]
+    fn skips_blank_lines_and_comments() {
+        assert_eq!(parsed("y = x\n\n# note\ny = 2x # trailing"), ["(y = x)", "(y = (2 * x))"]);
+    }
+
+    #[test]
+    fn reports_where_errors_are() {
+        assert_eq!(error("y = (x + 1"), (1, 11, String::from("Expected `)`, found end of line.")));
+        assert_eq!(error("y = x\ny = 2 +"), (2, 8, String::from("Expected an expression, found end of line.")));
+        assert_eq!(error("y = x $ 2"), (1, 7, String::from("Unexpected character `$`.")));
+    }
+}
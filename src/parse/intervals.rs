@@ -0,0 +1,545 @@
+use std::fmt;
+
+use crate::parse::var;
+
+
+// Unions with more pieces than this are joined into one interval.
+const MAX_INTERVALS : usize = 16;
+// Largest number of whole exponents tried for a negative base.
+const MAX_WHOLE_EXPONENTS : f64 = 8.0;
+
+
+// A closed range of values : Lower, Upper
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Interval {
+    pub lower : f64,
+    pub upper : f64
+}
+impl Interval {
+    pub fn new(lower : f64, upper : f64) -> Interval {
+        return Interval {lower, upper};
+    }
+    pub fn point(value : f64) -> Interval {
+        return Interval::new(value, value);
+    }
+    pub fn everything() -> Interval {
+        return Interval::new(f64::NEG_INFINITY, f64::INFINITY);
+    }
+    pub fn is_point(&self) -> bool {
+        return self.lower == self.upper;
+    }
+    pub fn contains(&self, value : f64) -> bool {
+        return self.lower <= value && value <= self.upper;
+    }
+    pub fn overlaps(&self, other : &Interval) -> bool {
+        return self.lower <= other.upper && other.lower <= self.upper;
+    }
+    // One step outwards on each side, to cover rounding in the result.
+    fn widen(&self) -> Interval {
+        return Interval::new(self.lower.next_down(), self.upper.next_up());
+    }
+}
+
+
+// Guaranteed enclosures of the values an expression takes over ranges of
+// inputs. Where `EvaluatedValues` holds points, this holds a union of
+// intervals, so a value outside all of them can not happen. Inputs where
+// the expression is undefined are left out, so an empty union means the
+// expression is undefined everywhere in the range.
+#[derive(Debug, Clone)]
+pub struct EvaluatedIntervals {
+    intervals : Vec<Interval>
+}
+impl Default for EvaluatedIntervals {
+    fn default() -> EvaluatedIntervals {
+        return EvaluatedIntervals::new();
+    }
+}
+impl EvaluatedIntervals {
+    pub fn new() -> EvaluatedIntervals {
+        return EvaluatedIntervals {intervals: vec![]};
+    }
+    pub fn from(intervals : Vec<Interval>) -> EvaluatedIntervals {
+        let mut new_intervals = EvaluatedIntervals::new();
+        for interval in intervals {
+            new_intervals.insert(interval);
+        }
+        return new_intervals.normalise();
+    }
+    pub fn copy(intervals : &EvaluatedIntervals) -> EvaluatedIntervals {
+        return EvaluatedIntervals {intervals: intervals.intervals.clone()};
+    }
+
+    pub fn add(&self, intervals : &EvaluatedIntervals) -> EvaluatedIntervals {
+        let mut new_intervals = EvaluatedIntervals::copy(self);
+        for interval in &intervals.intervals {
+            new_intervals.insert(*interval);
+        }
+        return new_intervals.normalise();
+    }
+    pub fn push(&self, interval : Interval) -> EvaluatedIntervals {
+        let mut new_intervals = EvaluatedIntervals::copy(self);
+        new_intervals.insert(interval);
+        return new_intervals.normalise();
+    }
+    pub fn get_intervals(&self) -> &Vec<Interval> {
+        return &self.intervals;
+    }
+    pub fn is_empty(&self) -> bool {
+        return self.intervals.is_empty();
+    }
+    pub fn contains(&self, value : f64) -> bool {
+        return self.intervals.iter().any(|interval| interval.contains(value));
+    }
+
+    pub fn addition(&self, other : &EvaluatedIntervals) -> EvaluatedIntervals {
+        return self.binary_operation(other, |a, b, new_intervals| {
+            new_intervals.insert(Interval::new(a.lower + b.lower, a.upper + b.upper));
+        });
+    }
+    pub fn subtraction(&self, other : &EvaluatedIntervals) -> EvaluatedIntervals {
+        return self.binary_operation(other, |a, b, new_intervals| {
+            new_intervals.insert(Interval::new(a.lower - b.upper, a.upper - b.lower));
+        });
+    }
+    pub fn multiplication(&self, other : &EvaluatedIntervals) -> EvaluatedIntervals {
+        return self.binary_operation(other, |a, b, new_intervals| new_intervals.insert(multiply(a, b)));
+    }
+    pub fn division(&self, other : &EvaluatedIntervals) -> EvaluatedIntervals {
+        return self.binary_operation(other, |a, b, new_intervals| {
+            for reciprocal in reciprocal(b) {
+                new_intervals.insert(multiply(a, reciprocal));
+            }
+        });
+    }
+    pub fn power(&self, other : &EvaluatedIntervals) -> EvaluatedIntervals {
+        return self.binary_operation(other, power);
+    }
+
+
+    pub fn absolute_value(&self) -> EvaluatedIntervals {
+        return self.unary_operation(|a, new_intervals| new_intervals.insert(absolute(a)));
+    }
+    pub fn square_root(&self) -> EvaluatedIntervals {
+        return self.unary_operation(|a, new_intervals| {
+            if (a.upper >= 0.0) {
+                new_intervals.insert(Interval::new(a.lower.max(0.0).sqrt(), a.upper.sqrt()));
+            }
+        });
+    }
+    pub fn nth_root(&self, degree : &EvaluatedIntervals) -> EvaluatedIntervals {
+        return self.binary_operation(degree, |a, n, new_intervals| {
+            if (n.is_point() && n.lower == 0.0) {
+                return;
+            }
+            // Non negative part.
+            if (a.upper >= 0.0) {
+                let positive = Interval::new(a.lower.max(0.0), a.upper);
+                for exponent in reciprocal(n) {
+                    power(positive, exponent, new_intervals);
+                }
+            }
+            // Odd roots of negative numbers are real.
+            if (a.lower < 0.0) {
+                let negative = Interval::new(-a.upper.min(0.0), -a.lower);
+                for_whole_numbers(n, new_intervals, |degree, new_intervals| {
+                    if (degree % 2.0 != 0.0) {
+                        let mut roots = EvaluatedIntervals::new();
+                        power(negative, Interval::point(1.0 / degree), &mut roots);
+                        for root in roots.intervals {
+                            new_intervals.insert(Interval::new(-root.upper, -root.lower));
+                        }
+                    }
+                });
+            }
+        });
+    }
+    pub fn sine(&self) -> EvaluatedIntervals {
+        return self.unary_operation(|a, new_intervals| new_intervals.insert(periodic(a, f64::sin, var::PI / 2.0, -var::PI / 2.0)));
+    }
+    pub fn cosine(&self) -> EvaluatedIntervals {
+        return self.unary_operation(|a, new_intervals| new_intervals.insert(periodic(a, f64::cos, 0.0, var::PI)));
+    }
+    pub fn tangent(&self) -> EvaluatedIntervals {
+        return self.unary_operation(|a, new_intervals| {
+            if (! (a.lower.is_finite() && a.upper.is_finite()) || a.upper - a.lower >= var::PI) {
+                new_intervals.insert(Interval::everything());
+            } else if (contains_repeating(a, var::PI / 2.0, var::PI)) {
+                // Split at the asymptote.
+                new_intervals.insert(Interval::new(a.lower.tan(), f64::INFINITY));
+                new_intervals.insert(Interval::new(f64::NEG_INFINITY, a.upper.tan()));
+            } else {
+                new_intervals.insert(Interval::new(a.lower.tan(), a.upper.tan()));
+            }
+        });
+    }
+    pub fn cosecant(&self) -> EvaluatedIntervals {
+        return EvaluatedIntervals::from(vec![Interval::point(1.0)]).division(&self.sine());
+    }
+    pub fn secant(&self) -> EvaluatedIntervals {
+        return EvaluatedIntervals::from(vec![Interval::point(1.0)]).division(&self.cosine());
+    }
+    pub fn cotangent(&self) -> EvaluatedIntervals {
+        return EvaluatedIntervals::from(vec![Interval::point(1.0)]).division(&self.tangent());
+    }
+    pub fn inverse_sine(&self) -> EvaluatedIntervals {
+        return self.unary_operation(|a, new_intervals| {
+            if (a.upper >= -1.0 && a.lower <= 1.0) {
+                new_intervals.insert(Interval::new(a.lower.max(-1.0).asin(), a.upper.min(1.0).asin()));
+            }
+        });
+    }
+    pub fn inverse_cosine(&self) -> EvaluatedIntervals {
+        return self.unary_operation(|a, new_intervals| {
+            if (a.upper >= -1.0 && a.lower <= 1.0) {
+                new_intervals.insert(Interval::new(a.upper.min(1.0).acos(), a.lower.max(-1.0).acos()));
+            }
+        });
+    }
+    pub fn inverse_tangent(&self) -> EvaluatedIntervals {
+        return self.unary_operation(|a, new_intervals| new_intervals.insert(Interval::new(a.lower.atan(), a.upper.atan())));
+    }
+    pub fn inverse_cosecant(&self) -> EvaluatedIntervals {
+        return EvaluatedIntervals::from(vec![Interval::point(1.0)]).division(self).inverse_sine();
+    }
+    pub fn inverse_secant(&self) -> EvaluatedIntervals {
+        return EvaluatedIntervals::from(vec![Interval::point(1.0)]).division(self).inverse_cosine();
+    }
+    pub fn inverse_cotangent(&self) -> EvaluatedIntervals {
+        return EvaluatedIntervals::from(vec![Interval::point(1.0)]).division(self).inverse_tangent();
+    }
+    pub fn hyperbolic_sine(&self) -> EvaluatedIntervals {
+        return self.unary_operation(|a, new_intervals| new_intervals.insert(Interval::new(a.lower.sinh(), a.upper.sinh())));
+    }
+    pub fn hyperbolic_cosine(&self) -> EvaluatedIntervals {
+        return self.unary_operation(|a, new_intervals| {
+            let a = absolute(a);
+            new_intervals.insert(Interval::new(a.lower.cosh(), a.upper.cosh()));
+        });
+    }
+    pub fn hyperbolic_tangent(&self) -> EvaluatedIntervals {
+        return self.unary_operation(|a, new_intervals| new_intervals.insert(Interval::new(a.lower.tanh(), a.upper.tanh())));
+    }
+    pub fn hyperbolic_cosecant(&self) -> EvaluatedIntervals {
+        return EvaluatedIntervals::from(vec![Interval::point(1.0)]).division(&self.hyperbolic_sine());
+    }
+    pub fn hyperbolic_secant(&self) -> EvaluatedIntervals {
+        return EvaluatedIntervals::from(vec![Interval::point(1.0)]).division(&self.hyperbolic_cosine());
+    }
+    pub fn hyperbolic_cotangent(&self) -> EvaluatedIntervals {
+        return EvaluatedIntervals::from(vec![Interval::point(1.0)]).division(&self.hyperbolic_tangent());
+    }
+    pub fn exponential(&self) -> EvaluatedIntervals {
+        return self.unary_operation(|a, new_intervals| new_intervals.insert(Interval::new(a.lower.exp(), a.upper.exp())));
+    }
+    pub fn natural_logarithm(&self) -> EvaluatedIntervals {
+        return self.unary_operation(|a, new_intervals| {
+            if (a.upper > 0.0) {
+                new_intervals.insert(Interval::new(a.lower.max(0.0).ln(), a.upper.ln()));
+            }
+        });
+    }
+    pub fn logarithm(&self, other : &EvaluatedIntervals) -> EvaluatedIntervals {
+        return self.natural_logarithm().division(&other.natural_logarithm());
+    }
+    pub fn modulo(&self, other : &EvaluatedIntervals) -> EvaluatedIntervals {
+        return self.binary_operation(other, |a, b, new_intervals| {
+            if (b.is_point() && b.lower != 0.0 && b.lower.is_finite()) {
+                // Exact when `a` does not wrap around.
+                let b        = b.lower;
+                let quotient = (a.lower / b).floor();
+                if (quotient == (a.upper / b).floor()) {
+                    new_intervals.insert(Interval::new(a.lower - b * quotient, a.upper - b * quotient));
+                } else {
+                    new_intervals.insert(Interval::new(b.min(0.0), b.max(0.0)));
+                }
+            } else {
+                // `a - b * floor(a / b)`
+                let a        = EvaluatedIntervals::from(vec![a]);
+                let b        = EvaluatedIntervals::from(vec![b]);
+                let quotient = a.division(&b).floor();
+                for interval in a.subtraction(&b.multiplication(&quotient)).intervals {
+                    new_intervals.insert(interval);
+                }
+            }
+        });
+    }
+    pub fn ceiling(&self) -> EvaluatedIntervals {
+        return self.unary_operation(|a, new_intervals| new_intervals.insert(Interval::new(a.lower.ceil(), a.upper.ceil())));
+    }
+    pub fn floor(&self) -> EvaluatedIntervals {
+        return self.unary_operation(|a, new_intervals| new_intervals.insert(Interval::new(a.lower.floor(), a.upper.floor())));
+    }
+    pub fn round(&self) -> EvaluatedIntervals {
+        return self.unary_operation(|a, new_intervals| new_intervals.insert(Interval::new(a.lower.round(), a.upper.round())));
+    }
+    pub fn sign(&self) -> EvaluatedIntervals {
+        return self.unary_operation(|a, new_intervals| new_intervals.insert(Interval::new(sign(a.lower), sign(a.upper))));
+    }
+    pub fn minimum(&self, other : &EvaluatedIntervals) -> EvaluatedIntervals {
+        return self.binary_operation(other, |a, b, new_intervals| new_intervals.insert(Interval::new(a.lower.min(b.lower), a.upper.min(b.upper))));
+    }
+    pub fn maximum(&self, other : &EvaluatedIntervals) -> EvaluatedIntervals {
+        return self.binary_operation(other, |a, b, new_intervals| new_intervals.insert(Interval::new(a.lower.max(b.lower), a.upper.max(b.upper))));
+    }
+
+
+    // Add an interval without merging. Intervals with `NaN` bounds are dropped.
+    fn insert(&mut self, interval : Interval) {
+        if (interval.lower <= interval.upper) {
+            self.intervals.push(interval);
+        }
+    }
+    // Sort and merge overlapping intervals.
+    fn normalise(mut self) -> EvaluatedIntervals {
+        self.intervals.sort_by(|a, b| a.lower.total_cmp(&b.lower));
+        let mut merged : Vec<Interval> = vec![];
+        for interval in self.intervals {
+            match (merged.last_mut()) {
+                Some(last) if (last.upper >= interval.lower) => {
+                    last.upper = last.upper.max(interval.upper);
+                },
+                _ => merged.push(interval)
+            };
+        }
+        if (merged.len() > MAX_INTERVALS) {
+            merged = vec![Interval::new(merged[0].lower, merged.iter().map(|interval| interval.upper).fold(f64::NEG_INFINITY, f64::max))];
+        }
+        return EvaluatedIntervals {intervals: merged};
+    }
+
+    fn unary_operation<T>(&self, target : T) -> EvaluatedIntervals
+        where T : Fn(Interval, &mut EvaluatedIntervals)
+    {
+        let mut new_intervals = EvaluatedIntervals::new();
+        for a in 0..self.intervals.len() {
+            target(self.intervals[a], &mut new_intervals);
+        }
+        return new_intervals.widen();
+    }
+    fn binary_operation<T>(&self, other : &EvaluatedIntervals, target : T) -> EvaluatedIntervals
+        where T : Fn(Interval, Interval, &mut EvaluatedIntervals)
+    {
+        let mut new_intervals = EvaluatedIntervals::new();
+        for a in 0..self.intervals.len() {
+            for b in 0..other.intervals.len() {
+                target(self.intervals[a], other.intervals[b], &mut new_intervals);
+            }
+        }
+        return new_intervals.widen();
+    }
+    fn widen(mut self) -> EvaluatedIntervals {
+        for interval in &mut self.intervals {
+            *interval = interval.widen();
+        }
+        return self.normalise();
+    }
+
+}
+impl fmt::Display for EvaluatedIntervals {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(f, "{}", self.intervals
+            .iter().map(|i| format!("[{}, {}]", i.lower, i.upper))
+            .collect::<Vec<String>>()
+            .join(",")
+        );
+    }
+}
+
+
+// `0 * ∞` is taken as `0`, as the infinite bound is never reached.
+fn multiply_bounds(a : f64, b : f64) -> f64 {
+    if (a == 0.0 || b == 0.0) {
+        return 0.0;
+    }
+    return a * b;
+}
+
+fn multiply(a : Interval, b : Interval) -> Interval {
+    let products = [
+        multiply_bounds(a.lower, b.lower), multiply_bounds(a.lower, b.upper),
+        multiply_bounds(a.upper, b.lower), multiply_bounds(a.upper, b.upper)
+    ];
+    return Interval::new(
+        products.iter().cloned().fold(f64::INFINITY, f64::min),
+        products.iter().cloned().fold(f64::NEG_INFINITY, f64::max)
+    );
+}
+
+// `1 / a`, in two pieces when `a` straddles zero.
+fn reciprocal(a : Interval) -> Vec<Interval> {
+    if (a.lower > 0.0 || a.upper < 0.0) {
+        return vec![Interval::new(1.0 / a.upper, 1.0 / a.lower)];
+    }
+    let mut pieces = vec![];
+    if (a.lower < 0.0) {
+        pieces.push(Interval::new(f64::NEG_INFINITY, 1.0 / a.lower));
+    }
+    if (a.upper > 0.0) {
+        pieces.push(Interval::new(1.0 / a.upper, f64::INFINITY));
+    }
+    return pieces;
+}
+
+fn absolute(a : Interval) -> Interval {
+    if (a.lower >= 0.0) {
+        return a;
+    }
+    if (a.upper <= 0.0) {
+        return Interval::new(-a.upper, -a.lower);
+    }
+    return Interval::new(0.0, a.upper.max(-a.lower));
+}
+
+fn sign(a : f64) -> f64 {
+    return if (a == 0.0) {0.0} else {a.signum()};
+}
+
+fn power(base : Interval, exponent : Interval, new_intervals : &mut EvaluatedIntervals) {
+    if (exponent.is_point() && exponent.lower.fract() == 0.0) {
+        whole_power(base, exponent.lower, new_intervals);
+        return;
+    }
+    // Positive bases. `bᵈ` only grows or shrinks along each of `b` and `d`,
+    // so the corners hold the extremes.
+    if (base.upper >= 0.0) {
+        let lower   = base.lower.max(0.0);
+        let corners = [
+            lower.powf(exponent.lower),      lower.powf(exponent.upper),
+            base.upper.powf(exponent.lower), base.upper.powf(exponent.upper)
+        ];
+        new_intervals.insert(Interval::new(
+            corners.iter().cloned().fold(f64::INFINITY, f64::min),
+            corners.iter().cloned().fold(f64::NEG_INFINITY, f64::max)
+        ));
+    }
+    // Negative bases are only defined for whole exponents.
+    if (base.lower < 0.0) {
+        let negative = Interval::new(base.lower, base.upper.min(0.0));
+        for_whole_numbers(exponent, new_intervals, |degree, new_intervals| whole_power(negative, degree, new_intervals));
+    }
+}
+
+fn whole_power(base : Interval, degree : f64, new_intervals : &mut EvaluatedIntervals) {
+    if (degree == 0.0) {
+        new_intervals.insert(Interval::point(1.0));
+        return;
+    }
+    // Even powers fold the negative side over, odd powers keep the order.
+    let base    = if (degree % 2.0 == 0.0) {absolute(base)} else {base};
+    let powered = Interval::new(base.lower.powf(degree.abs()), base.upper.powf(degree.abs()));
+    if (degree > 0.0) {
+        new_intervals.insert(powered);
+    } else {
+        for piece in reciprocal(powered) {
+            new_intervals.insert(piece);
+        }
+    }
+}
+
+// Call `target` with each whole number in `range`, or give up and allow
+// every value when there are too many.
+fn for_whole_numbers<T>(range : Interval, new_intervals : &mut EvaluatedIntervals, target : T)
+    where T : Fn(f64, &mut EvaluatedIntervals)
+{
+    let first = range.lower.ceil();
+    let last  = range.upper.floor();
+    if (! (first.is_finite() && last.is_finite()) || last - first >= MAX_WHOLE_EXPONENTS) {
+        new_intervals.insert(Interval::everything());
+        return;
+    }
+    let mut degree = first;
+    while (degree <= last) {
+        target(degree, new_intervals);
+        degree += 1.0;
+    }
+}
+
+// Whether `offset + period * k` is in `a` for some whole `k`.
+fn contains_repeating(a : Interval, offset : f64, period : f64) -> bool {
+    return offset + period * ((a.lower - offset) / period).ceil() <= a.upper;
+}
+
+// `sin` or `cos`, given where their peaks and troughs sit.
+fn periodic(a : Interval, function : fn(f64) -> f64, peak : f64, trough : f64) -> Interval {
+    if (! (a.lower.is_finite() && a.upper.is_finite()) || a.upper - a.lower >= var::TAU) {
+        return Interval::new(-1.0, 1.0);
+    }
+    let start = function(a.lower);
+    let end   = function(a.upper);
+    return Interval::new(
+        if (contains_repeating(a, trough, var::TAU)) {-1.0} else {start.min(end)},
+        if (contains_repeating(a, peak,   var::TAU)) {1.0}  else {start.max(end)}
+    );
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::parse::import::{self, Format};
+    use crate::parse::values::EvaluatedValues;
+
+    // `y = ...` over `x` in `[lower, upper]`, and at evenly spaced points across it.
+    fn evaluate(source : &str, lower : f64, upper : f64) -> (EvaluatedIntervals, Vec<f64>) {
+        let node = &import::parse(source, Format::Text).unwrap().nodes[0];
+        let mut variables = HashMap::new();
+        variables.insert(String::from("x"), EvaluatedIntervals::from(vec![Interval::new(lower, upper)]));
+        node.evaluate_intervals("y", &mut variables).unwrap();
+        let enclosure = variables.remove("y").unwrap();
+
+        let mut samples = vec![];
+        for i in 0..=200 {
+            let mut variables = HashMap::new();
+            variables.insert(String::from("x"), EvaluatedValues::from(vec![lower + (upper - lower) * i as f64 / 200.0]));
+            node.evaluate("y", &mut variables).unwrap();
+            samples.extend(variables["y"].get_values().iter().filter(|value| value.is_finite()));
+        }
+        return (enclosure, samples);
+    }
+
+    #[test]
+    fn enclosures_contain_every_value() {
+        let cases = [
+            ("y = x^2 - 2x",          -3.0, 1.5),
+            ("y = 1 / x",             -1.0, 2.0),
+            ("y = tan(x)",             1.0, 2.0),
+            ("y = sin(x) * cos(3x)",  -4.0, 4.0),
+            ("y = sqrt(x) + ln(x)",   -1.0, 3.0),
+            ("y = nthroot(3, x)",     -8.0, 8.0),
+            ("y = x^(-2)",            -0.5, 0.5),
+            ("y = mod(x, 1.5) + |x|", -3.0, 3.0),
+            ("y = sech(x) / csc(x)",  -2.0, 2.0)
+        ];
+        for (source, lower, upper) in cases {
+            let (enclosure, samples) = evaluate(source, lower, upper);
+            assert!(! samples.is_empty(), "`{}`", source);
+            for value in samples {
+                assert!(enclosure.contains(value), "`{}` gave {} outside {}", source, value, enclosure);
+            }
+        }
+    }
+
+    #[test]
+    fn poles_split_the_enclosure() {
+        // `1 / x` across zero never comes near zero.
+        let (enclosure, _) = evaluate("y = 1 / x", -1.0, 2.0);
+        assert_eq!(enclosure.get_intervals().len(), 2);
+        assert!(! enclosure.contains(0.0));
+        assert!(! enclosure.contains(-0.99) && ! enclosure.contains(0.49));
+        // Neither does `tan` across its pole at `pi / 2`.
+        let (enclosure, _) = evaluate("y = tan(x)", 1.0, 2.0);
+        assert_eq!(enclosure.get_intervals().len(), 2);
+        assert!(! enclosure.contains(0.0));
+        assert!(enclosure.contains(1e300) && enclosure.contains(-1e300));
+    }
+
+    #[test]
+    fn undefined_ranges_are_empty() {
+        assert!(evaluate("y = sqrt(x)", -3.0, -1.0).0.is_empty());
+        assert!(evaluate("y = ln(x)",   -3.0,  0.0).0.is_empty());
+        assert!(! evaluate("y = sqrt(x)", -3.0, 0.0).0.is_empty());
+    }
+}
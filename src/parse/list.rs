@@ -0,0 +1,698 @@
+// Lists, such as `[1, 2, 3]`, `[1...10]`, `[0, 0.5, ..., 5]` and
+// `[k x for k = [1...5]]`.
+//
+// Used as a number, a list stands for all of its elements at once, like
+// any other expression with several values, so `y = [1...5] x` draws a
+// family of five lines. Indexing and the list functions work on the
+// elements in order instead, repeats included:
+//
+//     a[2]             The second element, counting from 1. Nothing past either end.
+//     length(a)        Number of elements.
+//     total(a)         Sum of the elements.
+//     join(a, b, ...)  Elements of each list, one list after another.
+//     sort(a)          Elements in increasing order.
+//     unique(a)        Elements without repeats, each where it first appears.
+//     shuffle(a, s)    Elements in an order picked by the seed `s`, `0` if left out.
+//
+// Statistics such as `mean(a)` read a list the same way, see `parse::statistics`.
+//
+// A range steps from its first element by the gap to the second, or by 1
+// towards the last, up to the last without passing it. A comprehension
+// takes the body with the variable set to each element of the list in
+// turn, the first variable outermost when there are several.
+//
+// A list must have the same length at every point, so range bounds, the
+// elements of `unique` and the seed of `shuffle` can not depend on the
+// coordinates, see `render::dependency`.
+//
+// On import, list definitions are put in place wherever a list is read
+// element by element, and arithmetic on lists there is written as a
+// comprehension, so `length(2 a)` counts the elements of `[2 k for k = a]`.
+// As elsewhere, arithmetic on two lists takes every pair of elements.
+
+use std::collections::HashMap;
+
+use crate::error::Error;
+use crate::parse::node::{Node, NodeBase};
+use crate::parse::values::EvaluatedValues;
+use crate::parse::intervals::{Interval, EvaluatedIntervals};
+use crate::parse::complex::{Complex, EvaluatedComplex, ORDER_TOLERANCE};
+use crate::parse::statistics;
+
+
+// Most elements a list may have.
+pub const MAX_LENGTH : usize = 10000;
+// Slack for rounding when counting the steps of a range.
+const RANGE_TOLERANCE : f64 = 1e-9;
+
+
+// Values a list element may hold, for each kind of evaluation.
+pub trait Evaluated : Clone {
+    fn evaluate(node : &NodeBase, target_variable : &str, variables : &mut HashMap<String, Self>) -> Result<Self, Error>;
+    fn empty() -> Self;
+    fn number(value : f64) -> Self;
+    // Values of both, as `add`.
+    fn union(&self, other : &Self) -> Self;
+    fn addition(&self, other : &Self) -> Self;
+    fn subtraction(&self, other : &Self) -> Self;
+    fn multiplication(&self, other : &Self) -> Self;
+    fn division(&self, other : &Self) -> Self;
+    fn power(&self, other : &Self) -> Self;
+    fn square_root(&self) -> Self;
+    fn absolute_value(&self) -> Self;
+    fn sign(&self) -> Self;
+    fn minimum(&self, other : &Self) -> Self;
+    fn maximum(&self, other : &Self) -> Self;
+    // The value, if it is a single real number.
+    fn single(&self) -> Option<f64>;
+    // The values, if they are all real numbers, as `single`.
+    fn numbers(&self) -> Option<Vec<f64>>;
+    // Whether one of the values may be exactly `value`.
+    fn may_be(&self, value : f64) -> bool;
+    // Whether both hold the same values.
+    fn same(&self, other : &Self) -> bool;
+}
+
+impl Evaluated for EvaluatedValues {
+    fn evaluate(node : &NodeBase, target_variable : &str, variables : &mut HashMap<String, Self>) -> Result<Self, Error> {
+        return node.evaluate(target_variable, variables);
+    }
+    fn empty() -> Self {
+        return EvaluatedValues::new();
+    }
+    fn number(value : f64) -> Self {
+        return EvaluatedValues::from(vec![value]);
+    }
+    fn union(&self, other : &Self) -> Self {
+        return self.add(other);
+    }
+    fn addition(&self, other : &Self) -> Self {
+        return EvaluatedValues::addition(self, other);
+    }
+    fn subtraction(&self, other : &Self) -> Self {
+        return EvaluatedValues::subtraction(self, other);
+    }
+    fn multiplication(&self, other : &Self) -> Self {
+        return EvaluatedValues::multiplication(self, other);
+    }
+    fn division(&self, other : &Self) -> Self {
+        return EvaluatedValues::division(self, other);
+    }
+    fn power(&self, other : &Self) -> Self {
+        return EvaluatedValues::power(self, other);
+    }
+    fn square_root(&self) -> Self {
+        return EvaluatedValues::square_root(self);
+    }
+    fn absolute_value(&self) -> Self {
+        return EvaluatedValues::absolute_value(self);
+    }
+    fn sign(&self) -> Self {
+        return EvaluatedValues::sign(self);
+    }
+    fn minimum(&self, other : &Self) -> Self {
+        return EvaluatedValues::minimum(self, other);
+    }
+    fn maximum(&self, other : &Self) -> Self {
+        return EvaluatedValues::maximum(self, other);
+    }
+    fn single(&self) -> Option<f64> {
+        return match (self.get_values().as_slice()) {
+            [value] if (value.is_finite()) => Some(*value),
+            _                              => None
+        };
+    }
+    fn numbers(&self) -> Option<Vec<f64>> {
+        return Some(self.get_values().clone());
+    }
+    fn may_be(&self, value : f64) -> bool {
+        return self.get_values().contains(&value);
+    }
+    fn same(&self, other : &Self) -> bool {
+        return self.get_values().iter().all(|value| other.get_values().contains(value))
+            && other.get_values().iter().all(|value| self.get_values().contains(value));
+    }
+}
+
+impl Evaluated for EvaluatedIntervals {
+    fn evaluate(node : &NodeBase, target_variable : &str, variables : &mut HashMap<String, Self>) -> Result<Self, Error> {
+        return node.evaluate_intervals(target_variable, variables);
+    }
+    fn empty() -> Self {
+        return EvaluatedIntervals::new();
+    }
+    fn number(value : f64) -> Self {
+        return EvaluatedIntervals::from(vec![Interval::point(value)]);
+    }
+    fn union(&self, other : &Self) -> Self {
+        return self.add(other);
+    }
+    fn addition(&self, other : &Self) -> Self {
+        return EvaluatedIntervals::addition(self, other);
+    }
+    fn subtraction(&self, other : &Self) -> Self {
+        return EvaluatedIntervals::subtraction(self, other);
+    }
+    fn multiplication(&self, other : &Self) -> Self {
+        return EvaluatedIntervals::multiplication(self, other);
+    }
+    fn division(&self, other : &Self) -> Self {
+        return EvaluatedIntervals::division(self, other);
+    }
+    fn power(&self, other : &Self) -> Self {
+        return EvaluatedIntervals::power(self, other);
+    }
+    fn square_root(&self) -> Self {
+        return EvaluatedIntervals::square_root(self);
+    }
+    fn absolute_value(&self) -> Self {
+        return EvaluatedIntervals::absolute_value(self);
+    }
+    fn sign(&self) -> Self {
+        return EvaluatedIntervals::sign(self);
+    }
+    fn minimum(&self, other : &Self) -> Self {
+        return EvaluatedIntervals::minimum(self, other);
+    }
+    fn maximum(&self, other : &Self) -> Self {
+        return EvaluatedIntervals::maximum(self, other);
+    }
+    // The middle of a single interval, which is only widened by rounding
+    // where the value does not depend on the coordinates.
+    fn single(&self) -> Option<f64> {
+        return match (self.get_intervals().as_slice()) {
+            [interval] if (interval.lower.is_finite() && interval.upper.is_finite()) => Some((interval.lower + interval.upper) / 2.0),
+            _                                                                        => None
+        };
+    }
+    fn numbers(&self) -> Option<Vec<f64>> {
+        return self.get_intervals().iter()
+            .map(|interval| if (interval.lower.is_finite() && interval.upper.is_finite()) {Some((interval.lower + interval.upper) / 2.0)} else {None})
+            .collect();
+    }
+    fn may_be(&self, value : f64) -> bool {
+        return self.contains(value);
+    }
+    fn same(&self, other : &Self) -> bool {
+        return self.get_intervals() == other.get_intervals();
+    }
+}
+
+impl Evaluated for EvaluatedComplex {
+    fn evaluate(node : &NodeBase, target_variable : &str, variables : &mut HashMap<String, Self>) -> Result<Self, Error> {
+        return node.evaluate_complex(target_variable, variables);
+    }
+    fn empty() -> Self {
+        return EvaluatedComplex::new();
+    }
+    fn number(value : f64) -> Self {
+        return EvaluatedComplex::real(value);
+    }
+    fn union(&self, other : &Self) -> Self {
+        return self.add(other);
+    }
+    fn addition(&self, other : &Self) -> Self {
+        return EvaluatedComplex::addition(self, other);
+    }
+    fn subtraction(&self, other : &Self) -> Self {
+        return EvaluatedComplex::subtraction(self, other);
+    }
+    fn multiplication(&self, other : &Self) -> Self {
+        return EvaluatedComplex::multiplication(self, other);
+    }
+    fn division(&self, other : &Self) -> Self {
+        return EvaluatedComplex::division(self, other);
+    }
+    fn power(&self, other : &Self) -> Self {
+        return EvaluatedComplex::power(self, other);
+    }
+    fn square_root(&self) -> Self {
+        return EvaluatedComplex::square_root(self);
+    }
+    fn absolute_value(&self) -> Self {
+        return EvaluatedComplex::absolute_value(self);
+    }
+    fn sign(&self) -> Self {
+        return EvaluatedComplex::sign(self);
+    }
+    fn minimum(&self, other : &Self) -> Self {
+        return EvaluatedComplex::minimum(self, other);
+    }
+    fn maximum(&self, other : &Self) -> Self {
+        return EvaluatedComplex::maximum(self, other);
+    }
+    // Only values that are nearly real, see `complex::ORDER_TOLERANCE`.
+    fn single(&self) -> Option<f64> {
+        return match (self.get_values().as_slice()) {
+            [value] if (value.is_finite() && value.is_nearly_real(ORDER_TOLERANCE)) => Some(value.re),
+            _                                                                      => None
+        };
+    }
+    fn numbers(&self) -> Option<Vec<f64>> {
+        return self.get_values().iter()
+            .map(|value| if (value.is_nearly_real(ORDER_TOLERANCE)) {Some(value.re)} else {None})
+            .collect();
+    }
+    fn may_be(&self, value : f64) -> bool {
+        return self.get_values().iter().any(|a| a.re == value && a.is_nearly_real(ORDER_TOLERANCE));
+    }
+    fn same(&self, other : &Self) -> bool {
+        let contains = |values : &EvaluatedComplex, a : &Complex| values.get_values().iter().any(|b| a.re == b.re && a.im == b.im);
+        return self.get_values().iter().all(|a| contains(other, a)) && other.get_values().iter().all(|a| contains(self, a));
+    }
+}
+
+
+// Values of each element of a list, in order. Anything that is not a list
+// is a list of one element.
+pub fn elements<T : Evaluated>(node : &NodeBase, target_variable : &str, variables : &mut HashMap<String, T>) -> Result<Vec<T>, Error> {
+    macro elements {
+        ($from:expr) => {elements(&($from).base, target_variable, variables)?}
+    }
+
+    let list = match (node) {
+        NodeBase::MultiValue (values) => {
+            let mut list = vec![];
+            for value in values {
+                list.append(&mut elements!(value));
+            }
+            list
+        },
+        NodeBase::Range (first, second, last) => {
+            let first  = single(node, &T::evaluate(&first.base, target_variable, variables)?)?;
+            let second = match (second) {
+                Some(second) => Some(single(node, &T::evaluate(&second.base, target_variable, variables)?)?),
+                None         => None
+            };
+            let last   = single(node, &T::evaluate(&last.base, target_variable, variables)?)?;
+            range(node, first, second, last)?.into_iter().map(T::number).collect()
+        },
+        NodeBase::For (body, name, list) => {
+            let list     = elements!(list);
+            let previous = variables.remove(name);
+            let mut body_elements = vec![];
+            let mut failure       = None;
+            for element in list {
+                variables.insert(name.clone(), element);
+                match (elements(&body.base, target_variable, variables)) {
+                    Ok(mut each) => body_elements.append(&mut each),
+                    Err(error)   => {
+                        failure = Some(error);
+                        break;
+                    }
+                };
+            }
+            // The variable is only bound inside the body.
+            match (previous) {
+                Some(previous) => variables.insert(name.clone(), previous),
+                None           => variables.remove(name)
+            };
+            if let Some(error) = failure {
+                return Err(error);
+            }
+            body_elements
+        },
+        NodeBase::Join (lists) => {
+            let mut list = vec![];
+            for each in lists {
+                list.append(&mut elements!(each));
+            }
+            list
+        },
+        NodeBase::Sort (list) => {
+            let mut list = elements!(list);
+            let numbers  = list.iter().map(|element| element.single()).collect::<Option<Vec<f64>>>();
+            match (numbers) {
+                Some(mut numbers) => {
+                    numbers.sort_by(|a, b| a.total_cmp(b));
+                    numbers.into_iter().map(T::number).collect()
+                },
+                // Elements with several values are sorted value by value.
+                None => {
+                    for (a, b) in sorting_network(list.len()) {
+                        let (lower, upper) = (list[a].minimum(&list[b]), list[a].maximum(&list[b]));
+                        list[a] = lower;
+                        list[b] = upper;
+                    }
+                    list
+                }
+            }
+        },
+        NodeBase::Unique (list) => {
+            let mut unique : Vec<T> = vec![];
+            for element in elements!(list) {
+                if (! unique.iter().any(|kept| kept.same(&element))) {
+                    unique.push(element);
+                }
+            }
+            unique
+        },
+        NodeBase::Shuffle (list, seed) => {
+            let list = elements!(list);
+            let seed = match (seed) {
+                Some(seed) => match (T::evaluate(&seed.base, target_variable, variables)?.single()) {
+                    Some(seed) => seed,
+                    None       => return Err(Error::ShuffleSeed(node.to_string()))
+                },
+                None => 0.0
+            };
+            permutation(list.len(), seed).into_iter().map(|i| list[i].clone()).collect()
+        },
+        NodeBase::Stats (list) => statistics::evaluate_summary(elements!(list)),
+        _ => vec![T::evaluate(node, target_variable, variables)?]
+    };
+    if (list.len() > MAX_LENGTH) {
+        return Err(Error::ListLength(node.to_string()));
+    }
+    return Ok(list);
+}
+
+// Values of every element together.
+pub fn union<T : Evaluated>(list : Vec<T>) -> T {
+    return list.iter().fold(T::empty(), |all, element| all.union(element));
+}
+
+// Values of each element whose position, counting from 1, the index may be.
+pub fn index<T : Evaluated>(list : Vec<T>, index : &T) -> T {
+    return union(list.into_iter().enumerate().filter(|(i, _)| index.may_be((i + 1) as f64)).map(|(_, element)| element).collect());
+}
+
+pub fn length<T : Evaluated>(list : Vec<T>) -> T {
+    return T::number(list.len() as f64);
+}
+
+pub fn total<T : Evaluated>(list : Vec<T>) -> T {
+    return list.iter().fold(T::number(0.0), |total, element| total.addition(element));
+}
+
+// Elements of a range, checked against `MAX_LENGTH`.
+pub fn range(node : &NodeBase, first : f64, second : Option<f64>, last : f64) -> Result<Vec<f64>, Error> {
+    let step = match (second) {
+        Some(second) => second - first,
+        None         => if (last >= first) {1.0} else {-1.0}
+    };
+    if (step == 0.0 || ! step.is_finite()) {
+        return Err(Error::RangeBounds(node.to_string()));
+    }
+    let steps = (last - first) / step;
+    if (steps < -RANGE_TOLERANCE) {
+        return Ok(vec![]);
+    }
+    let steps = (steps + RANGE_TOLERANCE).floor();
+    if (steps >= MAX_LENGTH as f64) {
+        return Err(Error::ListLength(node.to_string()));
+    }
+    return Ok((0..=(steps as usize)).map(|i| first + step * (i as f64)).collect());
+}
+
+// Pairs of positions to put in order, one pair after another, so that any
+// list of the length ends up sorted. Batcher's odd-even merge sort, for
+// elements that can not simply be compared.
+pub fn sorting_network(length : usize) -> Vec<(usize, usize)> {
+    let mut pairs = vec![];
+    let mut p = 1;
+    while (p < length) {
+        let mut k = p;
+        while (k >= 1) {
+            let mut j = k % p;
+            while (j + k < length) {
+                for i in 0..k.min(length - j - k) {
+                    if ((i + j) / (p * 2) == (i + j + k) / (p * 2)) {
+                        pairs.push((i + j, i + j + k));
+                    }
+                }
+                j += k * 2;
+            }
+            k /= 2;
+        }
+        p *= 2;
+    }
+    return pairs;
+}
+
+// Order of the elements after a shuffle, the same for the same length and seed.
+pub fn permutation(length : usize, seed : f64) -> Vec<usize> {
+    let mut order = (0..length).collect::<Vec<usize>>();
+    let mut state = seed.to_bits();
+    for i in (1..length).rev() {
+        // SplitMix64
+        state = state.wrapping_add(0x9E3779B97F4A7C15);
+        let mut random = state;
+        random = (random ^ (random >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        random = (random ^ (random >> 27)).wrapping_mul(0x94D049BB133111EB);
+        random ^= random >> 31;
+        order.swap(i, (random % (i as u64 + 1)) as usize);
+    }
+    return order;
+}
+
+fn single<T : Evaluated>(node : &NodeBase, value : &T) -> Result<f64, Error> {
+    return match (value.single()) {
+        Some(value) => Ok(value),
+        None        => Err(Error::RangeBounds(node.to_string()))
+    };
+}
+
+
+// Put list definitions in place wherever a list is read element by element.
+pub fn inline(mut nodes : Vec<Node>) -> Vec<Node> {
+    let mut lists = HashMap::new();
+    // A definition may name another list, so look until no more are found.
+    loop {
+        let mut found = false;
+        for node in &nodes {
+            if let NodeBase::Equals(left, right) = &node.base {
+                if let NodeBase::Variable(name) = &left.base {
+                    if (! lists.contains_key(name) && is_list(right, &|name| lists.contains_key(name))) {
+                        lists.insert(name.clone(), (**right).clone());
+                        found = true;
+                    }
+                }
+            }
+        }
+        if (! found) {
+            break;
+        }
+    }
+    let mut inliner = Inliner {
+        lists     : &lists,
+        bound     : vec![],
+        expanding : vec![],
+        fresh     : 0
+    };
+    for node in &mut nodes {
+        inliner.visit(node);
+    }
+    return nodes;
+}
+
+// Whether a node is a list, given which variables name lists.
+pub fn is_list(node : &Node, named : &dyn Fn(&str) -> bool) -> bool {
+    return match (&node.base) {
+        NodeBase::Variable(name) => named(name),
+        base if (base.is_list()) => true,
+        base if (base.is_condition() || base.relation().is_some()) => false,
+        NodeBase::Index     (_, _)    |
+        NodeBase::Length    (_)       |
+        NodeBase::Total     (_)       |
+        NodeBase::Statistic (_, _)    |
+        NodeBase::Quantile  (_, _)    |
+        NodeBase::Quartile  (_, _)    |
+        NodeBase::Paired    (_, _, _) |
+        NodeBase::Piecewise (_, _)    |
+        NodeBase::Point     (_, _)    |
+        NodeBase::Call      (_, _)    => false,
+        // Arithmetic and functions of a list take each element.
+        base => base.children().iter().any(|child| is_list(child, named))
+    };
+}
+
+
+struct Inliner<'l> {
+    lists     : &'l HashMap<String, Node>,
+    // Names bound by the comprehensions around the node, innermost last.
+    bound     : Vec<String>,
+    // List definitions being put in place, so one that reads itself stops.
+    // It is reported as a cyclic definition, see `render::dependency`.
+    expanding : Vec<String>,
+    // Number of comprehension variables made so far, named `#0`, `#1`, ...
+    fresh     : usize
+}
+impl<'l> Inliner<'l> {
+    // A node used as a number, looking for lists read element by element inside it.
+    fn visit(&mut self, node : &mut Node) {
+        match (&mut node.base) {
+            NodeBase::Index (list, index) => {
+                self.lift(list);
+                self.visit(index);
+            },
+            NodeBase::Length    (list)    |
+            NodeBase::Total     (list)    |
+            NodeBase::Sort      (list)    |
+            NodeBase::Unique    (list)    |
+            NodeBase::Statistic (_, list) |
+            NodeBase::Stats     (list)    => self.lift(list),
+            NodeBase::Quantile (list, fraction) |
+            NodeBase::Quartile (list, fraction) => {
+                self.lift(list);
+                self.visit(fraction);
+            },
+            NodeBase::Paired (_, a, b) => {
+                self.lift(a);
+                self.lift(b);
+            },
+            NodeBase::Shuffle (list, seed) => {
+                self.lift(list);
+                if let Some(seed) = seed {
+                    self.visit(seed);
+                }
+            },
+            NodeBase::Join (lists) => {
+                for list in lists {
+                    self.lift(list);
+                }
+            },
+            NodeBase::For (body, name, list) => {
+                self.lift(list);
+                self.bound.push(name.clone());
+                self.lift(body);
+                self.bound.pop();
+            },
+            base => {
+                for child in base.children_mut() {
+                    self.visit(child);
+                }
+            }
+        };
+    }
+
+    // A node read element by element, with list definitions put in place
+    // and arithmetic on a list written as a comprehension over it.
+    fn lift(&mut self, node : &mut Node) {
+        if let NodeBase::Variable(name) = &node.base {
+            if (! self.bound.contains(name) && ! self.expanding.contains(name)) {
+                if let Some(list) = self.lists.get(name) {
+                    let name = name.clone();
+                    *node = list.clone();
+                    self.expanding.push(name);
+                    self.lift(node);
+                    self.expanding.pop();
+                }
+            }
+            return;
+        }
+        if (! node.base.is_list() && is_list(node, &|name| self.is_list_name(name))) {
+            let name = format!("#{}", self.fresh);
+            self.fresh += 1;
+            let mut body = node.clone();
+            let list = take_list(&mut body, &name, &|name| self.is_list_name(name)).expect("a list is read");
+            *node = Node {base: NodeBase::For(Box::new(body), name, Box::new(list))};
+        }
+        match (&mut node.base) {
+            NodeBase::MultiValue (values) => {
+                for value in values {
+                    self.lift(value);
+                }
+            },
+            _ => self.visit(node)
+        };
+    }
+
+    fn is_list_name(&self, name : &str) -> bool {
+        return self.lists.contains_key(name) && ! self.bound.iter().any(|bound| bound == name);
+    }
+}
+
+// Swap the first list read in an expression for a variable, giving back the list.
+fn take_list(node : &mut Node, name : &str, named : &dyn Fn(&str) -> bool) -> Option<Node> {
+    if (! is_list(node, named)) {
+        return None;
+    }
+    if (node.base.is_list() || matches!(node.base, NodeBase::Variable(_))) {
+        return Some(std::mem::replace(node, Node {base: NodeBase::Variable(String::from(name))}));
+    }
+    for child in node.base.children_mut() {
+        if let Some(list) = take_list(child, name, named) {
+            return Some(list);
+        }
+    }
+    return None;
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::bytecode::tests::evaluate_both;
+
+    // Values of `y` at `x = -5`, the same walking the node trees and compiled.
+    fn values(source : &str) -> Vec<f64> {
+        let (tree, compiled) = evaluate_both(source, -5.0);
+        assert_eq!(tree, compiled, "`{}`", source);
+        return tree;
+    }
+
+    #[test]
+    fn ranges_step_towards_the_end() {
+        assert_eq!(values("y = [1...5]"),    [1.0, 2.0, 3.0, 4.0, 5.0]);
+        assert_eq!(values("y = [1, 3...9]"), [1.0, 3.0, 5.0, 7.0, 9.0]);
+        assert_eq!(values("y = [5...1]"),    [5.0, 4.0, 3.0, 2.0, 1.0]);
+        assert_eq!(range(&NodeBase::Number(0.0), 0.0, Some(0.1), 0.3).unwrap().len(), 4);
+        assert_eq!(range(&NodeBase::Number(0.0), 1.0, Some(2.0), 0.0).unwrap(), Vec::<f64>::new());
+        assert!(matches!(range(&NodeBase::Number(0.0), 1.0, Some(1.0), 5.0), Err(Error::RangeBounds(_))));
+        assert!(matches!(range(&NodeBase::Number(0.0), 0.0, None, MAX_LENGTH as f64), Err(Error::ListLength(_))));
+    }
+
+    #[test]
+    fn comprehensions_run_over_every_combination() {
+        assert_eq!(values("y = [n^2 for n = [1...4]]"),                [1.0, 4.0, 9.0, 16.0]);
+        assert_eq!(values("y = [n + m for n = [1, 2], m = [10, 20]]"), [11.0, 21.0, 12.0, 22.0]);
+        assert_eq!(values("y = [1...3] + x"),                          [-4.0, -3.0, -2.0]);
+    }
+
+    #[test]
+    fn indexing_counts_from_one() {
+        assert_eq!(values("y = [10, 20, 30][2]"),               [20.0]);
+        assert_eq!(values("y = [10, 20][3]"),                   Vec::<f64>::new());
+        assert_eq!(values("a = [1...3]\ny = a[2] + length(a)"), [5.0]);
+    }
+
+    #[test]
+    fn list_functions_keep_repeated_elements() {
+        assert_eq!(values("y = length([1, 1, 2])"),                [3.0]);
+        assert_eq!(values("y = total([2, 2])"),                    [4.0]);
+        assert_eq!(values("a = [1...3]\nb = a * 2\ny = total(b)"), [12.0]);
+        assert_eq!(values("y = sort([3, 1, 2])"),                  [1.0, 2.0, 3.0]);
+        assert_eq!(values("y = sort([x, 0])"),                     [-5.0, 0.0]);
+        assert_eq!(values("y = unique([3, 1, 3, 2])"),             [3.0, 1.0, 2.0]);
+        assert_eq!(values("y = join([1, 2], [3])"),                [1.0, 2.0, 3.0]);
+        assert_eq!(values("y = min([4, 2, 8])"),                   [2.0]);
+        assert_eq!(values("y = max([4, 2, 8])"),                   [8.0]);
+    }
+
+    #[test]
+    fn sorting_networks_sort_every_order() {
+        for length in 0..9 {
+            let pairs = sorting_network(length);
+            for seed in 0..20 {
+                let mut list = permutation(length, seed as f64);
+                for (a, b) in &pairs {
+                    if (list[*a] > list[*b]) {
+                        list.swap(*a, *b);
+                    }
+                }
+                assert_eq!(list, (0..length).collect::<Vec<usize>>());
+            }
+        }
+    }
+
+    #[test]
+    fn shuffles_depend_only_on_the_seed() {
+        assert_eq!(permutation(5, 3.0), permutation(5, 3.0));
+        assert_ne!(permutation(8, 3.0), permutation(8, 4.0));
+        let mut order = permutation(8, 3.0);
+        order.sort();
+        assert_eq!(order, (0..8).collect::<Vec<usize>>());
+        assert_eq!(values("y = shuffle([1...5], 3)"), [4.0, 1.0, 3.0, 5.0, 2.0]);
+    }
+}
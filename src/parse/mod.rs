@@ -2,3 +2,9 @@ pub mod node;
 pub mod import;
 pub mod var;
 pub mod values;
+pub mod intervals;
+pub mod complex;
+pub mod function;
+pub mod bytecode;
+pub mod list;
+pub mod statistics;
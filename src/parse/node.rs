@@ -1,33 +1,51 @@
 use std::collections::HashMap;
-use std::process;
-
-use loggerithm::{logger, log};
-use loggerithm::level::ERROR;
-logger!(super);
+use std::fmt;
 
+use crate::error::Error;
 use crate::parse::values::EvaluatedValues;
+use crate::parse::intervals::{Interval, EvaluatedIntervals};
+use crate::parse::complex::{Complex, EvaluatedComplex, ORDER_TOLERANCE};
+use crate::parse::list;
+use crate::parse::statistics::{self, Statistic, Paired};
 
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Node {
     pub base : NodeBase,
 }
+impl fmt::Display for Node {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(f, "{}", self.base);
+    }
+}
 impl Node {
     pub fn new(base : NodeBase) -> Box<Node> {
         return Box::new(Node {
             base
         });
     }
-    pub fn to_string(&self) -> String {
-        return self.base.to_string();
-    }
-    pub fn evaluate(&self, target_variable : &String, variables : &mut HashMap<String, EvaluatedValues>) -> EvaluatedValues {
+    pub fn evaluate(&self, target_variable : &str, variables : &mut HashMap<String, EvaluatedValues>) -> Result<EvaluatedValues, Error> {
         return self.base.evaluate(target_variable, variables);
     }
+    pub fn evaluate_intervals(&self, target_variable : &str, variables : &mut HashMap<String, EvaluatedIntervals>) -> Result<EvaluatedIntervals, Error> {
+        return self.base.evaluate_intervals(target_variable, variables);
+    }
+    pub fn evaluate_complex(&self, target_variable : &str, variables : &mut HashMap<String, EvaluatedComplex>) -> Result<EvaluatedComplex, Error> {
+        return self.base.evaluate_complex(target_variable, variables);
+    }
+    // Whether the variable is read anywhere in this tree. A comprehension
+    // hides a variable of the same name in its body.
+    pub fn contains_variable(&self, name : &str) -> bool {
+        return match (&self.base) {
+            NodeBase::Variable (variable)           => variable == name,
+            NodeBase::For      (body, bound, list) => list.contains_variable(name) || (bound != name && body.contains_variable(name)),
+            base                                    => base.children().iter().any(|child| child.contains_variable(name))
+        };
+    }
 }
 
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum NodeBase {
     
     Addition       (Box<Node>, Box<Node>), // Left (l), Right (r)  : l + r
@@ -69,68 +87,329 @@ pub enum NodeBase {
     MultiValue (Vec<Box<Node>>),
     Number     (f64),
     Variable   (String),
+    Call       (String, Vec<Box<Node>>), // Name, Arguments : A user defined function, see `parse::function`.
+    Point      (Box<Node>, Box<Node>),   // X, Y : (x, y), a parametric curve when it reads `t`.
+    Piecewise  (Vec<(Box<Node>, Box<Node>)>, Option<Box<Node>>), // Condition and Value of each branch, Otherwise : {c: v, ..., o}
 
-    Equals (Box<Node>, Box<Node>), // Left, Right : Left = Right
+    // Lists, see `parse::list`.
+    Range   (Box<Node>, Option<Box<Node>>, Box<Node>), // First, Second, Last : [first, second, ..., last]
+    For     (Box<Node>, String, Box<Node>),            // Body, Variable, List : [body for variable = list]
+    Index   (Box<Node>, Box<Node>),                    // List, Index         : list[index]
+    Length  (Box<Node>),
+    Total   (Box<Node>),
+    Join    (Vec<Box<Node>>),
+    Sort    (Box<Node>),
+    Unique  (Box<Node>),
+    Shuffle (Box<Node>, Option<Box<Node>>),            // List, Seed
+
+    // Statistics, see `parse::statistics`.
+    Statistic (Statistic, Box<Node>),         // Statistic, List : mean(list), and so on
+    Quantile  (Box<Node>, Box<Node>),         // List, Fraction
+    Quartile  (Box<Node>, Box<Node>),         // List, Quartile
+    Paired    (Paired, Box<Node>, Box<Node>), // Statistic, List, List : cov(a, b), and so on
+    Stats     (Box<Node>),
+
+    // Conditions, a single `1` where they hold and no values where they do not.
+    Compare (Relation, Box<Node>, Box<Node>), // Relation, Left, Right : Left < Right, and so on
+    And     (Box<Node>, Box<Node>),
+    Or      (Box<Node>, Box<Node>),
+    Not     (Box<Node>),
+
+    Equals       (Box<Node>, Box<Node>), // Left, Right : Left = Right
+    Less         (Box<Node>, Box<Node>), // Left, Right : Left < Right
+    LessEqual    (Box<Node>, Box<Node>), // Left, Right : Left ≤ Right
+    Greater      (Box<Node>, Box<Node>), // Left, Right : Left > Right
+    GreaterEqual (Box<Node>, Box<Node>), // Left, Right : Left ≥ Right
     
 }
-impl NodeBase {
-    pub fn to_string(&self) -> String {
-        return match (self) {
+impl fmt::Display for NodeBase {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(f, "{}", match (self) {
+
+            NodeBase::Addition       (left, right) => format!("({} + {})", left, right),
+            NodeBase::Subtraction    (left, right) => format!("({} - {})", left, right),
+            NodeBase::Multiplication (left, right) => format!("({} * {})", left, right),
+            NodeBase::Division       (left, right) => format!("({} / {})", left, right),
+            NodeBase::Power          (left, right) => format!("({} ^ {})", left, right),
 
-            NodeBase::Addition       (left, right) => format!("({} + {})", (*left).to_string(), (*right).to_string()),
-            NodeBase::Subtraction    (left, right) => format!("({} - {})", (*left).to_string(), (*right).to_string()),
-            NodeBase::Multiplication (left, right) => format!("({} * {})", (*left).to_string(), (*right).to_string()),
-            NodeBase::Division       (left, right) => format!("({} / {})", (*left).to_string(), (*right).to_string()),
-            NodeBase::Power          (left, right) => format!("({} ^ {})", (*left).to_string(), (*right).to_string()),
-
-            NodeBase::AbsoluteValue       (arg)  => format!("|{}|", arg.to_string()),
-            NodeBase::SquareRoot          (arg)  => format!("sqrt({})", arg.to_string()),
-            NodeBase::NthRoot             (n, p) => format!("nthroot({}, {})", n.to_string(), p.to_string()),
-            NodeBase::Sine                (arg)  => format!("sin({})", arg.to_string()),
-            NodeBase::Cosine              (arg)  => format!("cos({})", arg.to_string()),
-            NodeBase::Tangent             (arg)  => format!("tan({})", arg.to_string()),
-            NodeBase::Cosecant            (arg)  => format!("scs({})", arg.to_string()),
-            NodeBase::Secant              (arg)  => format!("sec({})", arg.to_string()),
-            NodeBase::Cotangent           (arg)  => format!("cot({})", arg.to_string()),
-            NodeBase::InverseSine         (arg)  => format!("asin({})", arg.to_string()),
-            NodeBase::InverseCosine       (arg)  => format!("acos({})", arg.to_string()),
-            NodeBase::InverseTangent      (arg)  => format!("atan({})", arg.to_string()),
-            NodeBase::InverseCosecant     (arg)  => format!("ascs({})", arg.to_string()),
-            NodeBase::InverseSecant       (arg)  => format!("asec({})", arg.to_string()),
-            NodeBase::InverseCotangent    (arg)  => format!("acot({})", arg.to_string()),
-            NodeBase::HyperbolicSine      (arg)  => format!("sinh({})", arg.to_string()),
-            NodeBase::HyperbolicCosine    (arg)  => format!("cosh({})", arg.to_string()),
-            NodeBase::HyperbolicTangent   (arg)  => format!("tanh({})", arg.to_string()),
-            NodeBase::HyperbolicCosecant  (arg)  => format!("scsh({})", arg.to_string()),
-            NodeBase::HyperbolicSecant    (arg)  => format!("sech({})", arg.to_string()),
-            NodeBase::HyperbolicCotangent (arg)  => format!("coth({})", arg.to_string()),
-            NodeBase::Exponential         (arg)  => format!("exp({})", arg.to_string()),
-            NodeBase::NaturalLogarithm    (arg)  => format!("ln({})", arg.to_string()),
-            NodeBase::Logartithm          (b, r) => format!("log({}, {})", b.to_string(), r.to_string()),
-            NodeBase::Modulo              (a, b) => format!("mod({}, {})", a.to_string(), b.to_string()),
-            NodeBase::Ceiling             (arg)  => format!("ceil({})", arg.to_string()),
-            NodeBase::Floor               (arg)  => format!("floor({})", arg.to_string()),
-            NodeBase::Round               (arg)  => format!("round({})", arg.to_string()),
-            NodeBase::Sign                (arg)  => format!("sign({})", arg.to_string()),
+            NodeBase::AbsoluteValue       (arg)  => format!("|{}|", arg),
+            NodeBase::SquareRoot          (arg)  => format!("sqrt({})", arg),
+            NodeBase::NthRoot             (n, p) => format!("nthroot({}, {})", n, p),
+            NodeBase::Sine                (arg)  => format!("sin({})", arg),
+            NodeBase::Cosine              (arg)  => format!("cos({})", arg),
+            NodeBase::Tangent             (arg)  => format!("tan({})", arg),
+            NodeBase::Cosecant            (arg)  => format!("csc({})", arg),
+            NodeBase::Secant              (arg)  => format!("sec({})", arg),
+            NodeBase::Cotangent           (arg)  => format!("cot({})", arg),
+            NodeBase::InverseSine         (arg)  => format!("asin({})", arg),
+            NodeBase::InverseCosine       (arg)  => format!("acos({})", arg),
+            NodeBase::InverseTangent      (arg)  => format!("atan({})", arg),
+            NodeBase::InverseCosecant     (arg)  => format!("acsc({})", arg),
+            NodeBase::InverseSecant       (arg)  => format!("asec({})", arg),
+            NodeBase::InverseCotangent    (arg)  => format!("acot({})", arg),
+            NodeBase::HyperbolicSine      (arg)  => format!("sinh({})", arg),
+            NodeBase::HyperbolicCosine    (arg)  => format!("cosh({})", arg),
+            NodeBase::HyperbolicTangent   (arg)  => format!("tanh({})", arg),
+            NodeBase::HyperbolicCosecant  (arg)  => format!("csch({})", arg),
+            NodeBase::HyperbolicSecant    (arg)  => format!("sech({})", arg),
+            NodeBase::HyperbolicCotangent (arg)  => format!("coth({})", arg),
+            NodeBase::Exponential         (arg)  => format!("exp({})", arg),
+            NodeBase::NaturalLogarithm    (arg)  => format!("ln({})", arg),
+            NodeBase::Logartithm          (b, r) => format!("log({}, {})", b, r),
+            NodeBase::Modulo              (a, b) => format!("mod({}, {})", a, b),
+            NodeBase::Ceiling             (arg)  => format!("ceil({})", arg),
+            NodeBase::Floor               (arg)  => format!("floor({})", arg),
+            NodeBase::Round               (arg)  => format!("round({})", arg),
+            NodeBase::Sign                (arg)  => format!("sign({})", arg),
 
             NodeBase::MultiValue        (values)      => {
-                let mut string = vec![];
-                for i in 0..values.len() {
-                    string.push((*values[i]).to_string());
-                }
-                format!("[{}]", string.join(", "))
+                format!("[{}]", values.iter().map(|value| value.to_string()).collect::<Vec<String>>().join(", "))
             },
             NodeBase::Number            (value)       => value.to_string(),
             NodeBase::Variable          (name)        => String::from(name),
+            NodeBase::Call              (name, args)  => {
+                format!("{}({})", name, args.iter().map(|arg| arg.to_string()).collect::<Vec<String>>().join(", "))
+            },
+            NodeBase::Point             (x, y)        => format!("({}, {})", x, y),
+            NodeBase::Piecewise         (branches, otherwise) => {
+                let mut parts = branches.iter()
+                    .map(|(condition, value)| format!("{}: {}", condition, value))
+                    .collect::<Vec<String>>();
+                if let Some(otherwise) = otherwise {
+                    parts.push(otherwise.to_string());
+                }
+                format!("{{{}}}", parts.join(", "))
+            },
+
+            NodeBase::Range   (first, second, last) => match (second) {
+                Some(second) => format!("[{}, {}, ..., {}]", first, second, last),
+                None         => format!("[{}...{}]", first, last)
+            },
+            NodeBase::For     (body, name, list)    => format!("[{} for {} = {}]", body, name, list),
+            NodeBase::Index   (list, index)         => format!("{}[{}]", list, index),
+            NodeBase::Length  (list)                => format!("length({})", list),
+            NodeBase::Total   (list)                => format!("total({})", list),
+            NodeBase::Join    (lists)               => {
+                format!("join({})", lists.iter().map(|list| list.to_string()).collect::<Vec<String>>().join(", "))
+            },
+            NodeBase::Sort    (list)                => format!("sort({})", list),
+            NodeBase::Unique  (list)                => format!("unique({})", list),
+            NodeBase::Shuffle (list, seed)          => match (seed) {
+                Some(seed) => format!("shuffle({}, {})", list, seed),
+                None       => format!("shuffle({})", list)
+            },
+
+            NodeBase::Statistic (statistic, list)  => format!("{}({})", statistic.name(), list),
+            NodeBase::Quantile  (list, fraction)   => format!("quantile({}, {})", list, fraction),
+            NodeBase::Quartile  (list, quartile)   => format!("quartile({}, {})", list, quartile),
+            NodeBase::Paired    (paired, a, b)     => format!("{}({}, {})", paired.name(), a, b),
+            NodeBase::Stats     (list)             => format!("stats({})", list),
+
+            NodeBase::Compare (relation, left, right) => format!("({} {} {})", left, relation.symbol(), right),
+            NodeBase::And     (left, right)           => format!("({} and {})", left, right),
+            NodeBase::Or      (left, right)           => format!("({} or {})", left, right),
+            NodeBase::Not     (arg)                   => format!("(not {})", arg),
+
+            NodeBase::Equals       (left, right) => format!("({} = {})", left, right),
+            NodeBase::Less         (left, right) => format!("({} < {})", left, right),
+            NodeBase::LessEqual    (left, right) => format!("({} <= {})", left, right),
+            NodeBase::Greater      (left, right) => format!("({} > {})", left, right),
+            NodeBase::GreaterEqual (left, right) => format!("({} >= {})", left, right)
+
+        });
+    }
+}
+impl NodeBase {
+
+    pub fn children(&self) -> Vec<&Node> {
+        return match (self) {
+
+            NodeBase::Addition       (left, right) |
+            NodeBase::Subtraction    (left, right) |
+            NodeBase::Multiplication (left, right) |
+            NodeBase::Division       (left, right) |
+            NodeBase::Power          (left, right) => vec![left, right],
+
+            NodeBase::NthRoot    (a, b) |
+            NodeBase::Logartithm (a, b) |
+            NodeBase::Modulo     (a, b) => vec![a, b],
+
+            NodeBase::AbsoluteValue       (arg) |
+            NodeBase::SquareRoot          (arg) |
+            NodeBase::Sine                (arg) |
+            NodeBase::Cosine              (arg) |
+            NodeBase::Tangent             (arg) |
+            NodeBase::Cosecant            (arg) |
+            NodeBase::Secant              (arg) |
+            NodeBase::Cotangent           (arg) |
+            NodeBase::InverseSine         (arg) |
+            NodeBase::InverseCosine       (arg) |
+            NodeBase::InverseTangent      (arg) |
+            NodeBase::InverseCosecant     (arg) |
+            NodeBase::InverseSecant       (arg) |
+            NodeBase::InverseCotangent    (arg) |
+            NodeBase::HyperbolicSine      (arg) |
+            NodeBase::HyperbolicCosine    (arg) |
+            NodeBase::HyperbolicTangent   (arg) |
+            NodeBase::HyperbolicCosecant  (arg) |
+            NodeBase::HyperbolicSecant    (arg) |
+            NodeBase::HyperbolicCotangent (arg) |
+            NodeBase::Exponential         (arg) |
+            NodeBase::NaturalLogarithm    (arg) |
+            NodeBase::Ceiling             (arg) |
+            NodeBase::Floor               (arg) |
+            NodeBase::Round               (arg) |
+            NodeBase::Sign                (arg) => vec![arg],
+
+            NodeBase::MultiValue (values)  => values.iter().map(|value| value.as_ref()).collect(),
+            NodeBase::Number     (_)       => vec![],
+            NodeBase::Variable   (_)       => vec![],
+            NodeBase::Call       (_, args) => args.iter().map(|arg| arg.as_ref()).collect(),
+
+            NodeBase::Point      (x, y)    => vec![x, y],
+            NodeBase::Piecewise  (branches, otherwise) => {
+                branches.iter().flat_map(|(condition, value)| [condition.as_ref(), value.as_ref()]).chain(otherwise.as_deref()).collect()
+            },
+
+            NodeBase::Range   (first, second, last) => [first.as_ref()].into_iter().chain(second.as_deref()).chain([last.as_ref()]).collect(),
+            NodeBase::For     (body, _, list)       => vec![body, list],
+            NodeBase::Index   (list, index)         => vec![list, index],
+            NodeBase::Length  (list) |
+            NodeBase::Total   (list) |
+            NodeBase::Sort    (list) |
+            NodeBase::Unique  (list)                => vec![list],
+            NodeBase::Join    (lists)               => lists.iter().map(|list| list.as_ref()).collect(),
+            NodeBase::Shuffle (list, seed)          => [list.as_ref()].into_iter().chain(seed.as_deref()).collect(),
+
+            NodeBase::Statistic (_, list)     |
+            NodeBase::Stats     (list)        => vec![list],
+            NodeBase::Quantile  (list, a)     |
+            NodeBase::Quartile  (list, a)     => vec![list, a],
+            NodeBase::Paired    (_, a, b)     => vec![a, b],
+
+            NodeBase::Compare (_, left, right) |
+            NodeBase::And     (left, right)    |
+            NodeBase::Or      (left, right)    => vec![left, right],
+            NodeBase::Not     (arg)            => vec![arg],
+
+            NodeBase::Equals       (left, right) |
+            NodeBase::Less         (left, right) |
+            NodeBase::LessEqual    (left, right) |
+            NodeBase::Greater      (left, right) |
+            NodeBase::GreaterEqual (left, right) => vec![left, right]
+
+        };
+    }
+
+    // As `children`, but mutable, for rewriting a tree in place.
+    pub fn children_mut(&mut self) -> Vec<&mut Node> {
+        return match (self) {
+
+            NodeBase::Addition       (left, right) |
+            NodeBase::Subtraction    (left, right) |
+            NodeBase::Multiplication (left, right) |
+            NodeBase::Division       (left, right) |
+            NodeBase::Power          (left, right) => vec![left, right],
+
+            NodeBase::NthRoot    (a, b) |
+            NodeBase::Logartithm (a, b) |
+            NodeBase::Modulo     (a, b) => vec![a, b],
+
+            NodeBase::AbsoluteValue       (arg) |
+            NodeBase::SquareRoot          (arg) |
+            NodeBase::Sine                (arg) |
+            NodeBase::Cosine              (arg) |
+            NodeBase::Tangent             (arg) |
+            NodeBase::Cosecant            (arg) |
+            NodeBase::Secant              (arg) |
+            NodeBase::Cotangent           (arg) |
+            NodeBase::InverseSine         (arg) |
+            NodeBase::InverseCosine       (arg) |
+            NodeBase::InverseTangent      (arg) |
+            NodeBase::InverseCosecant     (arg) |
+            NodeBase::InverseSecant       (arg) |
+            NodeBase::InverseCotangent    (arg) |
+            NodeBase::HyperbolicSine      (arg) |
+            NodeBase::HyperbolicCosine    (arg) |
+            NodeBase::HyperbolicTangent   (arg) |
+            NodeBase::HyperbolicCosecant  (arg) |
+            NodeBase::HyperbolicSecant    (arg) |
+            NodeBase::HyperbolicCotangent (arg) |
+            NodeBase::Exponential         (arg) |
+            NodeBase::NaturalLogarithm    (arg) |
+            NodeBase::Ceiling             (arg) |
+            NodeBase::Floor               (arg) |
+            NodeBase::Round               (arg) |
+            NodeBase::Sign                (arg) => vec![arg],
+
+            NodeBase::MultiValue (values)  => values.iter_mut().map(|value| value.as_mut()).collect(),
+            NodeBase::Number     (_)       => vec![],
+            NodeBase::Variable   (_)       => vec![],
+            NodeBase::Call       (_, args) => args.iter_mut().map(|arg| arg.as_mut()).collect(),
+
+            NodeBase::Point      (x, y)    => vec![x, y],
+            NodeBase::Piecewise  (branches, otherwise) => {
+                branches.iter_mut().flat_map(|(condition, value)| [condition.as_mut(), value.as_mut()]).chain(otherwise.as_deref_mut()).collect()
+            },
+
+            NodeBase::Range   (first, second, last) => [first.as_mut()].into_iter().chain(second.as_deref_mut()).chain([last.as_mut()]).collect(),
+            NodeBase::For     (body, _, list)       => vec![body, list],
+            NodeBase::Index   (list, index)         => vec![list, index],
+            NodeBase::Length  (list) |
+            NodeBase::Total   (list) |
+            NodeBase::Sort    (list) |
+            NodeBase::Unique  (list)                => vec![list],
+            NodeBase::Join    (lists)               => lists.iter_mut().map(|list| list.as_mut()).collect(),
+            NodeBase::Shuffle (list, seed)          => [list.as_mut()].into_iter().chain(seed.as_deref_mut()).collect(),
+
+            NodeBase::Statistic (_, list)     |
+            NodeBase::Stats     (list)        => vec![list],
+            NodeBase::Quantile  (list, a)     |
+            NodeBase::Quartile  (list, a)     => vec![list, a],
+            NodeBase::Paired    (_, a, b)     => vec![a, b],
+
+            NodeBase::Compare (_, left, right) |
+            NodeBase::And     (left, right)    |
+            NodeBase::Or      (left, right)    => vec![left, right],
+            NodeBase::Not     (arg)            => vec![arg],
+
+            NodeBase::Equals       (left, right) |
+            NodeBase::Less         (left, right) |
+            NodeBase::LessEqual    (left, right) |
+            NodeBase::Greater      (left, right) |
+            NodeBase::GreaterEqual (left, right) => vec![left, right]
 
-            NodeBase::Equals (left, right) => format!("({} = {})", (*left).to_string(), (*right).to_string())
+        };
+    }
+
+    // Whether this is a condition, as in the branches of a piecewise expression.
+    pub fn is_condition(&self) -> bool {
+        return matches!(self, NodeBase::Compare(_, _, _) | NodeBase::And(_, _) | NodeBase::Or(_, _) | NodeBase::Not(_));
+    }
 
+    // Whether this is written as a list, see `parse::list`.
+    pub fn is_list(&self) -> bool {
+        return matches!(self,
+            NodeBase::MultiValue(_) | NodeBase::Range(_, _, _) | NodeBase::For(_, _, _) | NodeBase::Join(_)
+            | NodeBase::Sort(_) | NodeBase::Unique(_) | NodeBase::Shuffle(_, _) | NodeBase::Stats(_)
+        );
+    }
+
+    // Relation, Left, Right of an equation or inequality.
+    pub fn relation(&self) -> Option<(Relation, &Node, &Node)> {
+        return match (self) {
+            NodeBase::Equals       (left, right) => Some((Relation::Equal,        left, right)),
+            NodeBase::Less         (left, right) => Some((Relation::Less,         left, right)),
+            NodeBase::LessEqual    (left, right) => Some((Relation::LessEqual,    left, right)),
+            NodeBase::Greater      (left, right) => Some((Relation::Greater,      left, right)),
+            NodeBase::GreaterEqual (left, right) => Some((Relation::GreaterEqual, left, right)),
+            _                                    => None
         };
     }
 
-    pub fn evaluate(&self, target_variable : &String, variables : &mut HashMap<String, EvaluatedValues>) -> EvaluatedValues {
+    pub fn evaluate(&self, target_variable : &str, variables : &mut HashMap<String, EvaluatedValues>) -> Result<EvaluatedValues, Error> {
         macro evaluate {
-            ($from:expr) => {($from).evaluate(&target_variable, variables)}
+            ($from:expr) => {($from).evaluate(&target_variable, variables)?}
         }
         
         let values = match (self) {
@@ -173,33 +452,434 @@ impl NodeBase {
 
             NodeBase::MultiValue        (values)      => {
                 let mut evaluated_values = EvaluatedValues::new();
-                for i in 0..values.len() {
-                    evaluated_values = evaluated_values.add(&evaluate!(&values[i]));
+                for value in values {
+                    evaluated_values = evaluated_values.add(&evaluate!(value));
                 }
                 evaluated_values
             },
             NodeBase::Number            (value)       => EvaluatedValues::new().push(*value),
             NodeBase::Variable          (name)        => {
-                if (variables.contains_key(name)) {
-                    EvaluatedValues::new().add(&variables.get(name).unwrap())
-                } else {
-                    log!(ERROR, "Variable `{}` not defined.", name);
-                    process::exit(1);
+                match (variables.get(name)) {
+                    Some(values) => EvaluatedValues::new().add(values),
+                    None         => return Err(Error::UndefinedVariable(String::from(name)))
                 }
-            }
+            },
+            // Calls are replaced on import, see `parse::function`.
+            NodeBase::Call              (name, _)     => return Err(Error::UndefinedFunction(String::from(name))),
+            // Points are drawn by their coordinates, see `render::parametric`.
+            NodeBase::Point             (_, _)        => return Err(Error::PointValue(self.to_string())),
+            NodeBase::Piecewise         (branches, otherwise) => {
+                // Every part is evaluated, so errors do not depend on the branch taken.
+                let mut chosen = None;
+                for (condition, value) in branches {
+                    let (holds, value) = (! evaluate!(condition).get_values().is_empty(), evaluate!(value));
+                    if (holds && chosen.is_none()) {
+                        chosen = Some(value);
+                    }
+                }
+                let otherwise = match (otherwise) {
+                    Some(otherwise) => evaluate!(otherwise),
+                    None            => EvaluatedValues::new()
+                };
+                chosen.unwrap_or(otherwise)
+            },
+
+            NodeBase::Range   (_, _, _) |
+            NodeBase::For     (_, _, _) |
+            NodeBase::Join    (_)       |
+            NodeBase::Sort    (_)       |
+            NodeBase::Unique  (_)       |
+            NodeBase::Shuffle (_, _)    |
+            NodeBase::Stats   (_)       => list::union(list::elements(self, target_variable, variables)?),
+            NodeBase::Index   (list, index) => {
+                let index = evaluate!(index);
+                list::index(list::elements(&list.base, target_variable, variables)?, &index)
+            },
+            NodeBase::Length  (list)    => list::length(list::elements(&list.base, target_variable, variables)?),
+            NodeBase::Total   (list)    => list::total(list::elements(&list.base, target_variable, variables)?),
+
+            NodeBase::Statistic (_, _)    |
+            NodeBase::Quantile  (_, _)    |
+            NodeBase::Quartile  (_, _)    |
+            NodeBase::Paired    (_, _, _) => statistics::evaluate(self, target_variable, variables)?,
+
+            NodeBase::Compare (relation, left, right) => {
+                let (left, right) = (evaluate!(left), evaluate!(right));
+                truth(left.get_values().iter().any(|a| right.get_values().iter().any(|b| relation.holds(a - b))))
+            },
+            NodeBase::And (left, right) => {
+                let (left, right) = (evaluate!(left), evaluate!(right));
+                truth(! left.get_values().is_empty() && ! right.get_values().is_empty())
+            },
+            NodeBase::Or (left, right) => {
+                let (left, right) = (evaluate!(left), evaluate!(right));
+                truth(! left.get_values().is_empty() || ! right.get_values().is_empty())
+            },
+            NodeBase::Not (arg) => truth(evaluate!(arg).get_values().is_empty()),
 
             NodeBase::Equals (left, right) => {
-                match (&left.base) {
-                    NodeBase::Variable(name) => {
-                        let values = evaluate!(right);
-                        variables.insert(String::from(name), values);
-                    },
-                    _ => ()
+                if let NodeBase::Variable(name) = &left.base {
+                    let values = evaluate!(right);
+                    variables.insert(String::from(name), values);
                 }
                  EvaluatedValues::new()
-            }
+            },
+            // Inequalities draw regions, they have no value.
+            NodeBase::Less         (_, _) |
+            NodeBase::LessEqual    (_, _) |
+            NodeBase::Greater      (_, _) |
+            NodeBase::GreaterEqual (_, _) => EvaluatedValues::new()
             
         };
-        return values;
+        return Ok(values);
     }
+
+    // As `evaluate`, but over ranges of inputs. See `parse::intervals`.
+    pub fn evaluate_intervals(&self, target_variable : &str, variables : &mut HashMap<String, EvaluatedIntervals>) -> Result<EvaluatedIntervals, Error> {
+        macro evaluate {
+            ($from:expr) => {($from).evaluate_intervals(&target_variable, variables)?}
+        }
+        
+        let values = match (self) {
+            
+            NodeBase::Addition       (left, right) => evaluate!(left).addition(&evaluate!(right)),
+            NodeBase::Subtraction    (left, right) => evaluate!(left).subtraction(&evaluate!(right)),
+            NodeBase::Multiplication (left, right) => evaluate!(left).multiplication(&evaluate!(right)),
+            NodeBase::Division       (left, right) => evaluate!(left).division(&evaluate!(right)),
+            NodeBase::Power          (left, right) => evaluate!(left).power(&evaluate!(right)),
+
+            NodeBase::AbsoluteValue       (arg)  => evaluate!(arg).absolute_value(),
+            NodeBase::SquareRoot          (arg)  => evaluate!(arg).square_root(),
+            NodeBase::NthRoot             (n, p) => evaluate!(p).nth_root(&evaluate!(n)),
+            NodeBase::Sine                (arg)  => evaluate!(arg).sine(),
+            NodeBase::Cosine              (arg)  => evaluate!(arg).cosine(),
+            NodeBase::Tangent             (arg)  => evaluate!(arg).tangent(),
+            NodeBase::Cosecant            (arg)  => evaluate!(arg).cosecant(),
+            NodeBase::Secant              (arg)  => evaluate!(arg).secant(),
+            NodeBase::Cotangent           (arg)  => evaluate!(arg).cotangent(),
+            NodeBase::InverseSine         (arg)  => evaluate!(arg).inverse_sine(),
+            NodeBase::InverseCosine       (arg)  => evaluate!(arg).inverse_cosine(),
+            NodeBase::InverseTangent      (arg)  => evaluate!(arg).inverse_tangent(),
+            NodeBase::InverseCosecant     (arg)  => evaluate!(arg).inverse_cosecant(),
+            NodeBase::InverseSecant       (arg)  => evaluate!(arg).inverse_secant(),
+            NodeBase::InverseCotangent    (arg)  => evaluate!(arg).inverse_cotangent(),
+            NodeBase::HyperbolicSine      (arg)  => evaluate!(arg).hyperbolic_sine(),
+            NodeBase::HyperbolicCosine    (arg)  => evaluate!(arg).hyperbolic_cosine(),
+            NodeBase::HyperbolicTangent   (arg)  => evaluate!(arg).hyperbolic_tangent(),
+            NodeBase::HyperbolicCosecant  (arg)  => evaluate!(arg).hyperbolic_cosecant(),
+            NodeBase::HyperbolicSecant    (arg)  => evaluate!(arg).hyperbolic_secant(),
+            NodeBase::HyperbolicCotangent (arg)  => evaluate!(arg).hyperbolic_cotangent(),
+            NodeBase::Exponential         (arg)  => evaluate!(arg).exponential(),
+            NodeBase::NaturalLogarithm    (arg)  => evaluate!(arg).natural_logarithm(),
+            NodeBase::Logartithm          (b, r) => evaluate!(r).logarithm(&evaluate!(b)),
+            NodeBase::Modulo              (a, b) => evaluate!(a).modulo(&evaluate!(b)),
+            NodeBase::Ceiling             (arg)  => evaluate!(arg).ceiling(),
+            NodeBase::Floor               (arg)  => evaluate!(arg).floor(),
+            NodeBase::Round               (arg)  => evaluate!(arg).round(),
+            NodeBase::Sign                (arg)  => evaluate!(arg).sign(),
+
+            NodeBase::MultiValue        (values)      => {
+                let mut evaluated_intervals = EvaluatedIntervals::new();
+                for value in values {
+                    evaluated_intervals = evaluated_intervals.add(&evaluate!(value));
+                }
+                evaluated_intervals
+            },
+            NodeBase::Number            (value)       => EvaluatedIntervals::new().push(Interval::point(*value)),
+            NodeBase::Variable          (name)        => {
+                match (variables.get(name)) {
+                    Some(values) => EvaluatedIntervals::new().add(values),
+                    None         => return Err(Error::UndefinedVariable(String::from(name)))
+                }
+            },
+            // Calls are replaced on import, see `parse::function`.
+            NodeBase::Call              (name, _)     => return Err(Error::UndefinedFunction(String::from(name))),
+            // Points are drawn by their coordinates, see `render::parametric`.
+            NodeBase::Point             (_, _)        => return Err(Error::PointValue(self.to_string())),
+            // Branches that may apply are joined, up to the first that always does.
+            NodeBase::Piecewise         (branches, otherwise) => {
+                let mut joined  = EvaluatedIntervals::new();
+                let mut settled = false;
+                for (condition, value) in branches {
+                    let (truth, value) = (evaluate!(condition), evaluate!(value));
+                    if (! settled && ! truth.is_empty()) {
+                        joined  = joined.add(&value);
+                        settled = ! truth.contains(0.0);
+                    }
+                }
+                if let Some(otherwise) = otherwise {
+                    let otherwise = evaluate!(otherwise);
+                    if (! settled) {
+                        joined = joined.add(&otherwise);
+                    }
+                }
+                joined
+            },
+
+            NodeBase::Range   (_, _, _) |
+            NodeBase::For     (_, _, _) |
+            NodeBase::Join    (_)       |
+            NodeBase::Sort    (_)       |
+            NodeBase::Unique  (_)       |
+            NodeBase::Shuffle (_, _)    |
+            NodeBase::Stats   (_)       => list::union(list::elements(self, target_variable, variables)?),
+            NodeBase::Index   (list, index) => {
+                let index = evaluate!(index);
+                list::index(list::elements(&list.base, target_variable, variables)?, &index)
+            },
+            NodeBase::Length  (list)    => list::length(list::elements(&list.base, target_variable, variables)?),
+            NodeBase::Total   (list)    => list::total(list::elements(&list.base, target_variable, variables)?),
+
+            NodeBase::Statistic (_, _)    |
+            NodeBase::Quantile  (_, _)    |
+            NodeBase::Quartile  (_, _)    |
+            NodeBase::Paired    (_, _, _) => statistics::evaluate(self, target_variable, variables)?,
+
+            // Conditions are `[1, 1]` where they always hold, `[0, 1]` where
+            // they may, and empty where they never do. Where either side is
+            // undefined over part of a cell, the rest of the cell decides.
+            NodeBase::Compare (relation, left, right) => {
+                let difference = evaluate!(left).subtraction(&evaluate!(right));
+                let intervals  = difference.get_intervals();
+                truth_intervals(
+                    intervals.iter().any(|interval| relation.holds_somewhere(interval)),
+                    ! intervals.is_empty() && intervals.iter().all(|interval| relation.holds_everywhere(interval))
+                )
+            },
+            NodeBase::And (left, right) => {
+                let (left, right) = (evaluate!(left), evaluate!(right));
+                truth_intervals(
+                    ! left.is_empty() && ! right.is_empty(),
+                    ! left.is_empty() && ! right.is_empty() && ! left.contains(0.0) && ! right.contains(0.0)
+                )
+            },
+            NodeBase::Or (left, right) => {
+                let (left, right) = (evaluate!(left), evaluate!(right));
+                truth_intervals(
+                    ! left.is_empty() || ! right.is_empty(),
+                    (! left.is_empty() && ! left.contains(0.0)) || (! right.is_empty() && ! right.contains(0.0))
+                )
+            },
+            NodeBase::Not (arg) => {
+                let arg = evaluate!(arg);
+                truth_intervals(arg.is_empty() || arg.contains(0.0), arg.is_empty())
+            },
+
+            NodeBase::Equals (left, right) => {
+                if let NodeBase::Variable(name) = &left.base {
+                    let values = evaluate!(right);
+                    variables.insert(String::from(name), values);
+                }
+                 EvaluatedIntervals::new()
+            },
+            // Inequalities draw regions, they have no value.
+            NodeBase::Less         (_, _) |
+            NodeBase::LessEqual    (_, _) |
+            NodeBase::Greater      (_, _) |
+            NodeBase::GreaterEqual (_, _) => EvaluatedIntervals::new()
+            
+        };
+        return Ok(values);
+    }
+
+    // As `evaluate`, but over the complex numbers. See `parse::complex`.
+    pub fn evaluate_complex(&self, target_variable : &str, variables : &mut HashMap<String, EvaluatedComplex>) -> Result<EvaluatedComplex, Error> {
+        macro evaluate {
+            ($from:expr) => {($from).evaluate_complex(&target_variable, variables)?}
+        }
+        
+        let values = match (self) {
+            
+            NodeBase::Addition       (left, right) => evaluate!(left).addition(&evaluate!(right)),
+            NodeBase::Subtraction    (left, right) => evaluate!(left).subtraction(&evaluate!(right)),
+            NodeBase::Multiplication (left, right) => evaluate!(left).multiplication(&evaluate!(right)),
+            NodeBase::Division       (left, right) => evaluate!(left).division(&evaluate!(right)),
+            NodeBase::Power          (left, right) => evaluate!(left).power(&evaluate!(right)),
+
+            NodeBase::AbsoluteValue       (arg)  => evaluate!(arg).absolute_value(),
+            NodeBase::SquareRoot          (arg)  => evaluate!(arg).square_root(),
+            NodeBase::NthRoot             (n, p) => evaluate!(p).nth_root(&evaluate!(n)),
+            NodeBase::Sine                (arg)  => evaluate!(arg).sine(),
+            NodeBase::Cosine              (arg)  => evaluate!(arg).cosine(),
+            NodeBase::Tangent             (arg)  => evaluate!(arg).tangent(),
+            NodeBase::Cosecant            (arg)  => evaluate!(arg).cosecant(),
+            NodeBase::Secant              (arg)  => evaluate!(arg).secant(),
+            NodeBase::Cotangent           (arg)  => evaluate!(arg).cotangent(),
+            NodeBase::InverseSine         (arg)  => evaluate!(arg).inverse_sine(),
+            NodeBase::InverseCosine       (arg)  => evaluate!(arg).inverse_cosine(),
+            NodeBase::InverseTangent      (arg)  => evaluate!(arg).inverse_tangent(),
+            NodeBase::InverseCosecant     (arg)  => evaluate!(arg).inverse_cosecant(),
+            NodeBase::InverseSecant       (arg)  => evaluate!(arg).inverse_secant(),
+            NodeBase::InverseCotangent    (arg)  => evaluate!(arg).inverse_cotangent(),
+            NodeBase::HyperbolicSine      (arg)  => evaluate!(arg).hyperbolic_sine(),
+            NodeBase::HyperbolicCosine    (arg)  => evaluate!(arg).hyperbolic_cosine(),
+            NodeBase::HyperbolicTangent   (arg)  => evaluate!(arg).hyperbolic_tangent(),
+            NodeBase::HyperbolicCosecant  (arg)  => evaluate!(arg).hyperbolic_cosecant(),
+            NodeBase::HyperbolicSecant    (arg)  => evaluate!(arg).hyperbolic_secant(),
+            NodeBase::HyperbolicCotangent (arg)  => evaluate!(arg).hyperbolic_cotangent(),
+            NodeBase::Exponential         (arg)  => evaluate!(arg).exponential(),
+            NodeBase::NaturalLogarithm    (arg)  => evaluate!(arg).natural_logarithm(),
+            NodeBase::Logartithm          (b, r) => evaluate!(r).logarithm(&evaluate!(b)),
+            NodeBase::Modulo              (a, b) => evaluate!(a).modulo(&evaluate!(b)),
+            NodeBase::Ceiling             (arg)  => evaluate!(arg).ceiling(),
+            NodeBase::Floor               (arg)  => evaluate!(arg).floor(),
+            NodeBase::Round               (arg)  => evaluate!(arg).round(),
+            NodeBase::Sign                (arg)  => evaluate!(arg).sign(),
+
+            NodeBase::MultiValue        (values)      => {
+                let mut evaluated_values = EvaluatedComplex::new();
+                for value in values {
+                    evaluated_values = evaluated_values.add(&evaluate!(value));
+                }
+                evaluated_values
+            },
+            NodeBase::Number            (value)       => EvaluatedComplex::new().push(Complex::real(*value)),
+            NodeBase::Variable          (name)        => {
+                match (variables.get(name)) {
+                    Some(values) => EvaluatedComplex::new().add(values),
+                    None         => return Err(Error::UndefinedVariable(String::from(name)))
+                }
+            },
+            // Calls are replaced on import, see `parse::function`.
+            NodeBase::Call              (name, _)     => return Err(Error::UndefinedFunction(String::from(name))),
+            // Points are drawn by their coordinates, see `render::parametric`.
+            NodeBase::Point             (_, _)        => return Err(Error::PointValue(self.to_string())),
+            NodeBase::Piecewise         (branches, otherwise) => {
+                // Every part is evaluated, so errors do not depend on the branch taken.
+                let mut chosen = None;
+                for (condition, value) in branches {
+                    let (holds, value) = (! evaluate!(condition).get_values().is_empty(), evaluate!(value));
+                    if (holds && chosen.is_none()) {
+                        chosen = Some(value);
+                    }
+                }
+                let otherwise = match (otherwise) {
+                    Some(otherwise) => evaluate!(otherwise),
+                    None            => EvaluatedComplex::new()
+                };
+                chosen.unwrap_or(otherwise)
+            },
+
+            NodeBase::Range   (_, _, _) |
+            NodeBase::For     (_, _, _) |
+            NodeBase::Join    (_)       |
+            NodeBase::Sort    (_)       |
+            NodeBase::Unique  (_)       |
+            NodeBase::Shuffle (_, _)    |
+            NodeBase::Stats   (_)       => list::union(list::elements(self, target_variable, variables)?),
+            NodeBase::Index   (list, index) => {
+                let index = evaluate!(index);
+                list::index(list::elements(&list.base, target_variable, variables)?, &index)
+            },
+            NodeBase::Length  (list)    => list::length(list::elements(&list.base, target_variable, variables)?),
+            NodeBase::Total   (list)    => list::total(list::elements(&list.base, target_variable, variables)?),
+
+            NodeBase::Statistic (_, _)    |
+            NodeBase::Quantile  (_, _)    |
+            NodeBase::Quartile  (_, _)    |
+            NodeBase::Paired    (_, _, _) => statistics::evaluate(self, target_variable, variables)?,
+
+            // Only values that are nearly real are compared.
+            NodeBase::Compare (relation, left, right) => {
+                let (left, right) = (evaluate!(left).real_values(ORDER_TOLERANCE), evaluate!(right).real_values(ORDER_TOLERANCE));
+                truth_complex(left.iter().any(|a| right.iter().any(|b| relation.holds(a - b))))
+            },
+            NodeBase::And (left, right) => {
+                let (left, right) = (evaluate!(left), evaluate!(right));
+                truth_complex(! left.get_values().is_empty() && ! right.get_values().is_empty())
+            },
+            NodeBase::Or (left, right) => {
+                let (left, right) = (evaluate!(left), evaluate!(right));
+                truth_complex(! left.get_values().is_empty() || ! right.get_values().is_empty())
+            },
+            NodeBase::Not (arg) => truth_complex(evaluate!(arg).get_values().is_empty()),
+
+            NodeBase::Equals (left, right) => {
+                if let NodeBase::Variable(name) = &left.base {
+                    let values = evaluate!(right);
+                    variables.insert(String::from(name), values);
+                }
+                 EvaluatedComplex::new()
+            },
+            // Inequalities draw regions, they have no value.
+            NodeBase::Less         (_, _) |
+            NodeBase::LessEqual    (_, _) |
+            NodeBase::Greater      (_, _) |
+            NodeBase::GreaterEqual (_, _) => EvaluatedComplex::new()
+            
+        };
+        return Ok(values);
+    }
+}
+
+
+// How the two sides of an equation or inequality compare.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Relation {
+    Equal,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual
+}
+impl Relation {
+    // Whether the border itself is left out, `<` and `>`.
+    pub fn is_strict(&self) -> bool {
+        return matches!(self, Relation::Less | Relation::Greater);
+    }
+    // Whether the relation holds for a value of `left - right`.
+    pub fn holds(&self, difference : f64) -> bool {
+        return match (self) {
+            Relation::Equal        => difference == 0.0,
+            Relation::Less         => difference < 0.0,
+            Relation::LessEqual    => difference <= 0.0,
+            Relation::Greater      => difference > 0.0,
+            Relation::GreaterEqual => difference >= 0.0
+        };
+    }
+    // Whether the relation holds for any value of `left - right` in an interval.
+    pub fn holds_somewhere(&self, difference : &Interval) -> bool {
+        return match (self) {
+            Relation::Equal        => difference.contains(0.0),
+            Relation::Less         => difference.lower < 0.0,
+            Relation::LessEqual    => difference.lower <= 0.0,
+            Relation::Greater      => difference.upper > 0.0,
+            Relation::GreaterEqual => difference.upper >= 0.0
+        };
+    }
+    // Whether the relation holds for every value of `left - right` in an interval.
+    pub fn holds_everywhere(&self, difference : &Interval) -> bool {
+        return match (self) {
+            Relation::Equal        => difference.lower == 0.0 && difference.upper == 0.0,
+            Relation::Less         => difference.upper < 0.0,
+            Relation::LessEqual    => difference.upper <= 0.0,
+            Relation::Greater      => difference.lower > 0.0,
+            Relation::GreaterEqual => difference.lower >= 0.0
+        };
+    }
+    pub fn symbol(&self) -> &'static str {
+        return match (self) {
+            Relation::Equal        => "=",
+            Relation::Less         => "<",
+            Relation::LessEqual    => "<=",
+            Relation::Greater      => ">",
+            Relation::GreaterEqual => ">="
+        };
+    }
+}
+
+// The value of a condition, a single `1` where it holds.
+fn truth(holds : bool) -> EvaluatedValues {
+    return if (holds) {EvaluatedValues::from(vec![1.0])} else {EvaluatedValues::new()};
+}
+fn truth_complex(holds : bool) -> EvaluatedComplex {
+    return if (holds) {EvaluatedComplex::real(1.0)} else {EvaluatedComplex::new()};
+}
+// `[1, 1]` where a condition always holds, `[0, 1]` where it may, and empty where it never does.
+fn truth_intervals(sometimes : bool, always : bool) -> EvaluatedIntervals {
+    return match (sometimes, always) {
+        (_, true)     => EvaluatedIntervals::from(vec![Interval::point(1.0)]),
+        (true, false) => EvaluatedIntervals::from(vec![Interval::new(0.0, 1.0)]),
+        _             => EvaluatedIntervals::new()
+    };
 }
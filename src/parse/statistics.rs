@@ -0,0 +1,479 @@
+// Statistics of a list, such as `mean(a)` and `corr(a, b)`.
+//
+// Arithmetic on a list takes each element, see `parse::list`. These read
+// the whole list instead and give a single value, except `stats`:
+//
+//     mean(a)          Sum of the elements over how many there are.
+//     median(a)        Middle element in increasing order, or the mean of the middle two.
+//     min(a), max(a)   Least and greatest element.
+//     quantile(a, p)   A fraction `p` of the way through the elements in increasing
+//                      order, between the two nearest in proportion. Nothing for `p`
+//                      outside 0 to 1.
+//     quartile(a, q)   `quantile(a, q / 4)`, so `quartile(a, 2)` is the median.
+//     var(a)           Sample variance, the sum of squared differences from the mean
+//                      over one less than the number of elements.
+//     stdev(a)         Square root of `var(a)`.
+//     stdevp(a)        Population standard deviation, as `stdev` over the number of elements.
+//     mad(a)           Mean absolute difference from the mean.
+//     cov(a, b)        Sample covariance, the sum of the products of the differences
+//                      from each mean over one less than the number of pairs.
+//     covp(a, b)       Population covariance, as `cov` over the number of pairs.
+//     corr(a, b)       Pearson correlation.
+//     spearman(a, b)   Pearson correlation of the ranks, equal elements sharing the mean of theirs.
+//     stats(a)         The list `[min, first quartile, median, third quartile, max]`.
+//
+// A statistic with several arguments but one list reads them joined, so
+// `max(a, 0)` is never negative. A list too short for a statistic, such
+// as an empty one, or one element for `var`, gives nothing, as does a
+// correlation of a list whose elements are all equal. `cov`, `covp`,
+// `corr` and `spearman` pair the elements of two lists of the same length.
+//
+// Each statistic is written once, as arithmetic on the elements, carried
+// out straight away by the evaluators and compiled to instructions by
+// `parse::bytecode`. So, as elsewhere, elements with several values give a
+// value for each way the arithmetic combines them. The fraction of
+// `quantile` and `quartile` may have several values, giving a quantile
+// for each, but can not depend on the coordinates, see `render::dependency`.
+
+use std::collections::HashMap;
+use std::marker::PhantomData;
+
+use crate::error::Error;
+use crate::parse::node::NodeBase;
+use crate::parse::list::{self, Evaluated};
+
+
+// Statistics of one list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Statistic {
+    Mean,
+    Median,
+    Minimum,
+    Maximum,
+    Variance,
+    Deviation,
+    PopulationDeviation,
+    MeanAbsoluteDeviation
+}
+impl Statistic {
+    pub fn name(&self) -> &'static str {
+        return match (self) {
+            Statistic::Mean                  => "mean",
+            Statistic::Median                => "median",
+            Statistic::Minimum               => "min",
+            Statistic::Maximum               => "max",
+            Statistic::Variance              => "var",
+            Statistic::Deviation             => "stdev",
+            Statistic::PopulationDeviation   => "stdevp",
+            Statistic::MeanAbsoluteDeviation => "mad"
+        };
+    }
+}
+
+// Statistics of two lists, element by element.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Paired {
+    Covariance,
+    PopulationCovariance,
+    Correlation,
+    RankCorrelation
+}
+impl Paired {
+    pub fn name(&self) -> &'static str {
+        return match (self) {
+            Paired::Covariance           => "cov",
+            Paired::PopulationCovariance => "covp",
+            Paired::Correlation          => "corr",
+            Paired::RankCorrelation      => "spearman"
+        };
+    }
+}
+
+
+// What the statistics are written in. Values are carried out straight away,
+// and registers are compiled to instructions, see `bytecode::Compiler`.
+pub trait Arithmetic {
+    type Value : Clone;
+    fn empty(&mut self) -> Self::Value;
+    fn number(&mut self, value : f64) -> Self::Value;
+    // The value, if it is a single real number already known.
+    fn single(&self, value : &Self::Value) -> Option<f64>;
+    fn union(&mut self, a : &Self::Value, b : &Self::Value) -> Self::Value;
+    fn addition(&mut self, a : &Self::Value, b : &Self::Value) -> Self::Value;
+    fn subtraction(&mut self, a : &Self::Value, b : &Self::Value) -> Self::Value;
+    fn multiplication(&mut self, a : &Self::Value, b : &Self::Value) -> Self::Value;
+    fn division(&mut self, a : &Self::Value, b : &Self::Value) -> Self::Value;
+    fn power(&mut self, a : &Self::Value, b : &Self::Value) -> Self::Value;
+    fn square_root(&mut self, a : &Self::Value) -> Self::Value;
+    fn absolute_value(&mut self, a : &Self::Value) -> Self::Value;
+    fn sign(&mut self, a : &Self::Value) -> Self::Value;
+    fn minimum(&mut self, a : &Self::Value, b : &Self::Value) -> Self::Value;
+    fn maximum(&mut self, a : &Self::Value, b : &Self::Value) -> Self::Value;
+}
+
+// Arithmetic on the values of one kind of evaluation.
+struct Evaluation<T> {
+    values : PhantomData<T>
+}
+impl<T : Evaluated> Arithmetic for Evaluation<T> {
+    type Value = T;
+    fn empty(&mut self) -> T {
+        return T::empty();
+    }
+    fn number(&mut self, value : f64) -> T {
+        return T::number(value);
+    }
+    fn single(&self, value : &T) -> Option<f64> {
+        return value.single();
+    }
+    fn union(&mut self, a : &T, b : &T) -> T {
+        return a.union(b);
+    }
+    fn addition(&mut self, a : &T, b : &T) -> T {
+        return a.addition(b);
+    }
+    fn subtraction(&mut self, a : &T, b : &T) -> T {
+        return a.subtraction(b);
+    }
+    fn multiplication(&mut self, a : &T, b : &T) -> T {
+        return a.multiplication(b);
+    }
+    fn division(&mut self, a : &T, b : &T) -> T {
+        return a.division(b);
+    }
+    fn power(&mut self, a : &T, b : &T) -> T {
+        return a.power(b);
+    }
+    fn square_root(&mut self, a : &T) -> T {
+        return a.square_root();
+    }
+    fn absolute_value(&mut self, a : &T) -> T {
+        return a.absolute_value();
+    }
+    fn sign(&mut self, a : &T) -> T {
+        return a.sign();
+    }
+    fn minimum(&mut self, a : &T, b : &T) -> T {
+        return a.minimum(b);
+    }
+    fn maximum(&mut self, a : &T, b : &T) -> T {
+        return a.maximum(b);
+    }
+}
+
+
+// Values of a statistic giving a single value, for the evaluators.
+pub fn evaluate<T : Evaluated>(node : &NodeBase, target_variable : &str, variables : &mut HashMap<String, T>) -> Result<T, Error> {
+    let mut arithmetic = Evaluation {values: PhantomData};
+    return match (node) {
+        NodeBase::Statistic (statistic, list) => {
+            let list = list::elements(&list.base, target_variable, variables)?;
+            Ok(statistic_of(&mut arithmetic, *statistic, list))
+        },
+        NodeBase::Quantile (list, fraction) |
+        NodeBase::Quartile (list, fraction) => {
+            let fractions = match (T::evaluate(&fraction.base, target_variable, variables)?.numbers()) {
+                Some(fractions) => fractions,
+                None            => return Err(Error::QuantileFraction(node.to_string()))
+            };
+            let list  = list::elements(&list.base, target_variable, variables)?;
+            let scale = if (matches!(node, NodeBase::Quartile(_, _))) {0.25} else {1.0};
+            Ok(quantiles(&mut arithmetic, list, &fractions.iter().map(|fraction| fraction * scale).collect::<Vec<f64>>()))
+        },
+        NodeBase::Paired (paired, a, b) => {
+            let a = list::elements(&a.base, target_variable, variables)?;
+            let b = list::elements(&b.base, target_variable, variables)?;
+            if (a.len() != b.len()) {
+                return Err(Error::PairedLengths(node.to_string()));
+            }
+            Ok(paired_of(&mut arithmetic, *paired, a, b))
+        },
+        _ => Err(Error::Internal(format!("`{}` is not a statistic.", node)))
+    };
+}
+
+// Elements of `stats`, for the evaluators.
+pub fn evaluate_summary<T : Evaluated>(list : Vec<T>) -> Vec<T> {
+    return summary(&mut Evaluation {values: PhantomData}, list);
+}
+
+
+pub fn statistic_of<A : Arithmetic>(arithmetic : &mut A, statistic : Statistic, list : Vec<A::Value>) -> A::Value {
+    let needed = if (matches!(statistic, Statistic::Variance | Statistic::Deviation)) {2} else {1};
+    if (list.len() < needed) {
+        return arithmetic.empty();
+    }
+    let length = list.len() as f64;
+    return match (statistic) {
+        Statistic::Mean                  => mean(arithmetic, &list),
+        Statistic::Median                => {
+            let sorted = sorted(arithmetic, list);
+            quantile(arithmetic, &sorted, 0.5)
+        },
+        Statistic::Minimum               => fold(arithmetic, &list, A::minimum),
+        Statistic::Maximum               => fold(arithmetic, &list, A::maximum),
+        Statistic::Variance              => variance(arithmetic, &list, length - 1.0),
+        Statistic::Deviation             => {
+            let variance = variance(arithmetic, &list, length - 1.0);
+            arithmetic.square_root(&variance)
+        },
+        Statistic::PopulationDeviation   => {
+            let variance = variance(arithmetic, &list, length);
+            arithmetic.square_root(&variance)
+        },
+        Statistic::MeanAbsoluteDeviation => {
+            let differences = differences(arithmetic, &list);
+            let absolute    = differences.iter().map(|difference| arithmetic.absolute_value(difference)).collect::<Vec<A::Value>>();
+            mean(arithmetic, &absolute)
+        }
+    };
+}
+
+// Statistic of two lists of the same length.
+pub fn paired_of<A : Arithmetic>(arithmetic : &mut A, paired : Paired, a : Vec<A::Value>, b : Vec<A::Value>) -> A::Value {
+    let needed = if (paired == Paired::PopulationCovariance) {1} else {2};
+    if (a.len() < needed) {
+        return arithmetic.empty();
+    }
+    let length = a.len() as f64;
+    return match (paired) {
+        Paired::Covariance           => covariance(arithmetic, &a, &b, length - 1.0),
+        Paired::PopulationCovariance => covariance(arithmetic, &a, &b, length),
+        Paired::Correlation          => correlation(arithmetic, &a, &b),
+        Paired::RankCorrelation      => {
+            let (a, b) = (ranks(arithmetic, &a), ranks(arithmetic, &b));
+            correlation(arithmetic, &a, &b)
+        }
+    };
+}
+
+// Values of the quantile at each fraction together.
+pub fn quantiles<A : Arithmetic>(arithmetic : &mut A, list : Vec<A::Value>, fractions : &[f64]) -> A::Value {
+    let sorted = sorted(arithmetic, list);
+    let mut values = arithmetic.empty();
+    for fraction in fractions {
+        let value = quantile(arithmetic, &sorted, *fraction);
+        values = arithmetic.union(&values, &value);
+    }
+    return values;
+}
+
+// Elements of `stats`.
+pub fn summary<A : Arithmetic>(arithmetic : &mut A, list : Vec<A::Value>) -> Vec<A::Value> {
+    let sorted = sorted(arithmetic, list);
+    return [0.0, 0.25, 0.5, 0.75, 1.0].iter().map(|fraction| quantile(arithmetic, &sorted, *fraction)).collect();
+}
+
+
+// Elements in increasing order, as `sort`.
+fn sorted<A : Arithmetic>(arithmetic : &mut A, mut list : Vec<A::Value>) -> Vec<A::Value> {
+    if let Some(mut numbers) = list.iter().map(|element| arithmetic.single(element)).collect::<Option<Vec<f64>>>() {
+        numbers.sort_by(|a, b| a.total_cmp(b));
+        return numbers.into_iter().map(|value| arithmetic.number(value)).collect();
+    }
+    for (a, b) in list::sorting_network(list.len()) {
+        let (lower, upper) = (arithmetic.minimum(&list[a], &list[b]), arithmetic.maximum(&list[a], &list[b]));
+        list[a] = lower;
+        list[b] = upper;
+    }
+    return list;
+}
+
+// A fraction of the way through sorted elements, between the two nearest.
+fn quantile<A : Arithmetic>(arithmetic : &mut A, sorted : &[A::Value], fraction : f64) -> A::Value {
+    if (sorted.is_empty() || ! (0.0..=1.0).contains(&fraction)) {
+        return arithmetic.empty();
+    }
+    let position = (sorted.len() - 1) as f64 * fraction;
+    let below    = position.floor() as usize;
+    let above    = position - below as f64;
+    if (above == 0.0) {
+        return sorted[below].clone();
+    }
+    let (lower, upper) = (arithmetic.number(1.0 - above), arithmetic.number(above));
+    let lower = arithmetic.multiplication(&sorted[below], &lower);
+    let upper = arithmetic.multiplication(&sorted[below + 1], &upper);
+    return arithmetic.addition(&lower, &upper);
+}
+
+fn fold<A : Arithmetic>(arithmetic : &mut A, list : &[A::Value], operation : fn(&mut A, &A::Value, &A::Value) -> A::Value) -> A::Value {
+    let mut value = list[0].clone();
+    for element in &list[1..] {
+        value = operation(arithmetic, &value, element);
+    }
+    return value;
+}
+
+fn mean<A : Arithmetic>(arithmetic : &mut A, list : &[A::Value]) -> A::Value {
+    let total  = fold(arithmetic, list, A::addition);
+    let length = arithmetic.number(list.len() as f64);
+    return arithmetic.division(&total, &length);
+}
+
+// Difference of each element from the mean.
+fn differences<A : Arithmetic>(arithmetic : &mut A, list : &[A::Value]) -> Vec<A::Value> {
+    let mean = mean(arithmetic, list);
+    return list.iter().map(|element| arithmetic.subtraction(element, &mean)).collect();
+}
+
+// Sum of the products of each pair of differences from the means, over `divisor`.
+fn covariance<A : Arithmetic>(arithmetic : &mut A, a : &[A::Value], b : &[A::Value], divisor : f64) -> A::Value {
+    let sum     = products(arithmetic, a, b);
+    let divisor = arithmetic.number(divisor);
+    return arithmetic.division(&sum, &divisor);
+}
+
+fn variance<A : Arithmetic>(arithmetic : &mut A, list : &[A::Value], divisor : f64) -> A::Value {
+    let sum     = squares(arithmetic, list);
+    let divisor = arithmetic.number(divisor);
+    return arithmetic.division(&sum, &divisor);
+}
+
+fn correlation<A : Arithmetic>(arithmetic : &mut A, a : &[A::Value], b : &[A::Value]) -> A::Value {
+    let sum    = products(arithmetic, a, b);
+    let (a, b) = (squares(arithmetic, a), squares(arithmetic, b));
+    let spread = arithmetic.multiplication(&a, &b);
+    let spread = arithmetic.square_root(&spread);
+    return arithmetic.division(&sum, &spread);
+}
+
+// Sum of the products of each pair of differences from the means.
+fn products<A : Arithmetic>(arithmetic : &mut A, a : &[A::Value], b : &[A::Value]) -> A::Value {
+    let (a, b)   = (differences(arithmetic, a), differences(arithmetic, b));
+    let products = a.iter().zip(&b).map(|(a, b)| arithmetic.multiplication(a, b)).collect::<Vec<A::Value>>();
+    return fold(arithmetic, &products, A::addition);
+}
+
+// Sum of the squared differences from the mean.
+fn squares<A : Arithmetic>(arithmetic : &mut A, list : &[A::Value]) -> A::Value {
+    let two     = arithmetic.number(2.0);
+    let squares = differences(arithmetic, list).iter().map(|difference| arithmetic.power(difference, &two)).collect::<Vec<A::Value>>();
+    return fold(arithmetic, &squares, A::addition);
+}
+
+// Position of each element in increasing order, counting from 1, with
+// equal elements sharing the mean of their positions.
+fn ranks<A : Arithmetic>(arithmetic : &mut A, list : &[A::Value]) -> Vec<A::Value> {
+    if let Some(numbers) = list.iter().map(|element| arithmetic.single(element)).collect::<Option<Vec<f64>>>() {
+        let mut order = (0..numbers.len()).collect::<Vec<usize>>();
+        order.sort_by(|a, b| numbers[*a].total_cmp(&numbers[*b]));
+        let mut ranks = vec![0.0; numbers.len()];
+        let mut start = 0;
+        while (start < order.len()) {
+            let mut end = start + 1;
+            while (end < order.len() && numbers[order[end]] == numbers[order[start]]) {
+                end += 1;
+            }
+            for i in &order[start..end] {
+                ranks[*i] = (start + end + 1) as f64 / 2.0;
+            }
+            start = end;
+        }
+        return ranks.into_iter().map(|rank| arithmetic.number(rank)).collect();
+    }
+    // Each other element below adds 1, and each equal one adds a half:
+    // `(n + 1) / 2 + sum(sign(a - b)) / 2`.
+    let (middle, half) = (arithmetic.number((list.len() + 1) as f64 / 2.0), arithmetic.number(0.5));
+    let mut ranks = vec![];
+    for (i, a) in list.iter().enumerate() {
+        let mut rank = middle.clone();
+        for (j, b) in list.iter().enumerate() {
+            if (i != j) {
+                let difference = arithmetic.subtraction(a, b);
+                let sign       = arithmetic.sign(&difference);
+                let step       = arithmetic.multiplication(&sign, &half);
+                rank = arithmetic.addition(&rank, &step);
+            }
+        }
+        ranks.push(rank);
+    }
+    return ranks;
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::bytecode::Compiler;
+    use crate::parse::bytecode::tests::evaluate_both;
+    use crate::parse::import::{self, Format};
+    use crate::parse::values::EvaluatedValues;
+
+    // Values of `y` at `x = -5`, the same walking the node trees and compiled.
+    fn values(source : &str) -> Vec<f64> {
+        let (tree, compiled) = evaluate_both(source, -5.0);
+        assert_eq!(tree, compiled, "`{}`", source);
+        return tree;
+    }
+
+    fn assert_close(source : &str, expected : &[f64]) {
+        let found = values(source);
+        assert_eq!(found.len(), expected.len(), "`{}` gave {:?}", source, found);
+        for (found, expected) in found.iter().zip(expected) {
+            assert!((found - expected).abs() < 1e-12, "`{}` gave {}, expected {}", source, found, expected);
+        }
+    }
+
+    // Results of `y = ...` walking the node tree at `x = 1`, and compiling it.
+    fn results(source : &str) -> (Result<EvaluatedValues, Error>, Result<usize, Error>) {
+        let node = &import::parse(source, Format::Text).unwrap().nodes[0];
+        let mut variables = HashMap::new();
+        variables.insert(String::from("x"), EvaluatedValues::from(vec![1.0]));
+        let mut compiler = Compiler::new();
+        compiler.set_input("x");
+        return (node.evaluate("y", &mut variables), compiler.compile(node));
+    }
+
+    #[test]
+    fn averages_and_deviations() {
+        assert_close("y = mean([2, 4, 4, 4, 5, 5, 7, 9])",   &[5.0]);
+        assert_close("y = median([2, 4, 4, 4, 5, 5, 7, 9])", &[4.5]);
+        assert_close("y = median([x, 1, 3])",                &[1.0]);
+        assert_close("y = var([2, 4, 4, 4, 5, 5, 7, 9])",    &[32.0 / 7.0]);
+        assert_close("y = stdev([2, 4, 4, 4, 5, 5, 7, 9])",  &[(32.0f64 / 7.0).sqrt()]);
+        assert_close("y = stdevp([2, 4, 4, 4, 5, 5, 7, 9])", &[2.0]);
+        assert_close("y = mad([1, 2, 3, 4])",                &[1.0]);
+        assert_close("y = min([4, 2, 8]) + max([4, 2, 8])",  &[10.0]);
+    }
+
+    #[test]
+    fn quantiles_interpolate_between_elements() {
+        assert_close("y = quantile([1, 2, 3, 4], 0.25)",        &[1.75]);
+        assert_close("y = quantile([4, 3, 2, 1], [0, 0.5, 1])", &[1.0, 2.5, 4.0]);
+        assert_close("y = quartile([1, 2, 3, 4], 1)",           &[1.75]);
+        assert_close("y = quartile([1, 2, 3, 4], 3)",           &[3.25]);
+        assert_close("y = stats([5, 1, 4, 2, 3])",              &[1.0, 2.0, 3.0, 4.0, 5.0]);
+    }
+
+    #[test]
+    fn paired_statistics() {
+        assert_close("y = cov([1, 2, 3, 4], [2, 4, 5, 9])",            &[11.0 / 3.0]);
+        assert_close("y = covp([1, 2, 3, 4], [2, 4, 5, 9])",           &[2.75]);
+        assert_close("y = corr([1, 2, 3, 4], [2, 4, 5, 9])",           &[0.9647638212377322]);
+        assert_close("y = corr([1, 2, 3], [2, 4, 6])",                 &[1.0]);
+        assert_close("y = spearman([1, 2, 3, 4, 5], [2, 1, 4, 3, 5])", &[0.8]);
+        // Ties share the average of their ranks.
+        assert_close("y = spearman([1, 2, 2, 3], [1, 2, 3, 4])",       &[0.9486832980505138]);
+    }
+
+    #[test]
+    fn several_arguments_are_joined() {
+        assert_close("y = mean(1, 2, 6)",   &[3.0]);
+        assert_close("y = mean([1, 2], 6)", &[3.0]);
+    }
+
+    #[test]
+    fn lists_too_short_give_nothing() {
+        assert_close("y = mean([])",      &[]);
+        assert_close("y = var([1])",      &[]);
+        assert_close("y = cov([1], [2])", &[]);
+    }
+
+    #[test]
+    fn reports_bad_arguments() {
+        assert!(matches!(results("y = cov([1, 2], [1, 2, 3])"), (Err(Error::PairedLengths(_)), Err(Error::PairedLengths(_)))));
+        // At a single point the fraction is known, so only compiling, for
+        // every point, rejects it. Drawing rejects it first, see `render::dependency`.
+        assert!(matches!(results("y = quantile([1, 2, 3], x)"), (Ok(_), Err(Error::QuantileFraction(_)))));
+    }
+}
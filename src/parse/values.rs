@@ -1,13 +1,17 @@
 use std::fmt;
 
 use crate::parse::var;
-use crate::render::settings::RenderSettings;
 
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct EvaluatedValues {
     values : Vec<f64>
 }
+impl Default for EvaluatedValues {
+    fn default() -> EvaluatedValues {
+        return EvaluatedValues::new();
+    }
+}
 impl EvaluatedValues {
     pub fn new() -> EvaluatedValues {
         return EvaluatedValues {values: vec![]};
@@ -39,9 +43,10 @@ impl EvaluatedValues {
     pub fn get_values(&self) -> &Vec<f64> {
         return &self.values;
     }
-    pub fn compress(&self, settings : &RenderSettings) -> EvaluatedValues {
+    // Drop values that can not be drawn, such as `NaN` and infinities.
+    pub fn compress(&self) -> EvaluatedValues {
         return self.unary_operation(|a, new_values| {
-            if (a >= settings.frame[1] && a < settings.frame[3]) {
+            if (a.is_finite()) {
                 new_values.values.push(a);
             }
         });
@@ -59,7 +64,7 @@ impl EvaluatedValues {
     pub fn division(&self, other : &EvaluatedValues) -> EvaluatedValues {
         return self.binary_operation(other, |a, b, new_values| {
             if (b != 0.0) {
-                new_values.values.push(a * b);
+                new_values.values.push(a / b);
             }
         });
     }
@@ -78,8 +83,18 @@ impl EvaluatedValues {
             }
         });
     }
-    pub fn nth_root(&self, _degree : &EvaluatedValues) -> EvaluatedValues {
-        panic!("Unimplemented.");
+    pub fn nth_root(&self, degree : &EvaluatedValues) -> EvaluatedValues {
+        return self.binary_operation(degree, |a, n, new_values| {
+            if (n == 0.0) {
+                return;
+            }
+            if (a >= 0.0) {
+                new_values.values.push(a.powf(1.0 / n));
+            } else if (n.fract() == 0.0 && n % 2.0 != 0.0) {
+                // Odd roots of negative numbers are real.
+                new_values.values.push(-(-a).powf(1.0 / n));
+            }
+        });
     }
     pub fn sine(&self) -> EvaluatedValues {
         return self.unary_operation(|a, new_values| new_values.values.push(a.sin()));
@@ -109,16 +124,13 @@ impl EvaluatedValues {
         return self.unary_operation(|a, new_values| new_values.values.push(a.atan()));
     }
     pub fn inverse_cosecant(&self) -> EvaluatedValues {
-        panic!("Unimplemented.");
-        //return EvaluatedValues::from(vec![1.0]).division(self).inverse_sine();
+        return EvaluatedValues::from(vec![1.0]).division(self).inverse_sine();
     }
     pub fn inverse_secant(&self) -> EvaluatedValues {
-        panic!("Unimplemented.");
-        //return EvaluatedValues::from(vec![1.0]).division(self).inverse_cosine();
+        return EvaluatedValues::from(vec![1.0]).division(self).inverse_cosine();
     }
     pub fn inverse_cotangent(&self) -> EvaluatedValues {
-        panic!("Unimplemented.");
-        //return EvaluatedValues::from(vec![1.0]).division(self).inverse_tangent();
+        return EvaluatedValues::from(vec![1.0]).division(self).inverse_tangent();
     }
     pub fn hyperbolic_sine(&self) -> EvaluatedValues {
         return self.unary_operation(|a, new_values| new_values.values.push(a.sinh()));
@@ -164,6 +176,12 @@ impl EvaluatedValues {
             new_values.values.push(if (a == 0.0) {0.0} else {a / a.abs()})
         });
     }
+    pub fn minimum(&self, other : &EvaluatedValues) -> EvaluatedValues {
+        return self.binary_operation(other, |a, b, new_values| new_values.values.push(a.min(b)));
+    }
+    pub fn maximum(&self, other : &EvaluatedValues) -> EvaluatedValues {
+        return self.binary_operation(other, |a, b, new_values| new_values.values.push(a.max(b)));
+    }
 
     
     fn unary_operation<T>(&self, target : T) -> EvaluatedValues
@@ -196,7 +214,7 @@ impl EvaluatedValues {
                 .power(&self.multiplication(&EvaluatedValues::from(vec![-1.0])))
                 .multiplication(&EvaluatedValues::from(vec![sign]))
                 .addition(&EvaluatedValues::from(vec![var::E])
-                    .power(&self.multiplication(self))
+                    .power(self)
                 ),
             None => EvaluatedValues::from(vec![2.0])
         };
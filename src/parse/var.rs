@@ -1,6 +1,8 @@
 // https://en.wikipedia.org/wiki/List_of_mathematical_constants
 
-pub static PI  : f64 = 3.14159_26535_89793_23846; // π
-pub static TAU : f64 = 6.28318_53071_79586_47692; // 𝜏
-pub static PHI : f64 = 1.61803_39887_49894_84820; // φ
-pub static E   : f64 = 2.71828_18284_59045_23536; // e
+use std::f64::consts;
+
+pub static PI  : f64 = consts::PI;          // π
+pub static TAU : f64 = consts::TAU;         // 𝜏
+pub static PHI : f64 = 1.61803_39887_49895; // φ
+pub static E   : f64 = consts::E;           // e
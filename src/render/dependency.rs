@@ -0,0 +1,414 @@
+// Order of the definitions in an equation list.
+//
+// Definitions may come in any order, `b = a + 1` before `a = 2`, so they
+// are sorted to come after every definition they read. A definition that
+// reads itself, directly or through others, reads a name that is never
+// defined, or defines a variable already defined, is reported up front
+// with the offending equation and skipped, along with every equation that
+// reads it.
+//
+// Definitions that depend on no coordinate, `x`, `y`, `r` or `θ`, are
+// constants, evaluated once per render instead of at every point. Lists
+// whose length or order would depend on the coordinates, such as `[1...x]`,
+// are reported the same way, see `parse::list`, as are quantiles picked by
+// the coordinates, see `parse::statistics`.
+
+use std::collections::{HashMap, HashSet};
+
+use loggerithm::{logger, log};
+use loggerithm::level::{DEBUG, ERROR};
+logger!(super);
+
+use crate::error::Error;
+use crate::parse::node::{Node, NodeBase};
+use crate::render::settings::{RenderSettings, Evaluation};
+use super::{EquationKind, COORDINATES, constants};
+
+
+pub struct Dependencies {
+    // Index in the equation list of each definition depending on no
+    // coordinate, in the order they are evaluated.
+    constants : Vec<usize>,
+    // Index in the equation list of every other definition, in the order they are evaluated.
+    varying   : Vec<usize>,
+    // Whether each equation in the list is left out.
+    skipped   : Vec<bool>,
+    // Each equation left out, by index in the equation list, with why, in the order they were reported.
+    reports   : Vec<(usize, Error)>
+}
+impl Dependencies {
+    pub fn new(settings : &RenderSettings, nodes : &[Node], kinds : &[EquationKind]) -> Dependencies {
+        let mut known = constants().iter().map(|(name, _)| *name).collect::<HashSet<&str>>();
+        known.extend(COORDINATES);
+        if (settings.evaluation == Evaluation::Complex) {
+            known.insert("i");
+        }
+
+        let mut sorter = Sorter {
+            nodes,
+            known,
+            defined : HashMap::new(),
+            reads   : nodes.iter().enumerate().map(|(i, node)| reads(node, kinds[i])).collect(),
+            state   : vec![State::Unvisited; nodes.len()],
+            path    : vec![],
+            order   : vec![],
+            skipped : vec![false; nodes.len()],
+            reports : vec![]
+        };
+        for i in 0..nodes.len() {
+            if (kinds[i] != EquationKind::Definition) {
+                continue;
+            }
+            let name = defined_name(&nodes[i]);
+            if (sorter.defined.contains_key(name)) {
+                sorter.skip(i, Error::VariableRedefined(String::from(name)));
+            } else {
+                sorter.defined.insert(name, i);
+            }
+        }
+        for (i, kind) in kinds.iter().enumerate() {
+            if (*kind == EquationKind::Definition && ! sorter.skipped[i]) {
+                sorter.visit(i);
+            }
+        }
+        let mut varying   = vec![false; nodes.len()];
+        let mut constants = vec![];
+        let mut others    = vec![];
+        for i in sorter.order.clone() {
+            // A definition read may have been skipped for its lists.
+            if let Some(name) = sorter.reads[i].iter().find(|name| sorter.defined.get(*name).is_some_and(|definition| sorter.skipped[*definition])) {
+                sorter.skip(i, Error::SkippedDefinition(String::from(*name)));
+                continue;
+            }
+            if (sorter.check_lists(i, &varying, &[])) {
+                continue;
+            }
+            varying[i] = sorter.reads[i].iter().any(|name| match (sorter.defined.get(name)) {
+                Some(definition) => varying[*definition],
+                None             => COORDINATES.contains(name)
+            });
+            if (varying[i]) {
+                others.push(i);
+            } else {
+                constants.push(i);
+            }
+        }
+        if (! sorter.order.is_empty()) {
+            log!(DEBUG,
+                "Sorted {} definition{}, {} of them constant.",
+                constants.len() + others.len(),
+                if (constants.len() + others.len() == 1) {""} else {"s"},
+                constants.len()
+            );
+        }
+        // Everything else that is drawn only needs its names and lists checked.
+        for (i, kind) in kinds.iter().enumerate() {
+            let parameters : &[&str] = match (kind) {
+                EquationKind::Explicit | EquationKind::Implicit | EquationKind::Region | EquationKind::Polar => &[],
+                EquationKind::Parametric                                                                  => &["t"],
+                _                                                                                         => continue
+            };
+            sorter.check_names(i, parameters);
+            if (! sorter.skipped[i]) {
+                sorter.check_lists(i, &varying, parameters);
+            }
+        }
+        return Dependencies {constants, varying: others, skipped: sorter.skipped, reports: sorter.reports};
+    }
+
+    // Whether an equation was reported and must not be drawn.
+    pub fn is_skipped(&self, index : usize) -> bool {
+        return self.skipped[index];
+    }
+
+    // Each equation left out, by index in the equation list, with why.
+    pub fn reports(&self) -> &[(usize, Error)] {
+        return &self.reports;
+    }
+
+    // Definitions depending on no coordinate, in the order they are evaluated.
+    pub fn constants<'l>(&self, nodes : &'l [Node]) -> Vec<&'l Node> {
+        return self.constants.iter().map(|i| &nodes[*i]).collect();
+    }
+
+    // Every other definition, in the order they are evaluated.
+    pub fn varying<'l>(&self, nodes : &'l [Node]) -> Vec<&'l Node> {
+        return self.varying.iter().map(|i| &nodes[*i]).collect();
+    }
+}
+
+
+#[derive(Clone, Copy, PartialEq)]
+enum State {
+    Unvisited,
+    Visiting,
+    Visited
+}
+
+struct Sorter<'l> {
+    nodes   : &'l [Node],
+    // Names defined without a definition, such as `x` and `pi`.
+    known   : HashSet<&'static str>,
+    // Index in the equation list of the definition of each variable.
+    defined : HashMap<&'l str, usize>,
+    // Names read by each equation, in the order they first appear.
+    reads   : Vec<Vec<&'l str>>,
+    state   : Vec<State>,
+    // Definitions being visited, outermost first.
+    path    : Vec<usize>,
+    // Definitions that are not skipped, each after those it reads.
+    order   : Vec<usize>,
+    skipped : Vec<bool>,
+    reports : Vec<(usize, Error)>
+}
+impl<'l> Sorter<'l> {
+    // Depth first through the definitions a definition reads. Returns whether it is kept.
+    fn visit(&mut self, i : usize) -> bool {
+        if (self.state[i] == State::Visited) {
+            return ! self.skipped[i];
+        }
+        self.state[i] = State::Visiting;
+        self.path.push(i);
+        for name in self.reads[i].clone() {
+            match (self.defined.get(name).cloned()) {
+                Some(definition) if (self.state[definition] == State::Visiting) => {
+                    let start = self.path.iter().position(|j| *j == definition).unwrap();
+                    let cycle = self.path[start..].to_vec();
+                    for (k, member) in cycle.iter().enumerate() {
+                        // Named from each member, so every report starts with its own variable.
+                        let names = cycle[k..].iter().chain(cycle[..k].iter()).chain([member])
+                            .map(|j| String::from(defined_name(&self.nodes[*j])))
+                            .collect::<Vec<String>>();
+                        self.skip(*member, Error::CyclicDefinition(names));
+                    }
+                },
+                Some(definition) => {
+                    if (! self.visit(definition)) {
+                        self.skip(i, Error::SkippedDefinition(String::from(name)));
+                    }
+                },
+                None => {
+                    if (! self.known.contains(name)) {
+                        self.skip(i, Error::UndefinedVariable(String::from(name)));
+                    }
+                }
+            };
+        }
+        self.path.pop();
+        self.state[i] = State::Visited;
+        if (! self.skipped[i]) {
+            self.order.push(i);
+        }
+        return ! self.skipped[i];
+    }
+
+    // Skip an equation reading a name that is not defined, or whose
+    // definition is skipped. `parameters` are known to this equation only.
+    fn check_names(&mut self, i : usize, parameters : &[&str]) {
+        for name in self.reads[i].clone() {
+            match (self.defined.get(name).cloned()) {
+                Some(definition) if (self.skipped[definition])                       => self.skip(i, Error::SkippedDefinition(String::from(name))),
+                Some(_)                                                              => (),
+                None if (! self.known.contains(name) && ! parameters.contains(&name)) => self.skip(i, Error::UndefinedVariable(String::from(name))),
+                None                                                                 => ()
+            };
+        }
+    }
+
+    // Skip an equation with a list whose length or order depends on the
+    // point, or a quantile picked by it. Returns whether it is skipped.
+    fn check_lists(&mut self, i : usize, varying : &[bool], parameters : &[&str]) -> bool {
+        let varies = |name : &str| match (self.defined.get(name)) {
+            Some(definition) => varying[*definition],
+            None             => COORDINATES.contains(&name) || parameters.contains(&name)
+        };
+        let error = varying_list(&self.nodes[i], &varies, &mut vec![]).map(|list| match (&list.base) {
+            NodeBase::Quantile (_, _) |
+            NodeBase::Quartile (_, _) => Error::QuantileFraction(list.to_string()),
+            _                         => Error::VaryingList(list.to_string())
+        });
+        if let Some(error) = error {
+            self.skip(i, error);
+            return true;
+        }
+        return false;
+    }
+
+    // Report an equation the first time it is skipped.
+    fn skip(&mut self, i : usize, error : Error) {
+        if (! self.skipped[i]) {
+            log!(ERROR, "Equation {} `{}` skipped: {}", i + 1, self.nodes[i].to_string(), error);
+            self.skipped[i] = true;
+            self.reports.push((i, error));
+        }
+    }
+}
+
+// Name of the variable a definition binds.
+fn defined_name(node : &Node) -> &str {
+    return match (&node.base) {
+        NodeBase::Equals(left, _) => match (&left.base) {
+            NodeBase::Variable(name) => name,
+            _                        => ""
+        },
+        _ => ""
+    };
+}
+
+// Names an equation reads. A definition only reads its right side.
+fn reads(node : &Node, kind : EquationKind) -> Vec<&str> {
+    let mut names = vec![];
+    match (&node.base) {
+        NodeBase::Equals(_, right) if (kind == EquationKind::Definition) => find_names(right, &mut names),
+        _                                                               => find_names(node, &mut names)
+    };
+    return names;
+}
+
+// Names read, leaving out those bound by a comprehension around them.
+fn find_names<'l>(node : &'l Node, names : &mut Vec<&'l str>) {
+    find_free_names(node, &mut vec![], names);
+}
+fn find_free_names<'l>(node : &'l Node, bound : &mut Vec<&'l str>, names : &mut Vec<&'l str>) {
+    match (&node.base) {
+        NodeBase::Variable(name) => {
+            if (! bound.contains(&name.as_str()) && ! names.contains(&name.as_str())) {
+                names.push(name);
+            }
+        },
+        NodeBase::For(body, name, list) => {
+            find_free_names(list, bound, names);
+            bound.push(name);
+            find_free_names(body, bound, names);
+            bound.pop();
+        },
+        base => {
+            for child in base.children() {
+                find_free_names(child, bound, names);
+            }
+        }
+    };
+}
+
+// First list whose length or order depends on a varying name, with range
+// bounds, the elements of `unique` and the seed of `shuffle` checked, or
+// quantile whose fraction does.
+// `bound` holds each comprehension variable around the node, and whether
+// its list varies.
+fn varying_list<'l>(node : &'l Node, varies : &dyn Fn(&str) -> bool, bound : &mut Vec<(&'l str, bool)>) -> Option<&'l Node> {
+    let checked = match (&node.base) {
+        NodeBase::Range    (_, _, _)       => node.base.children(),
+        NodeBase::Unique   (list)          => vec![list.as_ref()],
+        NodeBase::Shuffle  (_, Some(seed)) => vec![seed.as_ref()],
+        NodeBase::Quantile (_, fraction)   |
+        NodeBase::Quartile (_, fraction)   => vec![fraction.as_ref()],
+        _                                  => vec![]
+    };
+    if (checked.iter().any(|child| reads_varying(child, varies, bound))) {
+        return Some(node);
+    }
+    if let NodeBase::For(body, name, list) = &node.base {
+        let list_varies = reads_varying(list, varies, bound);
+        let found       = varying_list(list, varies, bound);
+        bound.push((name, list_varies));
+        let found = found.or_else(|| varying_list(body, varies, bound));
+        bound.pop();
+        return found;
+    }
+    return node.base.children().into_iter().find_map(|child| varying_list(child, varies, bound));
+}
+
+// Whether a node reads a varying name.
+fn reads_varying<'l>(node : &'l Node, varies : &dyn Fn(&str) -> bool, bound : &mut Vec<(&'l str, bool)>) -> bool {
+    return match (&node.base) {
+        NodeBase::Variable(name) => match (bound.iter().rev().find(|(bound_name, _)| bound_name == name)) {
+            Some((_, list_varies)) => *list_varies,
+            None                   => varies(name)
+        },
+        NodeBase::For(body, name, list) => {
+            let list_varies = reads_varying(list, varies, bound);
+            bound.push((name, list_varies));
+            let body_varies = reads_varying(body, varies, bound);
+            bound.pop();
+            list_varies || body_varies
+        },
+        base => base.children().into_iter().any(|child| reads_varying(child, varies, bound))
+    };
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::import::{self, Format};
+    use crate::render::{classify, check};
+
+    fn sort(source : &str, settings : &RenderSettings) -> (Vec<Node>, Dependencies) {
+        let nodes        = import::parse(source, Format::Text).unwrap().nodes;
+        let kinds        = nodes.iter().map(classify).collect::<Vec<EquationKind>>();
+        let dependencies = Dependencies::new(settings, &nodes, &kinds);
+        return (nodes, dependencies);
+    }
+
+    #[test]
+    fn definitions_are_sorted_after_what_they_read() {
+        let (nodes, dependencies) = sort("c = b * 2\nb = a + 1\na = 2\ny = c * x", &RenderSettings::new());
+        let constants = dependencies.constants(&nodes).iter().map(|node| node.to_string()).collect::<Vec<String>>();
+        assert_eq!(constants, vec![nodes[2].to_string(), nodes[1].to_string(), nodes[0].to_string()]);
+        assert!(dependencies.reports().is_empty());
+        assert!((0..nodes.len()).all(|i| ! dependencies.is_skipped(i)));
+    }
+
+    #[test]
+    fn cycles_are_skipped_with_what_reads_them() {
+        let (_, dependencies) = sort("a = b + 1\nb = a\nc = a\ny = x", &RenderSettings::new());
+        assert!(dependencies.is_skipped(0));
+        assert!(dependencies.is_skipped(1));
+        assert!(dependencies.is_skipped(2));
+        assert!(! dependencies.is_skipped(3));
+        let cycles = dependencies.reports().iter().filter_map(|(i, error)| match (error) {
+            Error::CyclicDefinition(names) => Some((*i, names.clone())),
+            _                              => None
+        }).collect::<HashMap<usize, Vec<String>>>();
+        assert_eq!(cycles.get(&0), Some(&vec![String::from("a"), String::from("b"), String::from("a")]));
+        assert_eq!(cycles.get(&1), Some(&vec![String::from("b"), String::from("a"), String::from("b")]));
+        assert!(dependencies.reports().iter().any(|(i, error)| *i == 2 && matches!(error, Error::SkippedDefinition(name) if name == "a")));
+
+        let (_, dependencies) = sort("a = a + 1", &RenderSettings::new());
+        assert!(matches!(dependencies.reports(), [(0, Error::CyclicDefinition(_))]));
+    }
+
+    #[test]
+    fn undefined_names_are_skipped() {
+        let (_, dependencies) = sort("a = q\ny = a * x\ny = k * x\ny = x", &RenderSettings::new());
+        assert!(matches!(dependencies.reports(), [
+            (0, Error::UndefinedVariable(_)),
+            (1, Error::SkippedDefinition(_)),
+            (2, Error::UndefinedVariable(_))
+        ]));
+        assert!(! dependencies.is_skipped(3));
+
+        // `i` is only known to complex evaluation.
+        let (_, dependencies) = sort("y = i * x", &RenderSettings::new());
+        assert!(dependencies.is_skipped(0));
+        let (_, dependencies) = sort("y = i * x", &RenderSettings::new().set_evaluation(Evaluation::Complex));
+        assert!(! dependencies.is_skipped(0));
+    }
+
+    #[test]
+    fn redefined_variables_are_skipped() {
+        let (_, dependencies) = sort("a = 1\na = 2\ny = a * x", &RenderSettings::new());
+        // The first definition is kept, and read by the rest.
+        assert!(matches!(dependencies.reports(), [(1, Error::VariableRedefined(name))] if name == "a"));
+        assert!(! dependencies.is_skipped(0));
+        assert!(! dependencies.is_skipped(2));
+    }
+
+    #[test]
+    fn check_fails_when_any_equation_is_skipped() {
+        let nodes = |source : &str| import::parse(source, Format::Text).unwrap().nodes;
+        assert!(check(&nodes("b = a + 1\na = 2\ny = b * x"), &RenderSettings::new()).is_ok());
+        assert!(matches!(check(&nodes("a = b\nb = a\ny = x"), &RenderSettings::new()), Err(Error::SkippedEquations(2))));
+        assert!(matches!(check(&nodes("y = q * x"), &RenderSettings::new()), Err(Error::SkippedEquations(1))));
+    }
+}
@@ -0,0 +1,140 @@
+use rayon::prelude::*;
+use loggerithm::{logger, log};
+use loggerithm::level::{TRACE, DEBUG, ERROR};
+logger!(super);
+
+use crate::helper;
+use crate::parse::node::Node;
+use crate::parse::values::EvaluatedValues;
+use crate::render::settings::RenderSettings;
+use super::EquationKind;
+use super::dependency::Dependencies;
+use super::variables::{PointEquations, Quantity};
+
+
+// `y = f(x)` equations, sampled at the edges and middle of every column.
+// Each column stores the ranges of `y` the curves sweep across it, so a
+// cell is hit wherever a curve passes through, not only where it crosses
+// the cell's left or right edge.
+pub struct ExplicitEquations {
+    // Index in the equation list, Bottom, Top of each curve piece, for each column.
+    column_ranges : Vec<Vec<(usize, [f64; 2])>>
+}
+impl ExplicitEquations {
+    pub fn new(settings : &RenderSettings, resolution : &[u32; 2], nodes : &[Node], kinds : &[EquationKind], dependencies : &Dependencies) -> ExplicitEquations {
+        let (indices, samples) = generate_column_values(settings, resolution, nodes, kinds, dependencies);
+        let pixel_height  = (settings.frame[3] - settings.frame[1]) / (u32::pow(2, settings.split_depth) as f64);
+        let column_ranges = (0..resolution[0] as usize).into_par_iter()
+            .map(|i| {
+                let mut ranges = vec![];
+                // Each equation is joined up on its own, so one curve is never bridged to another.
+                for (j, index) in indices.iter().enumerate() {
+                    for range in generate_column_ranges(&samples[i * 2][j], &samples[i * 2 + 1][j], &samples[i * 2 + 2][j], pixel_height) {
+                        ranges.push((*index, range));
+                    }
+                }
+                return ranges;
+            })
+            .collect();
+        return ExplicitEquations {column_ranges};
+    }
+
+    // Index in the equation list of each curve passing through the cell.
+    pub fn check(&self, settings : &RenderSettings, position : [f32; 2], size : f32) -> Vec<usize> {
+        let mut hits = vec![];
+        let columns  = self.column_ranges.len();
+        if (columns == 0) {
+            return hits;
+        }
+        // Cells narrower than a column still test the column they are in.
+        let first  = ((position[0] * columns as f32) as usize).min(columns - 1);
+        let last   = (((position[0] + size) * columns as f32).ceil() as usize).clamp(first + 1, columns);
+        let bottom = settings.frame[1] + (settings.frame[3] - settings.frame[1]) * (position[1] as f64);
+        let top    = settings.frame[1] + (settings.frame[3] - settings.frame[1]) * ((position[1] + size) as f64);
+        for ranges in &self.column_ranges[first..last] {
+            for (index, range) in ranges {
+                if (range[0] < top && range[1] >= bottom && ! hits.contains(index)) {
+                    hits.push(*index);
+                }
+            }
+        }
+        return hits;
+    }
+}
+
+
+// Generate values at the left edge, middle and right edge of each column,
+// from the definitions and explicit equations. Returns the index in the
+// equation list of each explicit equation, and its values at each sample.
+// An equation that fails to evaluate is reported once and left out of every column.
+fn generate_column_values(settings : &RenderSettings, resolution : &[u32; 2], nodes : &[Node], kinds : &[EquationKind], dependencies : &Dependencies) -> (Vec<usize>, Vec<Vec<EvaluatedValues>>) {
+    let samples = resolution[0] * 2 + 1;
+    log!(DEBUG,
+        "Generating values for {} column{}.",
+        helper::commaify_i64(resolution[0].into()),
+        if (resolution[0] == 1) {""} else {"s"}
+    );
+    let quantities = (0..nodes.len())
+        .filter(|j| kinds[*j] == EquationKind::Explicit && ! dependencies.is_skipped(*j))
+        .filter_map(|j| nodes[j].base.relation().map(|(_, _, right)| (j, Quantity::Value(right))))
+        .collect();
+    // Definitions only fail here when they read `y`, which the equations
+    // reading them then report.
+    let (equations, failures) = PointEquations::new(settings, nodes, dependencies, &["x"], quantities);
+    for (j, error) in failures {
+        log!(ERROR, "Equation {} `{}` skipped: {}", j + 1, nodes[j].to_string(), error);
+    }
+    let values = (0..samples).into_par_iter().map(|i| {
+        let x      = settings.frame[0] + (settings.frame[2] - settings.frame[0]) * ((i as f64) / ((samples - 1) as f64));
+        let values = equations.evaluate(&[x]).iter().map(|values| values.compress()).collect::<Vec<EvaluatedValues>>();
+        log!(TRACE,
+            "Values {} found for sample {}.",
+            values.iter().map(|values| format!("[{}]", values)).collect::<Vec<String>>().join(", "),
+            helper::commaify_i64(i.into())
+        );
+        return values;
+    }).collect();
+    return (equations.indices().clone(), values);
+}
+
+// Join each value on one edge of a column to the nearest value on the other
+// edge. The pair is only joined when the middle sample lies between them,
+// give or take two pixels. Across an asymptote like those of `tan(x)` the
+// middle sample is always outside, so the branches are not bridged.
+fn generate_column_ranges(left : &EvaluatedValues, middle : &EvaluatedValues, right : &EvaluatedValues, pixel_height : f64) -> Vec<[f64; 2]> {
+    // Middle samples are always hit, in case they belong to no pair.
+    let mut ranges = middle.get_values().iter().map(|c| [*c, *c]).collect::<Vec<[f64; 2]>>();
+    for (from, to) in [(left, right), (right, left)] {
+        for a in from.get_values() {
+            let b = match (nearest(to, *a)) {
+                Some(b) => b,
+                None    => {
+                    ranges.push([*a, *a]);
+                    continue;
+                }
+            };
+            let bottom    = a.min(b);
+            let top       = a.max(b);
+            let tolerance = pixel_height * 2.0;
+            match (nearest(middle, (a + b) / 2.0)) {
+                Some(c) if (c >= bottom - tolerance && c <= top + tolerance) => {
+                    ranges.push([bottom.min(c), top.max(c)]);
+                },
+                _ => {
+                    ranges.push([*a, *a]);
+                }
+            };
+        }
+    }
+    return ranges;
+}
+
+fn nearest(values : &EvaluatedValues, target : f64) -> Option<f64> {
+    let mut nearest : Option<f64> = None;
+    for value in values.get_values() {
+        if (nearest.is_none_or(|n| (value - target).abs() < (n - target).abs())) {
+            nearest = Some(*value);
+        }
+    }
+    return nearest;
+}
@@ -0,0 +1,123 @@
+// G-code for GRBL style laser controllers.
+//
+//     G21
+//     G90
+//     M5
+//     G0 X12.500 Y50.195
+//     M4 S1000
+//     G1 X37.891 Y50.195 F1000
+//     M5
+//     ...
+//     G0 X0 Y0
+//     M2
+//
+// The frame is scaled to the job size, with its bottom left corner at the
+// machine origin. The laser is off for every `G0` travel move.
+
+use loggerithm::{logger, log};
+use loggerithm::level::DEBUG;
+logger!(super);
+
+use crate::helper;
+use crate::render::settings::{RenderSettings, LaserMode};
+
+
+// Write a program burning each path, in order.
+// Paths are lists of points in graph space.
+pub fn write(paths : &[Vec<[f64; 2]>], settings : &RenderSettings) -> String {
+    let gcode    = &settings.gcode;
+    let laser_on = match (gcode.laser_mode) {
+        LaserMode::Constant => "M3",
+        LaserMode::Dynamic  => "M4"
+    };
+    log!(DEBUG,
+        "Writing {} path{} as G-code for a {}x{}mm job.",
+        helper::commaify_i64(paths.len() as i64),
+        if (paths.len() == 1) {""} else {"s"},
+        settings.size[0], settings.size[1]
+    );
+
+    let mut lines = vec![
+        String::from("; lrinser_laser_etcher"),
+        String::from("G21 ; Millimetres"),
+        String::from("G90 ; Absolute positions"),
+        String::from("M5")
+    ];
+    for path in paths {
+        if (path.is_empty()) {
+            continue;
+        }
+        let start = to_millimetres(path[0], settings);
+        lines.push(format!("G0 X{:.3} Y{:.3}", start[0], start[1]));
+        lines.push(format!("{} S{}", laser_on, gcode.power));
+        if (path.len() == 1) {
+            // A lone point is burnt in place.
+            lines.push(format!("G1 X{:.3} Y{:.3} F{}", start[0], start[1], gcode.feed));
+        }
+        for (i, point) in path.iter().enumerate().skip(1) {
+            let point = to_millimetres(*point, settings);
+            if (i == 1) {
+                lines.push(format!("G1 X{:.3} Y{:.3} F{}", point[0], point[1], gcode.feed));
+            } else {
+                lines.push(format!("G1 X{:.3} Y{:.3}", point[0], point[1]));
+            }
+        }
+        lines.push(String::from("M5"));
+    }
+    lines.push(String::from("G0 X0 Y0"));
+    lines.push(String::from("M2"));
+    return lines.join("\n") + "\n";
+}
+
+// Graph space to machine space.
+fn to_millimetres(point : [f64; 2], settings : &RenderSettings) -> [f64; 2] {
+    return [
+        (point[0] - settings.frame[0]) / (settings.frame[2] - settings.frame[0]) * settings.size[0],
+        (point[1] - settings.frame[1]) / (settings.frame[3] - settings.frame[1]) * settings.size[1]
+    ];
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::settings::GcodeSettings;
+
+    #[test]
+    fn burns_each_path_after_travelling_to_it() {
+        let settings = RenderSettings::new().set_size([200.0, 100.0]);
+        let paths    = vec![vec![[-5.0, -5.0], [0.0, 0.0], [5.0, 0.0]], vec![[2.5, 5.0]]];
+        assert_eq!(write(&paths, &settings), [
+            "; lrinser_laser_etcher",
+            "G21 ; Millimetres",
+            "G90 ; Absolute positions",
+            "M5",
+            "G0 X0.000 Y0.000",
+            "M4 S1000",
+            "G1 X100.000 Y50.000 F1000",
+            "G1 X200.000 Y50.000",
+            "M5",
+            "G0 X150.000 Y100.000",
+            "M4 S1000",
+            "G1 X150.000 Y100.000 F1000",
+            "M5",
+            "G0 X0 Y0",
+            "M2",
+            ""
+        ].join("\n"));
+    }
+
+    #[test]
+    fn laser_mode_picks_the_command() {
+        let paths    = vec![vec![[0.0, 0.0], [1.0, 1.0]]];
+        let constant = GcodeSettings::new().set_laser_mode(LaserMode::Constant).set_power(250).set_feed(600.0);
+        let program  = write(&paths, &RenderSettings::new().set_gcode(constant));
+        assert!(program.contains("\nM3 S250\nG1 X60.000 Y60.000 F600\nM5\n"), "{}", program);
+        assert!(! program.contains("M4"));
+    }
+
+    #[test]
+    fn empty_jobs_only_return_home() {
+        assert_eq!(write(&[vec![]], &RenderSettings::new()), "; lrinser_laser_etcher\nG21 ; Millimetres\nG90 ; Absolute positions\nM5\nG0 X0 Y0\nM2\n");
+    }
+}
@@ -0,0 +1,266 @@
+// Parallel fill lines for engraving areas filled rather than outlined.
+//
+// Lines are laid out in millimetres on the job, so the angle and spacing
+// are true even when the graph's axes are scaled differently. Each row is
+// cut where it enters and leaves the area, either by crossing the edges
+// of closed polylines, with nested loops leaving holes, or by testing
+// points along it and narrowing down each change by halving.
+//
+// Rows are given in order across the area. With `HatchSettings::bidirectional`
+// every other row runs back the other way, so the laser zigzags instead
+// of travelling back across the job after each line. A cross hatch adds a
+// second pass at right angles to the first.
+
+use rayon::prelude::*;
+use loggerithm::{logger, log};
+use loggerithm::level::DEBUG;
+logger!(super);
+
+use crate::error::Error;
+use crate::helper;
+use crate::render::settings::RenderSettings;
+
+
+// Halvings used to find where a row crosses the edge of an area.
+const BORDER_STEPS : u32 = 12;
+// Most rows in one pass across the job.
+pub const MAX_ROWS : usize = 10_000;
+
+
+// Fill the areas enclosed by closed polylines in graph space. Paths that
+// do not end where they start are left out.
+pub fn fill_polygons(polygons : &[Vec<[f64; 2]>], settings : &RenderSettings) -> Result<Vec<Vec<[f64; 2]>>, Error> {
+    let polygons = polygons.iter()
+        .filter(|polygon| polygon.len() > 3 && polygon[0] == polygon[polygon.len() - 1])
+        .map(|polygon| polygon.iter().map(|point| to_millimetres(*point, settings)).collect::<Vec<[f64; 2]>>())
+        .collect::<Vec<Vec<[f64; 2]>>>();
+    if (polygons.is_empty()) {
+        return Ok(vec![]);
+    }
+    let mut lines = vec![];
+    for angle in passes(settings) {
+        let [along, across] = axes(angle);
+        let rows = row_offsets(settings, across)?.into_iter().map(|offset| {
+            // Where each edge crosses the row, as a distance along it.
+            let mut crossings = vec![];
+            for polygon in &polygons {
+                for edge in polygon.windows(2) {
+                    let [start, end] = [dot(edge[0], across), dot(edge[1], across)];
+                    if ((start > offset) != (end > offset)) {
+                        let t = (offset - start) / (end - start);
+                        crossings.push(dot(edge[0], along) + (dot(edge[1], along) - dot(edge[0], along)) * t);
+                    }
+                }
+            }
+            crossings.sort_by(|a, b| a.total_cmp(b));
+            return crossings.chunks_exact(2)
+                .map(|pair| [point_at(along, across, pair[0], offset), point_at(along, across, pair[1], offset)])
+                .collect::<Vec<[[f64; 2]; 2]>>();
+        }).collect::<Vec<Vec<[[f64; 2]; 2]>>>();
+        lines.append(&mut order(rows, settings));
+    }
+    return Ok(finish(lines, settings));
+}
+
+// Fill the part of the frame where `inside` holds, for points in graph space.
+pub fn fill_area<F : Fn([f64; 2]) -> bool + Sync>(inside : F, settings : &RenderSettings) -> Result<Vec<Vec<[f64; 2]>>, Error> {
+    // Sample about once per cell of the render node tree.
+    let grid_size = u32::pow(2, settings.split_depth) as f64;
+    let step      = (settings.size[0] / grid_size).min(settings.size[1] / grid_size);
+    let inside    = |point : [f64; 2]| inside(from_millimetres(point, settings));
+    let mut lines = vec![];
+    for angle in passes(settings) {
+        let [along, across] = axes(angle);
+        let rows = row_offsets(settings, across)?.into_par_iter().map(|offset| {
+            let [start, end] = match (clip_row(settings, along, across, offset)) {
+                Some(ends) => ends,
+                None       => return vec![]
+            };
+            let samples    = ((end - start) / step).ceil().max(1.0) as usize;
+            let at         = |distance : f64| inside(point_at(along, across, distance, offset));
+            let mut row    = vec![];
+            let mut was_in = at(start);
+            let mut enter  = if (was_in) {Some(start)} else {None};
+            for sample in 1..=samples {
+                let distance = start + (end - start) * sample as f64 / samples as f64;
+                let is_in    = at(distance);
+                if (is_in != was_in) {
+                    let border = find_border(&at, distance - (end - start) / samples as f64, distance);
+                    if (is_in) {
+                        enter = Some(border);
+                    } else if let Some(enter) = enter.take() {
+                        row.push([point_at(along, across, enter, offset), point_at(along, across, border, offset)]);
+                    }
+                }
+                was_in = is_in;
+            }
+            if let Some(enter) = enter {
+                row.push([point_at(along, across, enter, offset), point_at(along, across, end, offset)]);
+            }
+            return row;
+        }).collect::<Vec<Vec<[[f64; 2]; 2]>>>();
+        lines.append(&mut order(rows, settings));
+    }
+    return Ok(finish(lines, settings));
+}
+
+
+// Angle of each pass in radians.
+fn passes(settings : &RenderSettings) -> Vec<f64> {
+    let angle = settings.hatch.angle.to_radians();
+    return if (settings.hatch.cross) {vec![angle, angle + std::f64::consts::FRAC_PI_2]} else {vec![angle]};
+}
+
+// Unit vectors along and across the rows of a pass.
+fn axes(angle : f64) -> [[f64; 2]; 2] {
+    return [[angle.cos(), angle.sin()], [- angle.sin(), angle.cos()]];
+}
+
+// Distance of each row from the origin, across the rows, so that the rows
+// cover the whole job, `spacing` apart. Fails for spacings that are not
+// positive numbers, or would need more than `MAX_ROWS` rows.
+fn row_offsets(settings : &RenderSettings, across : [f64; 2]) -> Result<Vec<f64>, Error> {
+    let corners = [[0.0, 0.0], [settings.size[0], 0.0], [0.0, settings.size[1]], settings.size];
+    let low     = corners.iter().map(|corner| dot(*corner, across)).fold(f64::INFINITY, f64::min);
+    let high    = corners.iter().map(|corner| dot(*corner, across)).fold(f64::NEG_INFINITY, f64::max);
+    let spacing = settings.hatch.spacing;
+    let rows    = ((high - low) / spacing).floor();
+    if (! (spacing.is_finite() && spacing > 0.0) || rows > MAX_ROWS as f64) {
+        return Err(Error::HatchSpacing(spacing));
+    }
+    let rows    = rows as usize;
+    // Centre the rows, so the gaps at either side match.
+    let margin  = (high - low - spacing * (rows as f64 - 1.0)) / 2.0;
+    return Ok((0..rows).map(|row| low + margin + spacing * row as f64).collect());
+}
+
+// Distances along a row where it enters and leaves the job.
+fn clip_row(settings : &RenderSettings, along : [f64; 2], across : [f64; 2], offset : f64) -> Option<[f64; 2]> {
+    let origin    = [across[0] * offset, across[1] * offset];
+    let mut range = [f64::NEG_INFINITY, f64::INFINITY];
+    for axis in 0..2 {
+        if (along[axis].abs() < 1e-12) {
+            if (origin[axis] < 0.0 || origin[axis] > settings.size[axis]) {
+                return None;
+            }
+            continue;
+        }
+        let a = (0.0 - origin[axis]) / along[axis];
+        let b = (settings.size[axis] - origin[axis]) / along[axis];
+        range = [range[0].max(a.min(b)), range[1].min(a.max(b))];
+    }
+    return if (range[0] < range[1]) {Some(range)} else {None};
+}
+
+// Join the rows of a pass into lines, reversing every other row when bidirectional.
+fn order(rows : Vec<Vec<[[f64; 2]; 2]>>, settings : &RenderSettings) -> Vec<Vec<[f64; 2]>> {
+    let mut lines = vec![];
+    for (i, row) in rows.into_iter().filter(|row| ! row.is_empty()).enumerate() {
+        if (settings.hatch.bidirectional && i % 2 == 1) {
+            lines.extend(row.into_iter().rev().map(|[start, end]| vec![end, start]));
+        } else {
+            lines.extend(row.into_iter().map(|[start, end]| vec![start, end]));
+        }
+    }
+    return lines;
+}
+
+// Back to graph space.
+fn finish(lines : Vec<Vec<[f64; 2]>>, settings : &RenderSettings) -> Vec<Vec<[f64; 2]>> {
+    log!(DEBUG,
+        "Hatched {} line{}.",
+        helper::commaify_i64(lines.len() as i64),
+        if (lines.len() == 1) {""} else {"s"}
+    );
+    return lines.into_iter()
+        .map(|line| line.into_iter().map(|point| from_millimetres(point, settings)).collect())
+        .collect();
+}
+
+// Narrow down where `inside` changes between `a` and `b` by halving.
+fn find_border<F : Fn(f64) -> bool>(inside : &F, mut a : f64, mut b : f64) -> f64 {
+    let a_inside = inside(a);
+    for _ in 0..BORDER_STEPS {
+        let middle = (a + b) / 2.0;
+        if (inside(middle) == a_inside) {
+            a = middle;
+        } else {
+            b = middle;
+        }
+    }
+    return (a + b) / 2.0;
+}
+
+fn dot(a : [f64; 2], b : [f64; 2]) -> f64 {
+    return a[0] * b[0] + a[1] * b[1];
+}
+
+fn point_at(along : [f64; 2], across : [f64; 2], distance : f64, offset : f64) -> [f64; 2] {
+    return [along[0] * distance + across[0] * offset, along[1] * distance + across[1] * offset];
+}
+
+// Graph space to millimetres from the bottom left of the job.
+fn to_millimetres(point : [f64; 2], settings : &RenderSettings) -> [f64; 2] {
+    return [
+        (point[0] - settings.frame[0]) / (settings.frame[2] - settings.frame[0]) * settings.size[0],
+        (point[1] - settings.frame[1]) / (settings.frame[3] - settings.frame[1]) * settings.size[1]
+    ];
+}
+
+fn from_millimetres(point : [f64; 2], settings : &RenderSettings) -> [f64; 2] {
+    return [
+        settings.frame[0] + point[0] / settings.size[0] * (settings.frame[2] - settings.frame[0]),
+        settings.frame[1] + point[1] / settings.size[1] * (settings.frame[3] - settings.frame[1])
+    ];
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::settings::HatchSettings;
+
+    // A 10 by 10 job over the default frame, hatched `spacing` apart.
+    fn settings(spacing : f64) -> RenderSettings {
+        let mut settings = RenderSettings::new().set_size([10.0, 10.0]).set_split_depth(4);
+        settings.hatch.spacing = spacing;
+        return settings;
+    }
+
+    #[test]
+    fn rows_are_centred_and_evenly_spaced() {
+        assert_eq!(row_offsets(&settings(2.0), [0.0, 1.0]).unwrap(), [1.0, 3.0, 5.0, 7.0, 9.0]);
+        assert_eq!(row_offsets(&settings(3.0), [0.0, 1.0]).unwrap(), [2.0, 5.0, 8.0]);
+    }
+
+    #[test]
+    fn spacing_must_be_positive() {
+        for spacing in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            assert!(matches!(row_offsets(&settings(spacing), [0.0, 1.0]), Err(Error::HatchSpacing(_))), "{}", spacing);
+            assert!(matches!(fill_area(|_| true, &settings(spacing)), Err(Error::HatchSpacing(_))), "{}", spacing);
+        }
+    }
+
+    #[test]
+    fn rows_are_capped() {
+        assert_eq!(row_offsets(&settings(10.0 / MAX_ROWS as f64), [0.0, 1.0]).unwrap().len(), MAX_ROWS);
+        assert!(matches!(row_offsets(&settings(1e-9), [0.0, 1.0]), Err(Error::HatchSpacing(_))));
+    }
+
+    #[test]
+    fn invalid_spacing_settings_are_ignored() {
+        assert_eq!(HatchSettings::new().set_spacing(0.0).spacing, HatchSettings::new().spacing);
+        assert_eq!(HatchSettings::new().set_spacing(f64::NAN).spacing, HatchSettings::new().spacing);
+        assert_eq!(HatchSettings::new().set_spacing(0.25).spacing, 0.25);
+    }
+
+    #[test]
+    fn areas_are_filled_between_their_borders() {
+        // Right of `x = 0`, half the job.
+        let lines = fill_area(|point| point[0] > 0.0, &settings(2.0)).unwrap();
+        assert_eq!(lines.len(), 5);
+        for line in lines {
+            assert!((line[0][0] - 0.0).abs() < 0.01 && line[1][0] == 5.0, "{:?}", line);
+        }
+    }
+}
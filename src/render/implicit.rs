@@ -0,0 +1,147 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use loggerithm::{logger, log};
+use loggerithm::level::{DEBUG, ERROR};
+logger!(super);
+
+use crate::parse::node::Node;
+use crate::parse::values::EvaluatedValues;
+use crate::render::settings::RenderSettings;
+use super::{EquationKind, COORDINATES, reads_polar};
+use super::dependency::Dependencies;
+use super::polar;
+use super::variables::{PointEquations, Quantity};
+
+
+// Cells shallower than this always pass, as a curve can sit inside a
+// large cell without changing sign at any of its corners. Shallow renders
+// test the last two depths, see `ImplicitEquations::check`.
+const MIN_DEPTH : u32 = 6;
+// Number of separately locked parts of the corner cache, so threads
+// checking different cells rarely wait on each other.
+const CORNER_SHARDS : usize = 64;
+
+
+// Relations in `x` and `y` that are not `y = f(x)`, such as `x^2 + y^2 = 2.5^2`.
+// A cell is hit when `left - right` changes sign between its corners.
+pub struct ImplicitEquations<'l> {
+    // `left - right` of each equation.
+    equations : PointEquations<'l>,
+    // Values of `left - right` for each equation, keyed by grid point.
+    corners   : Vec<Mutex<HashMap<[u32; 2], Vec<EvaluatedValues>>>>,
+    // Grid points per side, one per pixel at the deepest iteration.
+    grid_size : u32,
+    // Whether any equation reads `r` or `θ`, so cells across the seam are checked again, see `polar`.
+    polar     : bool
+}
+impl<'l> ImplicitEquations<'l> {
+    pub fn new(settings : &RenderSettings, nodes : &'l [Node], kinds : &[EquationKind], dependencies : &Dependencies) -> ImplicitEquations<'l> {
+        let mut quantities = vec![];
+        let mut polar      = false;
+        for i in 0..nodes.len() {
+            if (matches!(kinds[i], EquationKind::Implicit | EquationKind::Region) && ! dependencies.is_skipped(i)) {
+                if let Some((_, left, right)) = nodes[i].base.relation() {
+                    polar |= reads_polar(&nodes[i]);
+                    quantities.push((i, Quantity::Difference(left, right)));
+                }
+            }
+        }
+        // Failing equations are reported and dropped up front, so the
+        // cells can then be checked from many threads without tracking them.
+        let (equations, failures) = PointEquations::new(settings, nodes, dependencies, &COORDINATES, quantities);
+        for (index, error) in failures {
+            log!(ERROR, "Equation {} `{}` skipped: {}", index + 1, nodes[index].to_string(), error);
+        }
+        if (! equations.is_empty()) {
+            log!(DEBUG,
+                "Found {} implicit equation{}.",
+                equations.indices().len(),
+                if (equations.indices().len() == 1) {""} else {"s"}
+            );
+        }
+        return ImplicitEquations {
+            equations,
+            corners   : (0..CORNER_SHARDS).map(|_| Mutex::new(HashMap::new())).collect(),
+            grid_size : u32::pow(2, settings.split_depth),
+            polar
+        };
+    }
+
+    // Index in the equation list of each equation that may pass through the cell.
+    pub fn check(&self, settings : &RenderSettings, position : [f32; 2], size : f32, iteration : u32) -> Vec<usize> {
+        let indices = self.equations.indices();
+        // Never down to the last cells, or nothing would be tested.
+        if (iteration < MIN_DEPTH.min(settings.split_depth.saturating_sub(1))) {
+            return indices.clone();
+        }
+        let left   = (position[0] * self.grid_size as f32) as u32;
+        let bottom = (position[1] * self.grid_size as f32) as u32;
+        let step   = ((size * self.grid_size as f32) as u32).max(1);
+        let points = [[left, bottom], [left + step, bottom], [left, bottom + step], [left + step, bottom + step]];
+        if (self.polar) {
+            let corners = points.map(|point| self.graph_point(settings, point));
+            if let Some(sides) = polar::seam_coordinates(settings, &corners) {
+                // A sign change on either side of the seam, not between them.
+                let mut hits = vec![];
+                for side in sides {
+                    let values = side.iter().map(|coordinates| self.equations.evaluate(coordinates)).collect::<Vec<Vec<EvaluatedValues>>>();
+                    for i in 0..indices.len() {
+                        if (changes_sign(values.iter().map(|values| &values[i])) && ! hits.contains(&indices[i])) {
+                            hits.push(indices[i]);
+                        }
+                    }
+                }
+                hits.sort();
+                return hits;
+            }
+        }
+        let mut negative = vec![false; indices.len()];
+        let mut positive = vec![false; indices.len()];
+        for point in &points {
+            let shard = &self.corners[(point[0] as usize * 31 + point[1] as usize) % CORNER_SHARDS];
+            if (! shard.lock().unwrap().contains_key(point)) {
+                // Evaluated outside the lock. Another thread may get here
+                // first, but both produce the same values.
+                let values = self.evaluate_point(settings, *point);
+                shard.lock().unwrap().insert(*point, values);
+            }
+            let shard = shard.lock().unwrap();
+            for (i, values) in shard[point].iter().enumerate() {
+                for value in values.get_values() {
+                    negative[i] |= *value <= 0.0;
+                    positive[i] |= *value >= 0.0;
+                }
+            }
+        }
+        return (0..indices.len())
+            .filter(|i| negative[*i] && positive[*i])
+            .map(|i| indices[i])
+            .collect();
+    }
+
+    // Evaluate `left - right` for every equation at a grid point.
+    fn evaluate_point(&self, settings : &RenderSettings, point : [u32; 2]) -> Vec<EvaluatedValues> {
+        return self.equations.evaluate(&polar::coordinates(settings, self.graph_point(settings, point)));
+    }
+
+    fn graph_point(&self, settings : &RenderSettings, point : [u32; 2]) -> [f64; 2] {
+        return [
+            settings.frame[0] + (settings.frame[2] - settings.frame[0]) * (point[0] as f64 / self.grid_size as f64),
+            settings.frame[1] + (settings.frame[3] - settings.frame[1]) * (point[1] as f64 / self.grid_size as f64)
+        ];
+    }
+}
+
+// Whether the values at the corners of a cell include both signs.
+fn changes_sign<'l>(corners : impl Iterator<Item = &'l EvaluatedValues>) -> bool {
+    let mut negative = false;
+    let mut positive = false;
+    for values in corners {
+        for value in values.get_values() {
+            negative |= *value <= 0.0;
+            positive |= *value >= 0.0;
+        }
+    }
+    return negative && positive;
+}
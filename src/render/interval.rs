@@ -0,0 +1,199 @@
+use std::collections::HashMap;
+
+use loggerithm::{logger, log};
+use loggerithm::level::{DEBUG, ERROR};
+logger!(super);
+
+use crate::error::Error;
+use crate::parse::node::Node;
+use crate::parse::intervals::{Interval, EvaluatedIntervals};
+use crate::render::settings::RenderSettings;
+use super::{EquationKind, constants};
+use super::polar;
+use super::dependency::Dependencies;
+
+
+// Every equation in `x` and `y`, or `r` and `θ`, evaluated over whole cells with interval
+// arithmetic. A cell is only discarded when `left - right` provably can
+// not be zero anywhere inside it.
+pub struct IntervalEquations<'l> {
+    // Constants and the definitions that depend on no coordinate.
+    constants   : HashMap<String, EvaluatedIntervals>,
+    // Definitions that do, in the order they are evaluated.
+    definitions : Vec<&'l Node>,
+    // Index in the equation list, Left, Right
+    equations   : Vec<(usize, &'l Node, &'l Node)>
+}
+impl<'l> IntervalEquations<'l> {
+    pub fn new(settings : &RenderSettings, nodes : &'l [Node], kinds : &[EquationKind], dependencies : &Dependencies) -> IntervalEquations<'l> {
+        let mut equations = vec![];
+        for i in 0..nodes.len() {
+            if (matches!(kinds[i], EquationKind::Explicit | EquationKind::Implicit | EquationKind::Region) && ! dependencies.is_skipped(i)) {
+                if let Some((_, left, right)) = nodes[i].base.relation() {
+                    equations.push((i, left, right));
+                }
+            }
+        }
+        let mut variables = HashMap::new();
+        for (name, value) in constants() {
+            variables.insert(String::from(name), EvaluatedIntervals::from(vec![Interval::point(value)]));
+        }
+        for definition in dependencies.constants(nodes) {
+            // Definitions are checked up front, see `dependency`.
+            let _ = definition.evaluate_intervals(&String::from("y"), &mut variables);
+        }
+        let mut interval_equations = IntervalEquations {
+            constants   : variables,
+            definitions : dependencies.varying(nodes),
+            equations
+        };
+        interval_equations.remove_failing(settings);
+        log!(DEBUG,
+            "Found {} equation{} for interval evaluation.",
+            interval_equations.equations.len(),
+            if (interval_equations.equations.len() == 1) {""} else {"s"}
+        );
+        return interval_equations;
+    }
+
+    // Evaluate everything once over the whole frame, reporting and
+    // dropping equations that fail. Failures do not depend on the
+    // values, so the cells can then be checked without mutation.
+    fn remove_failing(&mut self, settings : &RenderSettings) {
+        let mut variables = cell_variables(&self.constants, settings, [0.0, 0.0], 1.0);
+        for definition in &self.definitions {
+            // Definitions are checked up front, see `dependency`.
+            let _ = definition.evaluate_intervals(&String::from("y"), &mut variables);
+        }
+        self.equations.retain(|(index, left, right)| {
+            return match (evaluate_difference(left, right, &mut variables)) {
+                Ok(_)      => true,
+                Err(error) => {
+                    log!(ERROR, "Equation {} `{} = {}` skipped: {}", index + 1, left.to_string(), right.to_string(), error);
+                    false
+                }
+            };
+        });
+    }
+
+    // Index in the equation list of each equation that may pass through the cell.
+    pub fn check(&self, settings : &RenderSettings, position : [f32; 2], size : f32) -> Vec<usize> {
+        let mut hits = vec![];
+        if (self.equations.is_empty()) {
+            return hits;
+        }
+        let mut variables = cell_variables(&self.constants, settings, position, size);
+        for definition in &self.definitions {
+            // Definitions are checked up front, see `dependency`.
+            let _ = definition.evaluate_intervals(&String::from("y"), &mut variables);
+        }
+        for (index, left, right) in &self.equations {
+            let hit = match (evaluate_difference(left, right, &mut variables)) {
+                Ok(difference) => difference.contains(0.0),
+                // Can not be ruled out.
+                Err(_)         => true
+            };
+            if (hit) {
+                hits.push(*index);
+            }
+        }
+        return hits;
+    }
+}
+
+fn evaluate_difference(left : &Node, right : &Node, variables : &mut HashMap<String, EvaluatedIntervals>) -> Result<EvaluatedIntervals, Error> {
+    let left  = left.evaluate_intervals(&String::from("y"), variables)?;
+    let right = right.evaluate_intervals(&String::from("y"), variables)?;
+    return Ok(left.subtraction(&right));
+}
+
+// Constants, and `x`, `y`, `r` and `θ` over the cell.
+fn cell_variables(constants : &HashMap<String, EvaluatedIntervals>, settings : &RenderSettings, position : [f32; 2], size : f32) -> HashMap<String, EvaluatedIntervals> {
+    let width  = settings.frame[2] - settings.frame[0];
+    let height = settings.frame[3] - settings.frame[1];
+    let x      = [
+        settings.frame[0] + width * (position[0] as f64),
+        settings.frame[0] + width * ((position[0] + size) as f64)
+    ];
+    let y      = [
+        settings.frame[1] + height * (position[1] as f64),
+        settings.frame[1] + height * ((position[1] + size) as f64)
+    ];
+    let angles = EvaluatedIntervals::from(polar::angle_intervals(settings, x, y));
+    let mut variables = constants.clone();
+    variables.insert(String::from("x"),     EvaluatedIntervals::from(vec![Interval::new(x[0], x[1])]));
+    variables.insert(String::from("y"),     EvaluatedIntervals::from(vec![Interval::new(y[0], y[1])]));
+    variables.insert(String::from("r"),     EvaluatedIntervals::from(vec![polar::radius_interval(x, y)]));
+    variables.insert(String::from("θ"),     angles.clone());
+    variables.insert(String::from("theta"), angles);
+    return variables;
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::import::{self, Format};
+    use crate::parse::values::EvaluatedValues;
+    use crate::render::classify;
+
+    // Whether the cell at `position`, `size` across, in the default frame is hit by `source`.
+    fn hit(source : &str, position : [f32; 2], size : f32) -> bool {
+        let settings     = RenderSettings::new();
+        let nodes        = import::parse(source, Format::Text).unwrap().nodes;
+        let kinds        = nodes.iter().map(classify).collect::<Vec<EquationKind>>();
+        let dependencies = Dependencies::new(&settings, &nodes, &kinds);
+        return ! IntervalEquations::new(&settings, &nodes, &kinds, &dependencies).check(&settings, position, size).is_empty();
+    }
+
+    #[test]
+    fn cells_no_curve_passes_through_are_discarded() {
+        // `x` and `y` in `[-0.5, 0.5]`, inside the circle and between the branches of `1 / x`.
+        assert!(! hit("x^2 + y^2 = 2.5^2", [0.45, 0.45], 0.1));
+        assert!(! hit("y = 1 / x",         [0.45, 0.45], 0.1));
+        // `x` in `[1.5, 1.7]`, across the pole of `tan`, and `y` in `[4.5, 4.7]`.
+        assert!(! hit("y = tan(x)",        [0.65, 0.95], 0.02));
+        assert!(! hit("y = sqrt(x)",       [0.2, 0.6], 0.1));
+    }
+
+    #[test]
+    fn cells_a_curve_passes_through_are_kept() {
+        assert!(hit("x^2 + y^2 = 2.5^2", [0.7, 0.45], 0.1));
+        assert!(hit("y = 1 / x",         [0.5, 0.5], 0.5));
+        // `x` in `[1, 1.5]` and `y` in `[2, 2.5]`.
+        assert!(hit("y = tan(x)",        [0.6, 0.7], 0.05));
+        assert!(hit("x = 0",             [0.45, 0.0], 0.1));
+    }
+
+    // `left - right` at a point in the default frame, given as a fraction of it.
+    fn difference(left : &Node, right : &Node, point : [f32; 2]) -> f64 {
+        let mut variables = HashMap::new();
+        variables.insert(String::from("x"), EvaluatedValues::from(vec![-5.0 + 10.0 * point[0] as f64]));
+        variables.insert(String::from("y"), EvaluatedValues::from(vec![-5.0 + 10.0 * point[1] as f64]));
+        let left = left.evaluate("y", &mut variables).unwrap().get_values()[0];
+        return left - right.evaluate("y", &mut variables).unwrap().get_values()[0];
+    }
+
+    // Any cell where `left - right` takes both signs at sampled points has a
+    // zero inside, so must be hit.
+    #[test]
+    fn sign_changes_are_never_discarded() {
+        let size = 1.0 / 16.0;
+        for source in ["x^2 + y^2 = 2.5^2", "y = sin(3x)", "x y = 1", "y^2 = x^3 - x"] {
+            let nodes            = import::parse(source, Format::Text).unwrap().nodes;
+            let (_, left, right) = nodes[0].base.relation().unwrap();
+            for column in 0..16 {
+                for row in 0..16 {
+                    let position = [column as f32 * size, row as f32 * size];
+                    let signs    = (0..25)
+                        .map(|i| difference(left, right, [position[0] + size * (i % 5) as f32 / 4.0, position[1] + size * (i / 5) as f32 / 4.0]).signum())
+                        .collect::<Vec<f64>>();
+                    if (signs.contains(&1.0) && signs.contains(&-1.0)) {
+                        assert!(hit(source, position, size), "`{}` at {:?}", source, position);
+                    }
+                }
+            }
+        }
+    }
+}
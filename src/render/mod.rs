@@ -1,24 +1,259 @@
 use std::collections::HashMap;
-use std::process;
+use std::fs;
+use std::path::Path;
 
-use image::{ImageBuffer, GrayImage};
+use image::{ImageBuffer, RgbaImage};
+use rayon::prelude::*;
 use loggerithm::{logger, log};
-use loggerithm::level::{TRACE, DEBUG, FATAL};
+use loggerithm::level::DEBUG;
 logger!(super);
 
 mod node;
+mod explicit;
+mod implicit;
+mod interval;
+mod region;
+mod dependency;
+mod variables;
+mod parametric;
+mod polar;
+pub mod path;
+pub mod hatch;
+pub mod gcode;
+pub mod svg;
 pub mod settings;
 use node::RenderNode;
-use settings::RenderSettings;
+use explicit::ExplicitEquations;
+use implicit::ImplicitEquations;
+use interval::IntervalEquations;
+use region::{RegionEquations, FILL_OPACITY};
+use dependency::Dependencies;
+use parametric::ParametricCurves;
+use settings::{RenderSettings, Evaluation};
 
+use crate::error::Error;
 use crate::helper;
-use crate::parse::node::Node;
-use crate::parse::values::EvaluatedValues;
+use crate::parse::node::{Node, NodeBase};
 use crate::parse::var;
+use crate::parse::function;
 
 
-pub fn render(nodes : Vec<Node>, settings : RenderSettings) {
-    let resolution = get_resolution(&settings);
+// Render to an image buffer.
+pub fn render_image(nodes : &[Node], settings : &RenderSettings) -> Result<RgbaImage, Error> {
+    return render_with(nodes, settings, |rendered| {
+        // Parametric curves, and borders of strict inequalities drawn dashed,
+        // are drawn from their paths instead of from the tree.
+        let mut traced = HashMap::new();
+        for index in rendered.drawable() {
+            let paths = if (matches!(rendered.kinds[index], EquationKind::Parametric | EquationKind::Polar)) {
+                rendered.parametric.paths(index)
+            } else if (rendered.regions.is_strict(index)) {
+                path::dash(&trace_paths(&rendered.render_node_tree, settings, &[index]), settings)
+            } else {
+                continue;
+            };
+            for pixel in rasterise(&paths, settings, &rendered.resolution) {
+                traced.entry(pixel).or_insert_with(Vec::new).push(index);
+            }
+        }
+        return Ok(write_buffer(&rendered.render_node_tree, &rendered.regions, &traced, settings, &rendered.resolution));
+    });
+}
+
+// Render to toolpaths in graph space, see `path`. Fill lines come first,
+// see `hatch`, then the curves. Borders of strict inequalities are dashed.
+pub fn render_paths(nodes : &[Node], settings : &RenderSettings) -> Result<Vec<Vec<[f64; 2]>>, Error> {
+    let mut toolpaths = render_toolpaths(nodes, settings)?;
+    toolpaths.hatching.append(&mut toolpaths.outlines);
+    return Ok(toolpaths.hatching);
+}
+
+// Render to an SVG document with a layer for each equation, see `svg`.
+pub fn render_svg(nodes : &[Node], settings : &RenderSettings) -> Result<String, Error> {
+    let layers = render_with(nodes, settings, |rendered| {
+        return rendered.drawable().into_iter()
+            .map(|i| {
+                let paths = rendered.paths(settings, i);
+                return Ok(svg::Layer {
+                    index    : i,
+                    label    : nodes[i].to_string(),
+                    colour   : settings.colour(i),
+                    hatching : rendered.fill(settings, i, &paths)?,
+                    paths    : paths,
+                    dashed   : rendered.regions.is_strict(i)
+                });
+            })
+            .collect::<Result<Vec<svg::Layer>, Error>>();
+    })?;
+    return svg::write(&layers, settings);
+}
+
+// Render to a G-code program, see `gcode`. Fill lines keep their order, so
+// bidirectional hatching zigzags, and the curves are ordered for travel.
+pub fn render_gcode(nodes : &[Node], settings : &RenderSettings) -> Result<String, Error> {
+    let mut toolpaths = render_toolpaths(nodes, settings)?;
+    toolpaths.hatching.append(&mut path::order_for_travel(toolpaths.outlines));
+    return Ok(gcode::write(&toolpaths.hatching, settings));
+}
+
+// Render to the file named by `settings.target`, in the format picked by its extension.
+pub fn render_file(nodes : &[Node], settings : &RenderSettings) -> Result<(), Error> {
+    let target = settings.target.replace("\\", "\\\\").replace("`", "\\`");
+    return match (Output::from_path(Path::new(&settings.target))) {
+        Output::Image => {
+            let buffer = render_image(nodes, settings)?;
+            log!(DEBUG, "Writing image buffer to file `{}`.", target);
+            match (buffer.save(&settings.target)) {
+                Ok(_)      => Ok(()),
+                Err(error) => Err(Error::ImageWrite {path: settings.target.clone(), error})
+            }
+        },
+        Output::Svg => {
+            let document = render_svg(nodes, settings)?;
+            log!(DEBUG, "Writing SVG to file `{}`.", target);
+            match (fs::write(&settings.target, document)) {
+                Ok(_)      => Ok(()),
+                Err(error) => Err(Error::Write {path: settings.target.clone(), error})
+            }
+        },
+        Output::Gcode => {
+            let program = render_gcode(nodes, settings)?;
+            log!(DEBUG, "Writing G-code to file `{}`.", target);
+            match (fs::write(&settings.target, program)) {
+                Ok(_)      => Ok(()),
+                Err(error) => Err(Error::Write {path: settings.target.clone(), error})
+            }
+        }
+    };
+}
+
+// Check that every equation can be drawn, without rendering. Equations
+// that can not, such as definitions reading themselves or names never
+// defined, are reported, see `dependency`, and fail the check.
+pub fn check(nodes : &[Node], settings : &RenderSettings) -> Result<(), Error> {
+    let kinds        = nodes.iter().map(classify).collect::<Vec<EquationKind>>();
+    let dependencies = Dependencies::new(settings, nodes, &kinds);
+    if (! dependencies.reports().is_empty()) {
+        return Err(Error::SkippedEquations(dependencies.reports().len()));
+    }
+    return Ok(());
+}
+
+// Kind of file written by `render_file`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Output {
+    // Any format `image` can write, picked from the extension.
+    Image,
+    // SVG, see `svg`.
+    Svg,
+    // G-code, see `gcode`.
+    Gcode
+}
+impl Output {
+    // `.svg` files are SVG, `.gcode`, `.gc`, `.nc` and `.ngc` files are G-code, anything else is an image.
+    pub fn from_path(path : &Path) -> Output {
+        return match (path.extension().and_then(|extension| extension.to_str()).map(|extension| extension.to_lowercase()).as_deref()) {
+            Some("svg")                                           => Output::Svg,
+            Some("gcode") | Some("gc") | Some("nc") | Some("ngc") => Output::Gcode,
+            _                                                     => Output::Image
+        };
+    }
+}
+
+// Equations classified and drawn into the render tree, which every output is built from.
+struct Rendered<'l> {
+    resolution       : [u32; 2],
+    kinds            : Vec<EquationKind>,
+    dependencies     : Dependencies,
+    render_node_tree : RenderNode,
+    regions          : RegionEquations<'l>,
+    parametric       : ParametricCurves
+}
+impl Rendered<'_> {
+    // Index in the equation list of each equation that draws something, and was not skipped.
+    fn drawable(&self) -> Vec<usize> {
+        return (0..self.kinds.len())
+            .filter(|i| matches!(self.kinds[*i], EquationKind::Explicit | EquationKind::Implicit | EquationKind::Region | EquationKind::Parametric | EquationKind::Polar))
+            .filter(|i| ! self.dependencies.is_skipped(*i))
+            .collect();
+    }
+    // Paths of an equation: traced from the tree, or from `t` or `θ` for parametric and polar curves.
+    fn paths(&self, settings : &RenderSettings, index : usize) -> Vec<Vec<[f64; 2]>> {
+        if (matches!(self.kinds[index], EquationKind::Parametric | EquationKind::Polar)) {
+            return self.parametric.paths(index);
+        }
+        return trace_paths(&self.render_node_tree, settings, &[index]);
+    }
+    // Fill lines of an equation: across its region, and inside its closed
+    // curves when `settings.hatch.closed` is set.
+    fn fill(&self, settings : &RenderSettings, index : usize, paths : &[Vec<[f64; 2]>]) -> Result<Vec<Vec<[f64; 2]>>, Error> {
+        let mut lines = self.regions.hatch(settings, index)?;
+        if (settings.hatch.closed && matches!(self.kinds[index], EquationKind::Explicit | EquationKind::Implicit | EquationKind::Parametric | EquationKind::Polar)) {
+            lines.append(&mut hatch::fill_polygons(paths, settings)?);
+        }
+        return Ok(lines);
+    }
+}
+
+// Classify the equations and refine the render tree on a pool of
+// `settings.threads` threads, then build an output from them on the same pool.
+fn render_with<T, F>(nodes : &[Node], settings : &RenderSettings, output : F) -> Result<T, Error>
+    where T : Send, F : FnOnce(&Rendered) -> Result<T, Error> + Send
+{
+    settings.check()?;
+    let pool = match (rayon::ThreadPoolBuilder::new().num_threads(settings.threads).build()) {
+        Ok(pool)   => pool,
+        Err(error) => return Err(Error::ThreadPool(error))
+    };
+    log!(DEBUG,
+        "Rendering on {} thread{}.",
+        pool.current_num_threads(),
+        if (pool.current_num_threads() == 1) {""} else {"s"}
+    );
+    return pool.install(|| {
+        let resolution       = get_resolution(settings);
+        let kinds            = nodes.iter().map(classify).collect::<Vec<EquationKind>>();
+        let dependencies     = Dependencies::new(settings, nodes, &kinds);
+        let render_node_tree = generate_tree(nodes, &kinds, &dependencies, settings, &resolution)?;
+        let regions          = RegionEquations::new(settings, nodes, &kinds, &dependencies);
+        let parametric       = ParametricCurves::new(settings, nodes, &kinds, &dependencies);
+        return output(&Rendered {resolution, kinds, dependencies, render_node_tree, regions, parametric});
+    });
+}
+
+// Paths of every equation, in graph space.
+struct Toolpaths {
+    // Fill lines, in the order they are burnt.
+    hatching : Vec<Vec<[f64; 2]>>,
+    // Curves, with dashed borders split up.
+    outlines : Vec<Vec<[f64; 2]>>
+}
+
+fn render_toolpaths(nodes : &[Node], settings : &RenderSettings) -> Result<Toolpaths, Error> {
+    return render_with(nodes, settings, |rendered| {
+        let mut hatching = vec![];
+        let mut outlines = vec![];
+        for index in rendered.drawable() {
+            let mut paths = rendered.paths(settings, index);
+            hatching.append(&mut rendered.fill(settings, index, &paths)?);
+            if (rendered.regions.is_strict(index)) {
+                paths = path::dash(&paths, settings);
+            }
+            outlines.append(&mut paths);
+        }
+        return Ok(Toolpaths {hatching, outlines});
+    });
+}
+
+// Trace the cells hit by any of the equations into paths.
+fn trace_paths(render_node_tree : &RenderNode, settings : &RenderSettings, equations : &[usize]) -> Vec<Vec<[f64; 2]>> {
+    let mut cells = vec![];
+    render_node_tree.hit_cells(equations, &mut cells);
+    return path::trace(&cells, settings);
+}
+
+// Build the render node tree.
+fn generate_tree(nodes : &[Node], kinds : &[EquationKind], dependencies : &Dependencies, settings : &RenderSettings, resolution : &[u32; 2]) -> Result<RenderNode, Error> {
     log!(DEBUG,
         "Setting resolution to {},{} for {} iteration{}.",
         helper::commaify_i64(resolution[0].into()), helper::commaify_i64(resolution[1].into()),
@@ -26,102 +261,268 @@ pub fn render(nodes : Vec<Node>, settings : RenderSettings) {
         if (settings.split_depth == 1) {""} else {"s"}
     );
 
-    let column_values = generate_column_values(&settings, &resolution, &nodes);
-
-    let render_node_tree = generate_render_node_tree(&settings, &column_values);
+    return Ok(match (settings.evaluation) {
+        Evaluation::Points | Evaluation::Complex => {
+            let explicit_equations = ExplicitEquations::new(settings, resolution, nodes, kinds, dependencies);
+            let implicit_equations = ImplicitEquations::new(settings, nodes, kinds, dependencies);
+            generate_render_node_tree(settings, &explicit_equations, &implicit_equations)?
+        },
+        Evaluation::Intervals => {
+            let interval_equations = IntervalEquations::new(settings, nodes, kinds, dependencies);
+            refine_render_node_tree(settings, &interval_equations)
+        }
+    });
+}
 
-    // Write pixels.
-    let mut buffer : GrayImage = ImageBuffer::new(resolution[0], resolution[1]);
+// Write pixels. Region shading goes under the curves, and where equations
+// overlap, later ones are drawn over earlier ones. `traced` holds the
+// equations drawn at each pixel from their paths.
+fn write_buffer(render_node_tree : &RenderNode, regions : &RegionEquations, traced : &HashMap<[u32; 2], Vec<usize>>, settings : &RenderSettings, resolution : &[u32; 2]) -> RgbaImage {
+    let mut buffer : RgbaImage = ImageBuffer::new(resolution[0], resolution[1]);
     log!(DEBUG,
         "Writing {} pixel{} to image buffer.",
-        helper::commaify_i64((resolution[0] * resolution[1]).into()),
-        if (resolution[0] * resolution[1] == 1) {""} else {"s"}
+        helper::commaify_i64(resolution[0] as i64 * resolution[1] as i64),
+        if (resolution[0] as i64 * resolution[1] as i64 == 1) {""} else {"s"}
     );
-    for (pixel_x, pixel_y_reversed, pixel) in buffer.enumerate_pixels_mut() {
-        let pixel_y = resolution[1] - (pixel_y_reversed + 1);
-        let colour  = render_node_tree.get_pixel([
-            (pixel_x as f32) / (resolution[0] as f32),
-            (pixel_y as f32) / (resolution[1] as f32)
-        ]);
-        *pixel = image::Luma(colour);
-    }
-
-    // Write file.
-    log!(DEBUG, "Writing image buffer to file `{}`.", settings.target.replace("\\", "\\\\").replace("`", "\\`"));
-    match (buffer.save(settings.target)) {
-        Ok(_)  => (),
-        Err(_) => {
-            log!(FATAL, "Image write failed.");
-            process::exit(1);
+    buffer.par_chunks_mut(resolution[0] as usize * 4).enumerate().for_each(|(pixel_y_reversed, row)| {
+        let pixel_y = resolution[1] - (pixel_y_reversed as u32 + 1);
+        for (pixel_x, pixel) in row.chunks_mut(4).enumerate() {
+            let mut colour = settings.background;
+            let centre     = [
+                settings.frame[0] + (settings.frame[2] - settings.frame[0]) * (pixel_x as f64 + 0.5) / (resolution[0] as f64),
+                settings.frame[1] + (settings.frame[3] - settings.frame[1]) * (pixel_y as f64 + 0.5) / (resolution[1] as f64)
+            ];
+            for index in regions.filled(settings, centre) {
+                let [red, green, blue, alpha] = settings.colour(index);
+                colour = blend(colour, [red, green, blue, (alpha as f64 * FILL_OPACITY).round() as u8]);
+            }
+            let mut hits = render_node_tree.get_hits([
+                (pixel_x as f32) / (resolution[0] as f32),
+                (pixel_y as f32) / (resolution[1] as f32)
+            ]).iter().cloned().filter(|index| ! regions.is_strict(*index)).collect::<Vec<usize>>();
+            if let Some(paths) = traced.get(&[pixel_x as u32, pixel_y]) {
+                hits.extend(paths);
+                hits.sort();
+                hits.dedup();
+            }
+            for index in hits {
+                colour = blend(colour, settings.colour(index));
+            }
+            pixel.copy_from_slice(&colour);
+        }
+    });
+    return buffer;
+}
+
+// Pixels the paths pass through, counted up from the bottom left.
+fn rasterise(paths : &[Vec<[f64; 2]>], settings : &RenderSettings, resolution : &[u32; 2]) -> Vec<[u32; 2]> {
+    let to_pixels = |point : [f64; 2]| [
+        (point[0] - settings.frame[0]) / (settings.frame[2] - settings.frame[0]) * resolution[0] as f64,
+        (point[1] - settings.frame[1]) / (settings.frame[3] - settings.frame[1]) * resolution[1] as f64
+    ];
+    let mut pixels = vec![];
+    for path in paths {
+        for i in 0..path.len() {
+            let start = to_pixels(path[i]);
+            let end   = to_pixels(path[(i + 1).min(path.len() - 1)]);
+            // Half pixel steps, so no pixel along the way is skipped.
+            let steps = ((end[0] - start[0]).abs().max((end[1] - start[1]).abs()) * 2.0).ceil().max(1.0) as usize;
+            for step in 0..=steps {
+                let t = step as f64 / steps as f64;
+                let x = start[0] + (end[0] - start[0]) * t;
+                let y = start[1] + (end[1] - start[1]) * t;
+                if (x >= 0.0 && y >= 0.0 && x < resolution[0] as f64 && y < resolution[1] as f64) {
+                    pixels.push([x as u32, y as u32]);
+                }
+            }
+        }
+    }
+    return pixels;
+}
+
+// Draw a colour over another, by its alpha : Red, Green, Blue, Alpha
+fn blend(under : [u8; 4], over : [u8; 4]) -> [u8; 4] {
+    let over_alpha  = over[3] as f64 / 255.0;
+    let under_alpha = under[3] as f64 / 255.0 * (1.0 - over_alpha);
+    let alpha       = over_alpha + under_alpha;
+    if (alpha == 0.0) {
+        return [0, 0, 0, 0];
+    }
+    let mut colour = [0, 0, 0, (alpha * 255.0).round() as u8];
+    for i in 0..3 {
+        colour[i] = ((over[i] as f64 * over_alpha + under[i] as f64 * under_alpha) / alpha).round() as u8;
+    }
+    return colour;
+}
+
+// How an equation is drawn.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EquationKind {
+    // `a = ...`, binds a variable for the other equations.
+    Definition,
+    // `y = f(x)`, evaluated across each column.
+    Explicit,
+    // Any other relation, drawn where `left - right` changes sign.
+    Implicit,
+    // An inequality in `x` or `y`, with the area where it holds shaded.
+    // Its border is drawn as `Implicit`.
+    Region,
+    // `f(a, b) = ...`, a function for the other equations to call. Calls
+    // are inlined on import, so nothing is drawn.
+    Function,
+    // `(x(t), y(t))`, traced over `settings.parametric_domain`, see `parametric`.
+    Parametric,
+    // `r = f(θ)`, traced over `settings.polar_domain`. Other relations in
+    // `r` and `θ` are `Implicit` or `Region`, see `polar`.
+    Polar,
+    // Not an equation, nothing is drawn.
+    Expression
+}
+
+pub fn classify(node : &Node) -> EquationKind {
+    return match (&node.base) {
+        NodeBase::Equals(left, right) => match (&left.base) {
+            NodeBase::Variable(name) if (name == "y") => {
+                if (right.contains_variable("y") || reads_polar(right)) {EquationKind::Implicit} else {EquationKind::Explicit}
+            },
+            NodeBase::Variable(name) if (name == "r") => {
+                if (reads_cartesian(right) || right.contains_variable("r")) {EquationKind::Implicit} else {EquationKind::Polar}
+            },
+            NodeBase::Variable(name) if (! COORDINATES.contains(&name.as_str())) => EquationKind::Definition,
+            NodeBase::Call(_, _) if (function::definition(node).is_some())      => EquationKind::Function,
+            _                                                                   => EquationKind::Implicit
+        },
+        NodeBase::Point(_, _) if (node.contains_variable("t")) => EquationKind::Parametric,
+        base => match (base.relation()) {
+            Some(_) if (reads_cartesian(node) || reads_polar(node)) => EquationKind::Region,
+            _                                                      => EquationKind::Expression
         }
     };
 }
 
-// If settings define resolution as 0 or less, return 2 ** iterations.
+fn reads_cartesian(node : &Node) -> bool {
+    return node.contains_variable("x") || node.contains_variable("y");
+}
+
+fn reads_polar(node : &Node) -> bool {
+    return node.contains_variable("r") || node.contains_variable("θ") || node.contains_variable("theta");
+}
+
+// If settings define resolution as 0, return 2 ** iterations.
 fn get_resolution(settings : &RenderSettings) -> [u32; 2] {
     let mut resolution_x = settings.resolution[0];
-    if (resolution_x <= 0) {
+    if (resolution_x == 0) {
         resolution_x = u32::pow(2, settings.split_depth);
     }
     let mut resolution_y = settings.resolution[1];
-    if (resolution_y <= 0) {
+    if (resolution_y == 0) {
         resolution_y = u32::pow(2, settings.split_depth);
     }
     return [resolution_x, resolution_y];
 }
 
-// Generate values for each column.
-fn generate_column_values(settings : &RenderSettings, resolution : &[u32; 2], nodes : &Vec<Node>) -> Vec<EvaluatedValues> {
-    log!(DEBUG,
-        "Generating values for {} column{}.",
-        helper::commaify_i64((resolution[0] + 1).into()),
-        if (resolution[0] + 1 == 1) {""} else {"s"}
-    );
-    let mut columns   = vec![];
-    let mut variables = HashMap::new();
-    for i in 0..resolution[0] + 1 {
-        variables.clear();
-        let x = EvaluatedValues::from(vec![
-            settings.frame[0] + (settings.frame[2] - settings.frame[0]) * ((i as f64) / (resolution[0] as f64))
-        ]);
-        let mut values = EvaluatedValues::new();
-        for node in nodes {
-            variables.insert(String::from("x"), EvaluatedValues::copy(&x));
-            insert_consts(&mut variables);
-            node.evaluate(&String::from("y"), &mut variables);
-            if (variables.contains_key(&String::from("y"))) {
-                values = values.add(&variables.remove(&String::from("y")).unwrap());
-            }
-        }
-        values = values.compress(&settings);
-        log!(TRACE,
-            "Value {} found for column {}.",
-            values,
-            helper::commaify_i64(i.into())
-        );
-        columns.push(values);
-    }
-    return columns;
-}
+// Names bound at every point in the frame, Cartesian then polar, see `polar::coordinates`.
+const COORDINATES : [&str; 5] = ["x", "y", "r", "θ", "theta"];
 
-// Add the constants to the variable set.
-fn insert_consts(variables : &mut HashMap<String, EvaluatedValues>) {
-    variables.insert(String::from("pi"  ), EvaluatedValues::from(vec![var::PI  ]));
-    variables.insert(String::from("π"   ), EvaluatedValues::from(vec![var::PI  ]));
-    variables.insert(String::from("tau" ), EvaluatedValues::from(vec![var::TAU ]));
-    variables.insert(String::from("𝜏"   ), EvaluatedValues::from(vec![var::TAU ]));
-    variables.insert(String::from("phi" ), EvaluatedValues::from(vec![var::PHI ]));
-    variables.insert(String::from("φ"   ), EvaluatedValues::from(vec![var::PHI ]));
-    variables.insert(String::from("e"   ), EvaluatedValues::from(vec![var::E   ]));
+// Name, Value of each constant.
+fn constants() -> [(&'static str, f64); 7] {
+    return [
+        ("pi"  , var::PI  ),
+        ("π"   , var::PI  ),
+        ("tau" , var::TAU ),
+        ("𝜏"   , var::TAU ),
+        ("phi" , var::PHI ),
+        ("φ"   , var::PHI ),
+        ("e"   , var::E   )
+    ];
 }
 
 // Generate grid and split.
-fn generate_render_node_tree(settings : &RenderSettings, column_values : &Vec<EvaluatedValues>) -> RenderNode {
+fn generate_render_node_tree(settings : &RenderSettings, explicit_equations : &ExplicitEquations, implicit_equations : &ImplicitEquations) -> Result<RenderNode, Error> {
     log!(DEBUG, "Generating render node tree.");
     let mut render_node_tree = RenderNode::new(settings.split_depth);
-    for _i in 0..settings.split_depth + 1 {
-        render_node_tree.check(settings, column_values);
-        render_node_tree.split();
+    for i in 0..settings.split_depth + 1 {
+        render_node_tree.check(settings, explicit_equations, implicit_equations)?;
+        // The deepest cells are one pixel, and are not split further.
+        if (i < settings.split_depth) {
+            render_node_tree.split();
+        }
     }
+    return Ok(render_node_tree);
+}
+
+// Generate the tree with interval checks, see `RenderNode::refine`.
+fn refine_render_node_tree(settings : &RenderSettings, interval_equations : &IntervalEquations) -> RenderNode {
+    log!(DEBUG, "Refining render node tree with interval arithmetic.");
+    let mut render_node_tree = RenderNode::new(settings.split_depth);
+    render_node_tree.refine(settings, interval_equations);
     return render_node_tree;
 }
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::import::{self, Format};
+    use crate::render::settings::MAX_SPLIT_DEPTH;
+
+    fn nodes(source : &str) -> Vec<Node> {
+        return import::parse(source, Format::Text).unwrap().nodes;
+    }
+
+    #[test]
+    fn split_depth_is_capped() {
+        let settings = RenderSettings::new().set_split_depth(MAX_SPLIT_DEPTH + 1);
+        assert!(matches!(render_image(&nodes("y = x"), &settings), Err(Error::SplitDepth(_))));
+        assert!(matches!(render_gcode(&nodes("y = x"), &settings.set_split_depth(32)), Err(Error::SplitDepth(32))));
+    }
+
+    #[test]
+    fn frames_must_not_be_empty_or_inverted() {
+        for frame in [[5.0, -5.0, -5.0, 5.0], [-5.0, 5.0, 5.0, 5.0], [-5.0, -5.0, f64::NAN, 5.0], [f64::NEG_INFINITY, -5.0, 5.0, 5.0]] {
+            let settings = RenderSettings::new().set_frame(frame);
+            assert!(matches!(render_svg(&nodes("y = x"), &settings), Err(Error::Frame(_))), "{:?}", frame);
+        }
+        assert!(render_image(&nodes("y = x"), &RenderSettings::new().set_frame([-1.0, 0.0, 1.0, 2.0]).set_split_depth(4)).is_ok());
+    }
+
+    // Pixels not left as the background.
+    fn drawn(image : &RgbaImage) -> usize {
+        return image.pixels().filter(|pixel| pixel.0 != [255, 255, 255, 255]).count();
+    }
+
+    #[test]
+    fn shallow_implicit_renders_are_not_filled() {
+        for split_depth in 3..=6 {
+            let settings = RenderSettings::new().set_split_depth(split_depth);
+            let image    = render_image(&nodes("x^2 + y^2 = 2.5^2"), &settings).unwrap();
+            let pixels   = (image.width() * image.height()) as usize;
+            assert!(drawn(&image) > 0,          "depth {}", split_depth);
+            assert!(drawn(&image) < pixels / 2, "depth {} drew {} of {} pixels", split_depth, drawn(&image), pixels);
+            // The centre is inside the circle.
+            assert_eq!(image.get_pixel(image.width() / 2, image.height() / 2).0, [255, 255, 255, 255]);
+        }
+    }
+
+    #[test]
+    fn steep_explicit_curves_keep_a_pixel_in_every_row() {
+        let image = render_image(&nodes("y = 100x"), &RenderSettings::new().set_split_depth(6)).unwrap();
+        for y in 0..image.height() {
+            assert!((0..image.width()).any(|x| image.get_pixel(x, y).0 != [255, 255, 255, 255]), "row {}", y);
+        }
+        // Only the columns around `x = 0`.
+        assert!(drawn(&image) <= 2 * image.height() as usize);
+    }
+
+    #[test]
+    fn thread_count_does_not_change_the_output() {
+        let nodes = nodes("a = 2\ny = sin(a x)\nx^2 + y^2 = 2.5^2\ny < x - 1\n(2cos(t), sin(t))");
+        for evaluation in [Evaluation::Points, Evaluation::Intervals, Evaluation::Complex] {
+            let single = RenderSettings::new().set_split_depth(6).set_evaluation(evaluation).set_threads(1);
+            let many   = single.clone().set_threads(4);
+            assert!(render_image(&nodes, &single).unwrap() == render_image(&nodes, &many).unwrap(), "{:?}", evaluation);
+            assert_eq!(render_svg(&nodes, &single).unwrap(),   render_svg(&nodes, &many).unwrap(),   "{:?}", evaluation);
+            assert_eq!(render_gcode(&nodes, &single).unwrap(), render_gcode(&nodes, &many).unwrap(), "{:?}", evaluation);
+        }
+    }
+}
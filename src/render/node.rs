@@ -1,12 +1,13 @@
-use std::process;
-
 use loggerithm::{logger, log};
-use loggerithm::level::{TRACE, FATAL};
+use loggerithm::level::TRACE;
 logger!(super);
 
+use crate::error::Error;
 use crate::helper;
-use crate::parse::values::EvaluatedValues;
 use crate::render::settings::RenderSettings;
+use super::explicit::ExplicitEquations;
+use super::implicit::ImplicitEquations;
+use super::interval::IntervalEquations;
 
 
 #[derive(Debug)]
@@ -14,7 +15,9 @@ pub struct RenderNode {
     split      : RenderSplitOption,
     iteration  : u32,
     position   : [f32; 2],
-    iterations : u32
+    iterations : u32,
+    // Index in the equation list of each equation passing through the cell.
+    hits       : Vec<usize>
 }
 impl RenderNode {
     pub fn new(iterations : u32) -> RenderNode {
@@ -22,7 +25,8 @@ impl RenderNode {
             split      : RenderSplitOption::Wait,
             iteration  : 0,
             position   : [0.0, 0.0],
-            iterations : iterations
+            iterations : iterations,
+            hits       : vec![]
         };
     }
     fn new_split(&self, offset_mult : [f32; 2]) -> RenderNode {
@@ -58,19 +62,16 @@ impl RenderNode {
             }
         };
     }
-    pub fn check(&mut self, settings : &RenderSettings, column_values : &Vec<EvaluatedValues>) {
+    pub fn check(&mut self, settings : &RenderSettings, explicit_equations : &ExplicitEquations, implicit_equations : &ImplicitEquations) -> Result<(), Error> {
         match (self.split) {
             RenderSplitOption::Wait => {
-                let columns_length = column_values.len() as f32 - 1.0;
-                let left_index     = (self.position[0] * columns_length) as usize;
-                let right_index    = ((self.position[0] + get_pixel_size(self.iteration)) * columns_length) as usize;
-                // Get Y coord at top and bottom of split.
-                let bottom_value = settings.frame[1] + (settings.frame[3] - settings.frame[1]) * (self.position[1] as f64);
-                let top_value    = settings.frame[1] + (settings.frame[3] - settings.frame[1]) * ((self.position[1] + get_pixel_size(self.iteration)) as f64);
-                // Collect all values on the left and right edge.
-                let passed =   self.check_side(column_values[left_index  ].get_values(), bottom_value, top_value)
-                            || self.check_side(column_values[right_index ].get_values(), bottom_value, top_value);
-                if (! passed) {
+                let size     = get_pixel_size(self.iteration);
+                let mut hits = explicit_equations.check(settings, self.position, size);
+                hits.append(&mut implicit_equations.check(settings, self.position, size, self.iteration));
+                hits.sort();
+                hits.dedup();
+                self.hits = hits;
+                if (self.hits.is_empty()) {
                     log!(TRACE,
                         "Check on iteration {}, position {},{} did not pass.",
                         helper::commaify_i64(self.iteration.into()),
@@ -81,33 +82,77 @@ impl RenderNode {
             },
             RenderSplitOption::Stop => (),
             RenderSplitOption::Continue(ref mut split) => {
-                split.bl.check(settings, column_values);
-                split.tl.check(settings, column_values);
-                split.br.check(settings, column_values);
-                split.tr.check(settings, column_values);
+                // Subtrees are independent, so they are checked in parallel.
+                let ((bl, tl), (br, tr)) = rayon::join(
+                    || rayon::join(
+                        || split.bl.check(settings, explicit_equations, implicit_equations),
+                        || split.tl.check(settings, explicit_equations, implicit_equations)
+                    ),
+                    || rayon::join(
+                        || split.br.check(settings, explicit_equations, implicit_equations),
+                        || split.tr.check(settings, explicit_equations, implicit_equations)
+                    )
+                );
+                bl?; tl?; br?; tr?;
             }
         };
+        return Ok(());
     }
-    fn _check_top(&self, _settings : &RenderSettings) {
+    // Check and split down to the deepest iteration in one pass, discarding
+    // cells that provably contain no curve.
+    pub fn refine(&mut self, settings : &RenderSettings, interval_equations : &IntervalEquations) {
         if (! matches!(self.split, RenderSplitOption::Wait)) {
-            log!(FATAL, "`check_top` called when split option is not `RenderSplitOption::Wait`.");
-            process::exit(1);
-        };
-        panic!("Unimplemented.");
+            return;
+        }
+        self.hits = interval_equations.check(settings, self.position, get_pixel_size(self.iteration));
+        if (self.hits.is_empty()) {
+            log!(TRACE,
+                "Check on iteration {}, position {},{} did not pass.",
+                helper::commaify_i64(self.iteration.into()),
+                self.position[0], self.position[1]
+            );
+            self.split = RenderSplitOption::Stop;
+            return;
+        }
+        if (self.iteration >= self.iterations) {
+            return;
+        }
+        self.split();
+        if let RenderSplitOption::Continue(ref mut split) = self.split {
+            rayon::join(
+                || rayon::join(
+                    || split.bl.refine(settings, interval_equations),
+                    || split.tl.refine(settings, interval_equations)
+                ),
+                || rayon::join(
+                    || split.br.refine(settings, interval_equations),
+                    || split.tr.refine(settings, interval_equations)
+                )
+            );
+        }
     }
-    fn check_side(&self, side_values : &Vec<f64>, bottom_value : f64, top_value : f64) -> bool {
-        if (! matches!(self.split, RenderSplitOption::Wait)) {
-            log!(FATAL, "`check_side` called when split option is not RenderSplitOption::Wait.");
-            process::exit(1);
-        };
-        for value in side_values {
-            if (value >= &bottom_value && value < &top_value) {
-                return true;
+    // Grid positions of the cells any of the equations pass through, one
+    // grid cell per pixel at the deepest iteration.
+    pub fn hit_cells(&self, equations : &[usize], cells : &mut Vec<[u32; 2]>) {
+        match (&self.split) {
+            RenderSplitOption::Continue(ref split) => {
+                split.bl.hit_cells(equations, cells);
+                split.tl.hit_cells(equations, cells);
+                split.br.hit_cells(equations, cells);
+                split.tr.hit_cells(equations, cells);
+            },
+            RenderSplitOption::Stop => (),
+            RenderSplitOption::Wait => {
+                if (! self.hits.iter().any(|index| equations.contains(index))) {
+                    return;
+                }
+                let grid_size = u32::pow(2, self.iterations) as f32;
+                cells.push([(self.position[0] * grid_size) as u32, (self.position[1] * grid_size) as u32]);
             }
-        }
-        return false;
+        };
     }
-    pub fn get_pixel(&self, position : [f32; 2]) -> [u8; 1] {
+    // Equations passing through the cell at a position, in list order.
+    pub fn get_hits(&self, position : [f32; 2]) -> &[usize] {
         return match (&self.split) {
             RenderSplitOption::Continue(ref split) => {
                 let center_pos = [
@@ -117,14 +162,10 @@ impl RenderNode {
                 [&split.bl, &split.tl, &split.br, &split.tr][
                     if (position[0] < center_pos[0]) {0} else {1} +
                     if (position[1] < center_pos[1]) {0} else {2}
-                ].get_pixel(position)
-            },
-            RenderSplitOption::Stop => {
-                [((self.iteration as f64 / self.iterations as f64) * 255.0) as u8]
+                ].get_hits(position)
             },
-            RenderSplitOption::Wait => {
-                [255]
-            }
+            RenderSplitOption::Stop => &[],
+            RenderSplitOption::Wait => &self.hits
         };
     }
 }
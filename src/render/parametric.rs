@@ -0,0 +1,291 @@
+// Parametric curves, `(x(t), y(t))`.
+//
+// Each curve is sampled evenly over `settings.parametric_domain`, and every
+// step between two samples is halved until the curve at its middle lies
+// within a fraction of a pixel of the straight line between its ends. Steps
+// that jump across the frame, like those of `(t, tan(t))` around its
+// asymptotes, never settle, and the path is broken there once the step is
+// small enough. Steps lying off one side of the frame are not refined.
+//
+// Where `x` or `y` has several values, as with `(t, ±sqrt(t))`, each pair
+// of values is traced as its own branch.
+//
+// Polar curves, `r = f(θ)`, are traced the same way over
+// `settings.polar_domain`, as `(f(θ) cos(θ), f(θ) sin(θ))`.
+
+use rayon::prelude::*;
+use loggerithm::{logger, log};
+use loggerithm::level::{DEBUG, ERROR};
+logger!(super);
+
+use crate::parse::node::{Node, NodeBase};
+use crate::render::settings::RenderSettings;
+use super::EquationKind;
+use super::dependency::Dependencies;
+use super::path;
+use super::variables::{PointEquations, Quantity};
+
+
+// Even steps taken across the domain before any are halved.
+const SAMPLES   : usize = 256;
+// Times a step is halved before the path is broken.
+const MAX_DEPTH : u32   = 12;
+// Furthest the middle of a step may lie from the line between its ends, in pixels.
+const TOLERANCE : f64   = 0.25;
+// Longest a step may be, in pixels, so loops shorter than a step are not skipped.
+const MAX_CHORD : f64   = 8.0;
+
+
+// Parametric and polar curves.
+pub struct ParametricCurves {
+    // Index in the equation list, Paths in graph space of each curve.
+    curves : Vec<(usize, Vec<Vec<[f64; 2]>>)>
+}
+impl ParametricCurves {
+    pub fn new(settings : &RenderSettings, nodes : &[Node], kinds : &[EquationKind], dependencies : &Dependencies) -> ParametricCurves {
+        let mut equations = vec![];
+        for i in 0..nodes.len() {
+            if (dependencies.is_skipped(i)) {
+                continue;
+            }
+            let (shape, (curve, failures)) = match (kinds[i], &nodes[i].base) {
+                (EquationKind::Parametric, NodeBase::Point(x, y)) => {
+                    (Shape::Parametric, PointEquations::new(settings, nodes, dependencies, &["t"], vec![(i, Quantity::Value(x)), (i, Quantity::Value(y))]))
+                },
+                (EquationKind::Polar, NodeBase::Equals(_, radius)) => {
+                    (Shape::Polar, PointEquations::new(settings, nodes, dependencies, &["θ", "theta"], vec![(i, Quantity::Value(radius))]))
+                },
+                _ => continue
+            };
+            match (failures.into_iter().next()) {
+                Some((_, error)) => log!(ERROR, "Equation {} `{}` skipped: {}", i + 1, nodes[i].to_string(), error),
+                None             => equations.push((i, shape, curve))
+            };
+        }
+        if (equations.is_empty()) {
+            return ParametricCurves {curves: vec![]};
+        }
+        log!(DEBUG,
+            "Tracing {} parametric or polar curve{}.",
+            equations.len(),
+            if (equations.len() == 1) {""} else {"s"}
+        );
+        let curves = equations.par_iter()
+            .map(|(i, shape, curve)| (*i, path::clip(&Tracer::new(settings, *shape, curve).trace(), settings)))
+            .collect();
+        return ParametricCurves {curves};
+    }
+
+    // Paths of the curve at an index in the equation list.
+    pub fn paths(&self, index : usize) -> Vec<Vec<[f64; 2]>> {
+        return self.curves.iter()
+            .find(|(i, _)| *i == index)
+            .map(|(_, paths)| paths.clone())
+            .unwrap_or_default();
+    }
+}
+
+
+#[derive(Clone, Copy)]
+enum Shape {
+    // `x` and `y` of `t`.
+    Parametric,
+    // `r` of `θ`.
+    Polar
+}
+
+struct Tracer<'l, 'm> {
+    settings : &'l RenderSettings,
+    shape    : Shape,
+    curve    : &'l PointEquations<'m>,
+    // Graph units per pixel : X, Y
+    pixel    : [f64; 2]
+}
+impl<'l, 'm> Tracer<'l, 'm> {
+    fn new(settings : &'l RenderSettings, shape : Shape, curve : &'l PointEquations<'m>) -> Tracer<'l, 'm> {
+        let cells = u32::pow(2, settings.split_depth) as f64;
+        return Tracer {
+            settings,
+            shape,
+            curve,
+            pixel : [
+                (settings.frame[2] - settings.frame[0]) / cells,
+                (settings.frame[3] - settings.frame[1]) / cells
+            ]
+        };
+    }
+
+    // Points of each branch at `t`, or `θ`.
+    fn points(&self, t : f64) -> Vec<[f64; 2]> {
+        let mut points = vec![];
+        match (self.shape) {
+            Shape::Parametric => {
+                let values = self.curve.evaluate(&[t]);
+                for x in values[0].compress().get_values() {
+                    for y in values[1].compress().get_values() {
+                        points.push([*x, *y]);
+                    }
+                }
+            },
+            Shape::Polar => {
+                for r in self.curve.evaluate(&[t, t])[0].compress().get_values() {
+                    points.push([r * t.cos(), r * t.sin()]);
+                }
+            }
+        };
+        return points;
+    }
+
+    fn trace(&self) -> Vec<Vec<[f64; 2]>> {
+        let [start, end] = match (self.shape) {
+            Shape::Parametric => self.settings.parametric_domain,
+            Shape::Polar      => self.settings.polar_domain
+        };
+        let ts      = (0..=SAMPLES).map(|i| start + (end - start) * (i as f64) / (SAMPLES as f64)).collect::<Vec<f64>>();
+        let samples = ts.iter().map(|t| self.points(*t)).collect::<Vec<Vec<[f64; 2]>>>();
+        let branches = samples.iter().map(|points| points.len()).max().unwrap_or(0);
+        let mut paths = vec![];
+        for branch in 0..branches {
+            let mut branch_paths = vec![vec![]];
+            if let Some(point) = samples[0].get(branch) {
+                branch_paths[0].push(*point);
+            }
+            for i in 0..SAMPLES {
+                self.step(branch, [ts[i], ts[i + 1]], [samples[i].get(branch).cloned(), samples[i + 1].get(branch).cloned()], 0, &mut branch_paths);
+            }
+            paths.append(&mut branch_paths);
+        }
+        return paths.into_iter().filter(|path| path.len() > 1).collect();
+    }
+
+    // Add the end of a step to the last path, halving the step until it is
+    // straight enough, or start a new path from it.
+    fn step(&self, branch : usize, t : [f64; 2], ends : [Option<[f64; 2]>; 2], depth : u32, paths : &mut Vec<Vec<[f64; 2]>>) {
+        let [start, end] = match (ends) {
+            [Some(start), Some(end)] => [start, end],
+            [None, None]             => return,
+            _ => {
+                // Narrow down where the branch starts or stops.
+                if (depth < MAX_DEPTH) {
+                    let middle_t = (t[0] + t[1]) / 2.0;
+                    let middle   = self.points(middle_t).get(branch).cloned();
+                    self.step(branch, [t[0], middle_t], [ends[0], middle], depth + 1, paths);
+                    self.step(branch, [middle_t, t[1]], [middle, ends[1]], depth + 1, paths);
+                } else if let Some(end) = ends[1] {
+                    paths.push(vec![end]);
+                }
+                return;
+            }
+        };
+        let middle_t = (t[0] + t[1]) / 2.0;
+        let middle   = match (self.points(middle_t).get(branch).cloned()) {
+            Some(middle) => middle,
+            None         => {
+                self.step(branch, [t[0], middle_t], [Some(start), None], depth + 1, paths);
+                self.step(branch, [middle_t, t[1]], [None, Some(end)], depth + 1, paths);
+                return;
+            }
+        };
+        if (self.off_frame([start, middle, end])) {
+            paths.push(vec![end]);
+            return;
+        }
+        if (self.is_straight(start, middle, end)) {
+            push(paths, end);
+            return;
+        }
+        if (depth >= MAX_DEPTH) {
+            paths.push(vec![end]);
+            return;
+        }
+        self.step(branch, [t[0], middle_t], [Some(start), Some(middle)], depth + 1, paths);
+        self.step(branch, [middle_t, t[1]], [Some(middle), Some(end)], depth + 1, paths);
+    }
+
+    // Whether the middle lies near the line between the ends, and the ends are close.
+    fn is_straight(&self, start : [f64; 2], middle : [f64; 2], end : [f64; 2]) -> bool {
+        let chord  = [(end[0] - start[0]) / self.pixel[0], (end[1] - start[1]) / self.pixel[1]];
+        let offset = [
+            (middle[0] - (start[0] + end[0]) / 2.0) / self.pixel[0],
+            (middle[1] - (start[1] + end[1]) / 2.0) / self.pixel[1]
+        ];
+        return chord[0].hypot(chord[1]) <= MAX_CHORD && offset[0].hypot(offset[1]) <= TOLERANCE;
+    }
+
+    // Whether every point lies beyond the same side of the frame.
+    fn off_frame(&self, points : [[f64; 2]; 3]) -> bool {
+        let frame = self.settings.frame;
+        return points.iter().all(|point| point[0] < frame[0])
+            || points.iter().all(|point| point[1] < frame[1])
+            || points.iter().all(|point| point[0] > frame[2])
+            || points.iter().all(|point| point[1] > frame[3]);
+    }
+}
+
+// Add a point to the last path.
+fn push(paths : &mut Vec<Vec<[f64; 2]>>, point : [f64; 2]) {
+    match (paths.last_mut()) {
+        Some(path) => path.push(point),
+        None       => paths.push(vec![point])
+    };
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::import::{self, Format};
+    use crate::parse::var;
+    use crate::render::classify;
+
+    fn trace(source : &str, settings : &RenderSettings) -> Vec<Vec<[f64; 2]>> {
+        let nodes        = import::parse(source, Format::Text).unwrap().nodes;
+        let kinds        = nodes.iter().map(classify).collect::<Vec<EquationKind>>();
+        let dependencies = Dependencies::new(settings, &nodes, &kinds);
+        return ParametricCurves::new(settings, &nodes, &kinds, &dependencies).paths(0);
+    }
+
+    fn close(a : [f64; 2], b : [f64; 2]) -> bool {
+        return (a[0] - b[0]).hypot(a[1] - b[1]) < 1e-9;
+    }
+
+    #[test]
+    fn closed_curves_are_traced_over_the_whole_domain() {
+        let settings = RenderSettings::new();
+        let paths    = trace("(2 * cos(t), 2 * sin(t))", &settings);
+        assert_eq!(paths.len(), 1);
+        let path  = &paths[0];
+        let pixel = 10.0 / 256.0;
+        // Starts and ends at `t = 0` and `t = 2π`, the same point.
+        assert!(close(path[0], [2.0, 0.0]));
+        assert!(close(path[path.len() - 1], [2.0, 0.0]));
+        assert!(path.iter().all(|point| (point[0].hypot(point[1]) - 2.0).abs() < 1e-9));
+        // No step is longer than allowed, so no part of the circle is cut short.
+        assert!(path.windows(2).all(|pair| (pair[1][0] - pair[0][0]).hypot(pair[1][1] - pair[0][1]) <= MAX_CHORD * pixel));
+        // Goes around once, anticlockwise.
+        let angles = path.iter().map(|point| point[1].atan2(point[0]).rem_euclid(var::TAU)).collect::<Vec<f64>>();
+        assert!(angles[1..angles.len() - 1].windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn curves_stop_at_the_ends_of_the_domain() {
+        let settings = RenderSettings::new().set_parametric_domain([0.0, var::PI]);
+        let paths    = trace("(2 * cos(t), 2 * sin(t))", &settings);
+        assert_eq!(paths.len(), 1);
+        let path = &paths[0];
+        assert!(close(path[0], [2.0, 0.0]));
+        assert!(close(path[path.len() - 1], [-2.0, 0.0]));
+        assert!(path.iter().all(|point| point[1] >= -1e-9));
+    }
+
+    #[test]
+    fn paths_break_where_the_curve_leaves_the_frame() {
+        let settings = RenderSettings::new().set_parametric_domain([-3.0, 3.0]);
+        let paths    = trace("(t, tan(t))", &settings);
+        // Either side of `t = ±π/2`.
+        assert_eq!(paths.len(), 3);
+        let pixel = 10.0 / 256.0;
+        assert!(paths.iter().flatten().all(|point| point[1].abs() <= 5.0 + 1e-9));
+        assert!(paths.iter().all(|path| path.windows(2).all(|pair| (pair[1][1] - pair[0][1]).abs() <= MAX_CHORD * pixel)));
+    }
+}
@@ -0,0 +1,435 @@
+// Polylines traced through the hit cells of a render node tree.
+//
+// Bands of hit cells are first thinned to one cell wide, then neighbouring
+// cells, including diagonals, are joined into one path through their
+// centres. Wherever the curve leaves a gap, such as across the asymptotes
+// of `tan(x)`, the path ends and a new one starts. Branches that meet a
+// path already traced are joined to it, so crossings are not left with a
+// gap.
+
+use std::collections::HashSet;
+
+use loggerithm::{logger, log};
+use loggerithm::level::DEBUG;
+logger!(super);
+
+use crate::helper;
+use crate::render::settings::RenderSettings;
+
+
+// Offsets to the neighbours of a cell, sides before corners.
+const NEIGHBOURS : [[i64; 2]; 8] = [
+    [1, 0], [0, 1], [-1, 0], [0, -1],
+    [1, 1], [-1, 1], [-1, -1], [1, -1]
+];
+
+// Dash pattern for borders that are not part of their region, in
+// millimetres on the job : On, Off
+pub const DASH : [f64; 2] = [2.0, 1.0];
+
+
+// Trace the cells into paths of points in graph space.
+pub fn trace(cells : &[[u32; 2]], settings : &RenderSettings) -> Vec<Vec<[f64; 2]>> {
+    let hit         = thin(cells.iter().cloned().collect::<HashSet<[u32; 2]>>());
+    let mut visited = HashSet::new();
+    let mut order   = hit.iter().cloned().collect::<Vec<[u32; 2]>>();
+    order.sort_by_key(|cell| (cell[1], cell[0]));
+
+    let mut paths = vec![];
+    for start in order {
+        if (visited.contains(&start)) {
+            continue;
+        }
+        visited.insert(start);
+        let mut path = vec![start];
+        extend(&mut path, &hit, &mut visited);
+        // The start may be partway along the curve, so go back the other way too.
+        path.reverse();
+        extend(&mut path, &hit, &mut visited);
+        // Close loops.
+        if (path.len() > 2 && is_neighbour(path[0], path[path.len() - 1])) {
+            path.push(path[0]);
+        }
+        paths.push(simplify(&path));
+    }
+    log!(DEBUG,
+        "Traced {} path{} through {} cell{}.",
+        helper::commaify_i64(paths.len() as i64),
+        if (paths.len() == 1) {""} else {"s"},
+        helper::commaify_i64(cells.len() as i64),
+        if (cells.len() == 1) {""} else {"s"}
+    );
+
+    let grid_size = u32::pow(2, settings.split_depth) as f64;
+    return paths.into_iter().map(|path| path.into_iter().map(|point| [
+        settings.frame[0] + (settings.frame[2] - settings.frame[0]) * (point[0] + 0.5) / grid_size,
+        settings.frame[1] + (settings.frame[3] - settings.frame[1]) * (point[1] + 0.5) / grid_size
+    ]).collect()).collect();
+}
+
+// Split paths into dashes, measured in millimetres on the job so the
+// pattern looks the same along both axes.
+pub fn dash(paths : &[Vec<[f64; 2]>], settings : &RenderSettings) -> Vec<Vec<[f64; 2]>> {
+    let scale  = [
+        settings.size[0] / (settings.frame[2] - settings.frame[0]),
+        settings.size[1] / (settings.frame[3] - settings.frame[1])
+    ];
+    let mut dashes = vec![];
+    for path in paths {
+        if (path.len() < 2) {
+            dashes.push(path.clone());
+            continue;
+        }
+        let mut on      = true;
+        let mut left    = DASH[0];
+        let mut current = vec![path[0]];
+        for pair in path.windows(2) {
+            let mut start = pair[0];
+            let end       = pair[1];
+            loop {
+                let length = ((end[0] - start[0]) * scale[0]).hypot((end[1] - start[1]) * scale[1]);
+                if (length <= left) {
+                    left -= length;
+                    if (on) {
+                        current.push(end);
+                    }
+                    break;
+                }
+                let t = left / length;
+                start = [start[0] + (end[0] - start[0]) * t, start[1] + (end[1] - start[1]) * t];
+                if (on) {
+                    current.push(start);
+                    dashes.push(std::mem::take(&mut current));
+                } else {
+                    current = vec![start];
+                }
+                on   = ! on;
+                left = if (on) {DASH[0]} else {DASH[1]};
+            }
+        }
+        if (on && current.len() > 1) {
+            dashes.push(current);
+        }
+    }
+    return dashes;
+}
+
+// Cut paths at the edges of the frame, leaving only the parts inside it
+// (Liang-Barsky). A path leaving and coming back is split in two.
+pub fn clip(paths : &[Vec<[f64; 2]>], settings : &RenderSettings) -> Vec<Vec<[f64; 2]>> {
+    let frame       = settings.frame;
+    let mut clipped = vec![];
+    for path in paths {
+        let mut current : Vec<[f64; 2]> = vec![];
+        for pair in path.windows(2) {
+            let (start, end) = (pair[0], pair[1]);
+            let delta        = [end[0] - start[0], end[1] - start[1]];
+            let mut range    = [0.0, 1.0];
+            let mut inside   = true;
+            // Distance to each edge against the direction of travel : Left, Right, Bottom, Top
+            for (p, q) in [
+                (-delta[0], start[0] - frame[0]), (delta[0], frame[2] - start[0]),
+                (-delta[1], start[1] - frame[1]), (delta[1], frame[3] - start[1])
+            ] {
+                if (p == 0.0) {
+                    if (q < 0.0) {
+                        inside = false;
+                    }
+                    continue;
+                }
+                let t = q / p;
+                if (p < 0.0) {
+                    range[0] = f64::max(range[0], t);
+                } else {
+                    range[1] = f64::min(range[1], t);
+                }
+            }
+            if (! inside || range[0] > range[1]) {
+                if (current.len() > 1) {
+                    clipped.push(std::mem::take(&mut current));
+                }
+                current.clear();
+                continue;
+            }
+            // Unclipped ends are kept exactly, so consecutive segments still join.
+            let from = if (range[0] > 0.0) {[start[0] + delta[0] * range[0], start[1] + delta[1] * range[0]]} else {start};
+            let to   = if (range[1] < 1.0) {[start[0] + delta[0] * range[1], start[1] + delta[1] * range[1]]} else {end};
+            if (current.last() != Some(&from)) {
+                if (current.len() > 1) {
+                    clipped.push(std::mem::take(&mut current));
+                }
+                current = vec![from];
+            }
+            current.push(to);
+            // Leaving the frame ends the path.
+            if (range[1] < 1.0) {
+                clipped.push(std::mem::take(&mut current));
+            }
+        }
+        if (current.len() > 1) {
+            clipped.push(current);
+        }
+    }
+    return clipped;
+}
+
+// Order paths so each starts near where the last ended, reversing them
+// where that is shorter, to cut down on travel between paths.
+pub fn order_for_travel(paths : Vec<Vec<[f64; 2]>>) -> Vec<Vec<[f64; 2]>> {
+    let mut remaining = paths.into_iter().filter(|path| ! path.is_empty()).collect::<Vec<Vec<[f64; 2]>>>();
+    let mut ordered   = vec![];
+    let mut position  = [f64::NEG_INFINITY, f64::NEG_INFINITY];
+    while (! remaining.is_empty()) {
+        let mut best = (0, false, f64::INFINITY);
+        for (i, path) in remaining.iter().enumerate() {
+            let start = distance(position, path[0]);
+            let end   = distance(position, path[path.len() - 1]);
+            if (start < best.2) {
+                best = (i, false, start);
+            }
+            if (end < best.2) {
+                best = (i, true, end);
+            }
+        }
+        let mut path = remaining.swap_remove(best.0);
+        if (best.1) {
+            path.reverse();
+        }
+        position = path[path.len() - 1];
+        ordered.push(path);
+    }
+    return ordered;
+}
+
+
+// Peel cells off the sides of thick bands until they are one cell wide,
+// keeping ends and connections (Zhang-Suen thinning).
+fn thin(mut cells : HashSet<[u32; 2]>) -> HashSet<[u32; 2]> {
+    // Clockwise from above.
+    const AROUND : [[i64; 2]; 8] = [[0, 1], [1, 1], [1, 0], [1, -1], [0, -1], [-1, -1], [-1, 0], [-1, 1]];
+    loop {
+        let mut changed = false;
+        for step in 0..2 {
+            let remove = cells.iter().filter(|cell| {
+                let around = AROUND.map(|offset| offset_cell(**cell, offset).is_some_and(|cell| cells.contains(&cell)));
+                let count  = around.iter().filter(|hit| **hit).count();
+                let starts = (0..8).filter(|i| ! around[*i] && around[(i + 1) % 8]).count();
+                let [above, _, right, _, below, _, left, _] = around;
+                let sides  = if (step == 0) {
+                    ! (above && right && below) && ! (right && below && left)
+                } else {
+                    ! (above && right && left) && ! (above && below && left)
+                };
+                return (2..=6).contains(&count) && starts == 1 && sides;
+            }).cloned().collect::<Vec<[u32; 2]>>();
+            changed |= ! remove.is_empty();
+            for cell in remove {
+                cells.remove(&cell);
+            }
+        }
+        if (! changed) {
+            break;
+        }
+    }
+    // Thinning leaves the inside corners of staircases, which the cells on
+    // either side already join diagonally.
+    let mut order = cells.iter().cloned().collect::<Vec<[u32; 2]>>();
+    order.sort_by_key(|cell| (cell[1], cell[0]));
+    for cell in order {
+        let around = AROUND.map(|offset| offset_cell(cell, offset).is_some_and(|cell| cells.contains(&cell)));
+        let count  = around.iter().filter(|hit| **hit).count();
+        if (count >= 2 && count_groups(around) == 1 && (0..8).step_by(2).any(|i| ! around[i])) {
+            cells.remove(&cell);
+        }
+    }
+    return cells;
+}
+
+// Number of separate groups among the hit cells around a cell, in the
+// order of `AROUND` in `thin`. Sides touching diagonally count as joined.
+fn count_groups(around : [bool; 8]) -> usize {
+    let mut group = [usize::MAX; 8];
+    let mut count = 0;
+    for i in 0..8 {
+        if (! around[i] || group[i] != usize::MAX) {
+            continue;
+        }
+        // Flood around the ring.
+        let mut stack = vec![i];
+        group[i] = count;
+        while let Some(j) = stack.pop() {
+            let mut next = vec![(j + 1) % 8, (j + 7) % 8];
+            if (j % 2 == 0) {
+                next.push((j + 2) % 8);
+                next.push((j + 6) % 8);
+            }
+            for k in next {
+                if (around[k] && group[k] == usize::MAX) {
+                    group[k] = count;
+                    stack.push(k);
+                }
+            }
+        }
+        count += 1;
+    }
+    return count;
+}
+
+// Walk on from the end of the path through unvisited cells, keeping as
+// straight as possible.
+fn extend(path : &mut Vec<[u32; 2]>, hit : &HashSet<[u32; 2]>, visited : &mut HashSet<[u32; 2]>) {
+    loop {
+        let last      = path[path.len() - 1];
+        let direction = if (path.len() > 1) {
+            let previous = path[path.len() - 2];
+            [last[0] as i64 - previous[0] as i64, last[1] as i64 - previous[1] as i64]
+        } else {[0, 0]};
+        let mut best : Option<([u32; 2], i64)> = None;
+        for offset in NEIGHBOURS {
+            let cell = match (offset_cell(last, offset)) {
+                Some(cell) if (hit.contains(&cell) && ! visited.contains(&cell)) => cell,
+                _ => continue
+            };
+            // Higher is straighter.
+            let score = offset[0] * direction[0] + offset[1] * direction[1];
+            if (best.is_none_or(|(_, best_score)| score > best_score)) {
+                best = Some((cell, score));
+            }
+        }
+        match (best) {
+            Some((cell, _)) => {
+                visited.insert(cell);
+                path.push(cell);
+            },
+            None => {
+                // Join onto a path already traced, if one is beside the end.
+                let previous = if (path.len() > 1) {Some(path[path.len() - 2])} else {None};
+                for offset in NEIGHBOURS {
+                    if let Some(cell) = offset_cell(last, offset) {
+                        if (hit.contains(&cell) && Some(cell) != previous && ! path.contains(&cell)) {
+                            path.push(cell);
+                            break;
+                        }
+                    }
+                }
+                return;
+            }
+        };
+    }
+}
+
+fn offset_cell(cell : [u32; 2], offset : [i64; 2]) -> Option<[u32; 2]> {
+    let x = u32::try_from(cell[0] as i64 + offset[0]).ok()?;
+    let y = u32::try_from(cell[1] as i64 + offset[1]).ok()?;
+    return Some([x, y]);
+}
+
+fn is_neighbour(a : [u32; 2], b : [u32; 2]) -> bool {
+    return a != b && a[0].abs_diff(b[0]) <= 1 && a[1].abs_diff(b[1]) <= 1;
+}
+
+fn distance(a : [f64; 2], b : [f64; 2]) -> f64 {
+    return ((a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2)).sqrt();
+}
+
+// Drop points that lie within half a cell of a straight line between
+// their neighbours (Ramer-Douglas-Peucker), leaving points in cell units.
+fn simplify(path : &[[u32; 2]]) -> Vec<[f64; 2]> {
+    let points = path.iter().map(|cell| [cell[0] as f64, cell[1] as f64]).collect::<Vec<[f64; 2]>>();
+    if (points.len() < 3) {
+        return points;
+    }
+    let mut keep = vec![false; points.len()];
+    keep[0]                = true;
+    keep[points.len() - 1] = true;
+    let mut stack = vec![(0, points.len() - 1)];
+    while let Some((first, last)) = stack.pop() {
+        let mut furthest = (0, 0.0);
+        for i in first + 1..last {
+            let offset = distance_to_line(points[i], points[first], points[last]);
+            if (offset > furthest.1) {
+                furthest = (i, offset);
+            }
+        }
+        if (furthest.1 > 0.5) {
+            keep[furthest.0] = true;
+            stack.push((first, furthest.0));
+            stack.push((furthest.0, last));
+        }
+    }
+    return points.into_iter().zip(keep).filter(|(_, keep)| *keep).map(|(point, _)| point).collect();
+}
+
+fn distance_to_line(point : [f64; 2], start : [f64; 2], end : [f64; 2]) -> f64 {
+    let length = distance(start, end);
+    if (length == 0.0) {
+        return distance(point, start);
+    }
+    return ((end[0] - start[0]) * (start[1] - point[1]) - (start[0] - point[0]) * (end[1] - start[1])).abs() / length;
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Traced in an 8 by 8 grid, so graph space is cell units, offset to the
+    // cell centres. Paths run back from the last cell they reach.
+    fn traced(cells : &[[u32; 2]]) -> Vec<Vec<[f64; 2]>> {
+        return trace(cells, &RenderSettings::new().set_frame([0.0, 0.0, 8.0, 8.0]).set_split_depth(3));
+    }
+
+    #[test]
+    fn lines_become_their_end_points() {
+        let row = (0..8).map(|x| [x, 3]).collect::<Vec<[u32; 2]>>();
+        assert_eq!(traced(&row), [[[7.5, 3.5], [0.5, 3.5]]]);
+        let diagonal = (0..8).map(|x| [x, x]).collect::<Vec<[u32; 2]>>();
+        assert_eq!(traced(&diagonal), [[[7.5, 7.5], [0.5, 0.5]]]);
+    }
+
+    #[test]
+    fn bands_are_thinned_to_one_path() {
+        let band = (0..8).flat_map(|x| [[x, 3], [x, 4]]).collect::<Vec<[u32; 2]>>();
+        let paths = traced(&band);
+        assert_eq!(paths.len(), 1);
+        assert_eq!(paths[0].len(), 2);
+    }
+
+    #[test]
+    fn gaps_start_a_new_path() {
+        let cells = [[0, 0], [1, 0], [2, 0], [5, 0], [6, 0], [7, 0]];
+        assert_eq!(traced(&cells), [[[2.5, 0.5], [0.5, 0.5]], [[7.5, 0.5], [5.5, 0.5]]]);
+    }
+
+    #[test]
+    fn loops_are_closed() {
+        let mut ring = vec![];
+        for i in 2..6 {
+            ring.extend([[i, 2], [i, 5], [2, i], [5, i]]);
+        }
+        let paths = traced(&ring);
+        assert_eq!(paths.len(), 1);
+        assert_eq!(paths[0].first(), paths[0].last());
+        // The four corners, with the first repeated.
+        assert_eq!(paths[0].len(), 5);
+    }
+
+    #[test]
+    fn crossings_are_joined() {
+        let mut cross = (0..7).map(|x| [x, 3]).collect::<Vec<[u32; 2]>>();
+        cross.extend((0..7).filter(|y| *y != 3).map(|y| [3, y]));
+        let paths = traced(&cross);
+        let ends  = paths.iter().flat_map(|path| [path[0], path[path.len() - 1]]).collect::<Vec<[f64; 2]>>();
+        for end in [[0.5, 3.5], [6.5, 3.5], [3.5, 0.5], [3.5, 6.5]] {
+            assert!(ends.contains(&end), "{:?} in {:?}", end, paths);
+        }
+        // Arms traced after the first are joined onto it at the centre.
+        assert_eq!(ends.iter().filter(|end| **end == [3.5, 3.5]).count(), paths.len() - 1, "{:?}", paths);
+    }
+
+    #[test]
+    fn travel_order_starts_near_the_last_end() {
+        let paths = vec![vec![[0.0, 0.0], [4.0, 0.0]], vec![[10.0, 0.0], [12.0, 0.0]], vec![[9.0, 0.0], [5.0, 0.0]]];
+        assert_eq!(order_for_travel(paths), [[[0.0, 0.0], [4.0, 0.0]], [[5.0, 0.0], [9.0, 0.0]], [[10.0, 0.0], [12.0, 0.0]]]);
+    }
+}
@@ -0,0 +1,166 @@
+// Polar coordinates, `r` and `θ` (or `theta`), in the Cartesian frame.
+//
+// `r = f(θ)` is traced as a curve over `settings.polar_domain`, see
+// `parametric`. Every other relation in `r` and `θ` is drawn on the grid
+// like any implicit equation or region, with `r` and `θ` worked out from
+// `x` and `y` at each point. `θ` then goes once around, from the start of
+// the domain, and points at angles past its end are left out.
+//
+// Relations that do not repeat every turn, such as `θ = 1`, change sign
+// where `θ` wraps around. Cells across that seam are checked again with `θ`
+// carried on smoothly from either side, so no line is drawn along it.
+
+use crate::parse::var;
+use crate::parse::intervals::Interval;
+use crate::render::settings::RenderSettings;
+
+
+// Values of `super::COORDINATES` at a point in graph space. The angle is
+// `NaN` outside the domain, which no relation holds for.
+pub fn coordinates(settings : &RenderSettings, point : [f64; 2]) -> [f64; 5] {
+    let [start, end] = settings.polar_domain;
+    let mut angle = fold(start, point[1].atan2(point[0]));
+    if (angle > end) {
+        angle = f64::NAN;
+    }
+    return with_angle(point, angle);
+}
+
+// Values of `super::COORDINATES` at the corners of a cell lying across the
+// seam, with `θ` carried on past it from the start of the domain, and from
+// the end where the domain goes all the way around. `None` for any other
+// cell, including those around the origin, where every angle meets.
+pub fn seam_coordinates(settings : &RenderSettings, corners : &[[f64; 2]]) -> Option<Vec<Vec<[f64; 5]>>> {
+    let [start, end] = settings.polar_domain;
+    if (contains_origin(corners)) {
+        return None;
+    }
+    let angles = corners.iter().map(|corner| fold(start, corner[1].atan2(corner[0]))).collect::<Vec<f64>>();
+    let lowest  = angles.iter().cloned().fold(f64::INFINITY, f64::min);
+    let highest = angles.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    // A cell clear of the origin spans less than half a turn, unless it crosses the seam.
+    if (highest - lowest <= var::PI) {
+        return None;
+    }
+    let halfway = start + var::PI;
+    let mut sides = vec![
+        corners.iter().zip(&angles).map(|(corner, angle)| with_angle(*corner, if (*angle > halfway) {angle - var::TAU} else {*angle})).collect()
+    ];
+    if (end >= start + var::TAU) {
+        sides.push(corners.iter().zip(&angles).map(|(corner, angle)| with_angle(*corner, if (*angle < halfway) {angle + var::TAU} else {*angle})).collect());
+    }
+    return Some(sides);
+}
+
+// Values of `r` over a cell.
+pub fn radius_interval(x : [f64; 2], y : [f64; 2]) -> Interval {
+    let nearest  = [0.0f64.clamp(x[0], x[1]), 0.0f64.clamp(y[0], y[1])];
+    let furthest = [x[0].abs().max(x[1].abs()), y[0].abs().max(y[1].abs())];
+    return Interval::new(nearest[0].hypot(nearest[1]), furthest[0].hypot(furthest[1]));
+}
+
+// Values of `θ` over a cell, in two pieces where it crosses the seam. Empty
+// where the cell lies wholly past the end of the domain.
+pub fn angle_intervals(settings : &RenderSettings, x : [f64; 2], y : [f64; 2]) -> Vec<Interval> {
+    let [start, end] = settings.polar_domain;
+    let turn         = (start + var::TAU).min(end);
+    let corners      = [[x[0], y[0]], [x[1], y[0]], [x[0], y[1]], [x[1], y[1]]];
+    if (contains_origin(&corners)) {
+        return vec![Interval::new(start, turn)];
+    }
+    // Corner angles near that of the centre, so they do not wrap.
+    let centre  = ((y[0] + y[1]) / 2.0).atan2((x[0] + x[1]) / 2.0);
+    let angles  = corners.iter().map(|corner| centre + (corner[1].atan2(corner[0]) - centre + var::PI).rem_euclid(var::TAU) - var::PI).collect::<Vec<f64>>();
+    let lowest  = angles.iter().cloned().fold(f64::INFINITY, f64::min);
+    let highest = angles.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    let shift   = fold(start, lowest) - lowest;
+    let pieces  = if (highest + shift <= start + var::TAU) {
+        vec![Interval::new(lowest + shift, highest + shift)]
+    } else {
+        vec![Interval::new(lowest + shift, start + var::TAU), Interval::new(start, highest + shift - var::TAU)]
+    };
+    return pieces.into_iter()
+        .filter(|piece| piece.lower <= turn)
+        .map(|piece| Interval::new(piece.lower, piece.upper.min(turn)))
+        .collect();
+}
+
+// An angle moved by whole turns into `[start, start + 2π)`.
+fn fold(start : f64, angle : f64) -> f64 {
+    return start + (angle - start).rem_euclid(var::TAU);
+}
+
+fn with_angle(point : [f64; 2], angle : f64) -> [f64; 5] {
+    return [point[0], point[1], point[0].hypot(point[1]), angle, angle];
+}
+
+// Whether the origin lies within the bounds of the points.
+fn contains_origin(points : &[[f64; 2]]) -> bool {
+    return points.iter().any(|point| point[0] <= 0.0) && points.iter().any(|point| point[0] >= 0.0)
+        && points.iter().any(|point| point[1] <= 0.0) && points.iter().any(|point| point[1] >= 0.0);
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::import::{self, Format};
+    use crate::render::{classify, EquationKind};
+    use crate::render::dependency::Dependencies;
+    use crate::render::parametric::ParametricCurves;
+
+    fn trace(source : &str, settings : &RenderSettings) -> Vec<Vec<[f64; 2]>> {
+        let nodes        = import::parse(source, Format::Text).unwrap().nodes;
+        let kinds        = nodes.iter().map(classify).collect::<Vec<EquationKind>>();
+        let dependencies = Dependencies::new(settings, &nodes, &kinds);
+        return ParametricCurves::new(settings, &nodes, &kinds, &dependencies).paths(0);
+    }
+
+    fn close(a : [f64; 2], b : [f64; 2]) -> bool {
+        return (a[0] - b[0]).hypot(a[1] - b[1]) < 1e-9;
+    }
+
+    #[test]
+    fn curves_are_traced_over_the_angle_range() {
+        let settings = RenderSettings::new().set_polar_domain([0.0, var::PI / 2.0]);
+        let paths    = trace("r = 2", &settings);
+        assert_eq!(paths.len(), 1);
+        let path = &paths[0];
+        assert!(close(path[0], [2.0, 0.0]));
+        assert!(close(path[path.len() - 1], [0.0, 2.0]));
+        assert!(path.iter().all(|point| point[0] >= -1e-9 && point[1] >= -1e-9));
+
+        // Past one turn, `r = θ / 4` keeps winding outwards.
+        let settings = RenderSettings::new().set_polar_domain([0.0, 2.0 * var::TAU]);
+        let paths    = trace("r = θ / 4", &settings);
+        assert_eq!(paths.len(), 1);
+        let path  = &paths[0];
+        let radii = path.iter().map(|point| point[0].hypot(point[1])).collect::<Vec<f64>>();
+        assert!(close(path[path.len() - 1], [var::PI, 0.0]));
+        assert!(radii.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn angles_past_the_range_are_undefined() {
+        let settings = RenderSettings::new().set_polar_domain([0.0, var::PI]);
+        assert_eq!(coordinates(&settings, [0.0, 1.0])[3], var::PI / 2.0);
+        assert!(coordinates(&settings, [0.0, -1.0])[3].is_nan());
+        // Angles are folded to start at the start of the range.
+        let settings = RenderSettings::new().set_polar_domain([var::PI, 2.0 * var::PI]);
+        assert_eq!(coordinates(&settings, [0.0, -1.0])[3], 3.0 * var::PI / 2.0);
+        assert!(coordinates(&settings, [0.0, 1.0])[3].is_nan());
+        assert!(angle_intervals(&settings, [0.5, 1.0], [0.5, 1.0]).is_empty());
+        assert_eq!(angle_intervals(&settings, [-1.0, -0.5], [-1.0, -0.5]).len(), 1);
+    }
+
+    #[test]
+    fn cells_across_the_seam_are_split() {
+        let settings = RenderSettings::new();
+        let pieces   = angle_intervals(&settings, [0.5, 1.0], [-0.25, 0.25]);
+        assert_eq!(pieces.len(), 2);
+        assert!(pieces.iter().any(|piece| piece.lower == 0.0));
+        assert!(pieces.iter().any(|piece| piece.upper == var::TAU));
+        assert!(seam_coordinates(&settings, &[[0.5, -0.25], [1.0, 0.25]]).is_some());
+        assert!(seam_coordinates(&settings, &[[0.5, 0.25], [1.0, 0.5]]).is_none());
+    }
+}
@@ -0,0 +1,141 @@
+use loggerithm::{logger, log};
+use loggerithm::level::DEBUG;
+logger!(super);
+
+use crate::error::Error;
+use crate::parse::node::{Node, Relation};
+use crate::parse::values::EvaluatedValues;
+use crate::render::settings::RenderSettings;
+use super::{EquationKind, COORDINATES};
+use super::dependency::Dependencies;
+use super::hatch;
+use super::polar;
+use super::variables::{PointEquations, Quantity};
+
+
+// Opacity of region shading, relative to the equation's colour, as in Desmos.
+pub const FILL_OPACITY : f64 = 0.4;
+
+
+// Inequalities in `x` and `y`, such as `x^2 + y^2 < 4`. The borders are
+// drawn as curves like any other equation, this tests which points lie
+// inside, for shading and hatching.
+pub struct RegionEquations<'l> {
+    // `left - right` of each region.
+    equations : PointEquations<'l>,
+    // Relation of each region, in the order of `equations`.
+    relations : Vec<Relation>
+}
+impl<'l> RegionEquations<'l> {
+    pub fn new(settings : &RenderSettings, nodes : &'l [Node], kinds : &[EquationKind], dependencies : &Dependencies) -> RegionEquations<'l> {
+        let mut quantities = vec![];
+        for i in 0..nodes.len() {
+            if (kinds[i] == EquationKind::Region && ! dependencies.is_skipped(i)) {
+                if let Some((_, left, right)) = nodes[i].base.relation() {
+                    quantities.push((i, Quantity::Difference(left, right)));
+                }
+            }
+        }
+        // Failing regions are already reported by the pass drawing their borders.
+        let (equations, _) = PointEquations::new(settings, nodes, dependencies, &COORDINATES, quantities);
+        let relations = equations.indices().iter()
+            .filter_map(|i| nodes[*i].base.relation().map(|(relation, _, _)| relation))
+            .collect::<Vec<Relation>>();
+        if (! equations.is_empty()) {
+            log!(DEBUG,
+                "Found {} region{}.",
+                relations.len(),
+                if (relations.len() == 1) {""} else {"s"}
+            );
+        }
+        return RegionEquations {equations, relations};
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.equations.is_empty();
+    }
+
+    // Whether the equation is a region whose border is not part of it, drawn dashed.
+    pub fn is_strict(&self, index : usize) -> bool {
+        return self.equations.indices().iter().zip(&self.relations).any(|(i, relation)| *i == index && relation.is_strict());
+    }
+
+    // Index in the equation list of each region containing a point in graph space.
+    pub fn filled(&self, settings : &RenderSettings, point : [f64; 2]) -> Vec<usize> {
+        if (self.is_empty()) {
+            return vec![];
+        }
+        let differences = self.equations.evaluate(&polar::coordinates(settings, point));
+        return (0..differences.len())
+            .filter(|i| contains(&differences[*i], self.relations[*i]))
+            .map(|i| self.equations.indices()[i])
+            .collect();
+    }
+
+    // Fill lines across a region, see `hatch`.
+    pub fn hatch(&self, settings : &RenderSettings, index : usize) -> Result<Vec<Vec<[f64; 2]>>, Error> {
+        return match (self.equations.indices().iter().position(|i| *i == index)) {
+            Some(i) => hatch::fill_area(
+                |point| contains(&self.equations.evaluate(&polar::coordinates(settings, point))[i], self.relations[i]),
+                settings
+            ),
+            None => Ok(vec![])
+        };
+    }
+}
+
+// Whether any value of `left - right` satisfies the relation.
+fn contains(difference : &EvaluatedValues, relation : Relation) -> bool {
+    return difference.get_values().iter().any(|value| relation.holds(*value));
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::import::{self, Format};
+    use crate::render::classify;
+
+    // Run `target` with the regions of `source` in the default frame.
+    fn with_regions<T>(source : &str, target : impl FnOnce(&RegionEquations, &RenderSettings) -> T) -> T {
+        let settings     = RenderSettings::new();
+        let nodes        = import::parse(source, Format::Text).unwrap().nodes;
+        let kinds        = nodes.iter().map(classify).collect::<Vec<EquationKind>>();
+        let dependencies = Dependencies::new(&settings, &nodes, &kinds);
+        return target(&RegionEquations::new(&settings, &nodes, &kinds, &dependencies), &settings);
+    }
+
+    // Whether each point is in the region of the last equation.
+    fn filled(source : &str, points : &[[f64; 2]]) -> Vec<bool> {
+        let last = source.lines().count() - 1;
+        return with_regions(source, |regions, settings| {
+            return points.iter().map(|point| regions.filled(settings, *point) == [last]).collect();
+        });
+    }
+
+    #[test]
+    fn shades_the_side_where_the_relation_holds() {
+        let above_and_below = [[0.0, 2.0], [0.0, -2.0]];
+        assert_eq!(filled("y > x",  &above_and_below), [true, false]);
+        assert_eq!(filled("y < x",  &above_and_below), [false, true]);
+        assert_eq!(filled("x < y",  &above_and_below), [true, false]);
+        assert_eq!(filled("-y > x", &above_and_below), [false, true]);
+        // Points on the border are only shaded when it is part of the region.
+        let inside_outside_and_on = [[0.0, 0.0], [0.0, 2.0], [1.0, 0.0]];
+        assert_eq!(filled("x^2 + y^2 <= 1", &inside_outside_and_on), [true, false, true]);
+        assert_eq!(filled("x^2 + y^2 < 1",  &inside_outside_and_on), [true, false, false]);
+        assert_eq!(filled("x^2 + y^2 > 1",  &inside_outside_and_on), [false, true, false]);
+    }
+
+    #[test]
+    fn regions_read_definitions_and_polar_coordinates() {
+        assert_eq!(filled("a = 3\ny >= a", &[[0.0, 3.0], [0.0, 2.9]]), [true, false]);
+        assert_eq!(filled("r < 2",         &[[1.0, 1.0], [2.0, 2.0]]), [true, false]);
+    }
+
+    #[test]
+    fn only_strict_inequalities_are_dashed() {
+        let strict = with_regions("y > x\ny >= x\ny = x", |regions, _| (0..3).map(|i| regions.is_strict(i)).collect::<Vec<bool>>());
+        assert_eq!(strict, [true, false, false]);
+    }
+}
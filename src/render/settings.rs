@@ -1,3 +1,15 @@
+use loggerithm::{logger, log};
+use loggerithm::level::WARN;
+logger!(super);
+
+use crate::error::Error;
+use crate::parse::var;
+
+
+// Deepest the render tree can be split, `2 ^ 24` cells across.
+pub const MAX_SPLIT_DEPTH : u32 = 24;
+
+
 #[derive(Clone)]
 pub struct RenderSettings {
     // Corners of graph : Left, Bottom, Right, Top
@@ -7,5 +19,316 @@ pub struct RenderSettings {
     // Image size : Width, Height
     pub resolution: [u32; 2],
     // Filename
-    pub target: String
+    pub target: String,
+    // How cells are tested for curves.
+    pub evaluation: Evaluation,
+    // With `Evaluation::Complex`, how far from zero the imaginary part of a
+    // value may be for it to be drawn, relative to the real part once that is over one.
+    pub imaginary_tolerance: f64,
+    // Values of `t` parametric curves are traced over : Start, End
+    pub parametric_domain: [f64; 2],
+    // Values of `θ` polar curves are traced over : Start, End
+    pub polar_domain: [f64; 2],
+    // Worker threads, `0` for one per core.
+    pub threads: usize,
+    // Colour of each equation by index in the equation list : Red, Green, Blue, Alpha
+    // Equations past the end use `DEFAULT_COLOURS`.
+    pub colours: Vec<[u8; 4]>,
+    // Colour of image pixels no equation passes through : Red, Green, Blue, Alpha
+    pub background: [u8; 4],
+    // Size of vector and G-code output in millimetres : Width, Height
+    pub size: [f64; 2],
+    // Machine settings for G-code output.
+    pub gcode: GcodeSettings,
+    // Extra drawing for SVG output.
+    pub svg: SvgSettings,
+    // Fill lines for regions and closed curves in SVG and G-code output.
+    pub hatch: HatchSettings
+}
+impl Default for RenderSettings {
+    fn default() -> RenderSettings {
+        return RenderSettings::new();
+    }
+}
+impl RenderSettings {
+    pub fn new() -> RenderSettings {
+        return RenderSettings {
+            frame               : [-5.0, -5.0, 5.0, 5.0],
+            split_depth         : 8,
+            resolution          : [0, 0],
+            target              : String::from("target.png"),
+            evaluation          : Evaluation::Points,
+            imaginary_tolerance : 1e-9,
+            parametric_domain   : [0.0, var::TAU],
+            polar_domain        : [0.0, var::TAU],
+            threads             : 0,
+            colours             : vec![],
+            background          : [255, 255, 255, 255],
+            size                : [100.0, 100.0],
+            gcode               : GcodeSettings::new(),
+            svg                 : SvgSettings::new(),
+            hatch               : HatchSettings::new()
+        };
+    }
+    // Left must be less than right, and bottom less than top, see `check`.
+    pub fn set_frame(mut self, frame : [f64; 4]) -> RenderSettings {
+        self.frame = frame;
+        return self;
+    }
+    // At most `MAX_SPLIT_DEPTH`, see `check`.
+    pub fn set_split_depth(mut self, split_depth : u32) -> RenderSettings {
+        self.split_depth = split_depth;
+        return self;
+    }
+    // `0` on either axis uses 2 ^ split depth.
+    pub fn set_resolution(mut self, resolution : [u32; 2]) -> RenderSettings {
+        self.resolution = resolution;
+        return self;
+    }
+    pub fn set_target<S : Into<String>>(mut self, target : S) -> RenderSettings {
+        self.target = target.into();
+        return self;
+    }
+    pub fn set_evaluation(mut self, evaluation : Evaluation) -> RenderSettings {
+        self.evaluation = evaluation;
+        return self;
+    }
+    pub fn set_imaginary_tolerance(mut self, imaginary_tolerance : f64) -> RenderSettings {
+        self.imaginary_tolerance = imaginary_tolerance;
+        return self;
+    }
+    // Once around a circle by default, so roses and Lissajous figures close.
+    pub fn set_parametric_domain(mut self, parametric_domain : [f64; 2]) -> RenderSettings {
+        self.parametric_domain = parametric_domain;
+        return self;
+    }
+    // Once around by default. Relations other than `r = f(θ)` only ever
+    // see one turn, from the start, see `polar`.
+    pub fn set_polar_domain(mut self, polar_domain : [f64; 2]) -> RenderSettings {
+        self.polar_domain = polar_domain;
+        return self;
+    }
+    // `0` uses one thread per core. The output is the same for any count.
+    pub fn set_threads(mut self, threads : usize) -> RenderSettings {
+        self.threads = threads;
+        return self;
+    }
+    pub fn set_colours(mut self, colours : Vec<[u8; 4]>) -> RenderSettings {
+        self.colours = colours;
+        return self;
+    }
+    // `[0, 0, 0, 0]` for a transparent background.
+    pub fn set_background(mut self, background : [u8; 4]) -> RenderSettings {
+        self.background = background;
+        return self;
+    }
+    pub fn set_size(mut self, size : [f64; 2]) -> RenderSettings {
+        self.size = size;
+        return self;
+    }
+    pub fn set_gcode(mut self, gcode : GcodeSettings) -> RenderSettings {
+        self.gcode = gcode;
+        return self;
+    }
+    pub fn set_svg(mut self, svg : SvgSettings) -> RenderSettings {
+        self.svg = svg;
+        return self;
+    }
+    pub fn set_hatch(mut self, hatch : HatchSettings) -> RenderSettings {
+        self.hatch = hatch;
+        return self;
+    }
+
+    // Whether the frame and split depth can be drawn. Every render entry point checks first.
+    pub fn check(&self) -> Result<(), Error> {
+        if (self.split_depth > MAX_SPLIT_DEPTH) {
+            return Err(Error::SplitDepth(self.split_depth));
+        }
+        let [left, bottom, right, top] = self.frame;
+        // Written so `NaN` fails as well.
+        if (! (left < right && bottom < top) || ! self.frame.iter().all(|corner| corner.is_finite())) {
+            return Err(Error::Frame(self.frame));
+        }
+        return Ok(());
+    }
+
+    // Colour an equation is drawn in, by index in the equation list.
+    pub fn colour(&self, index : usize) -> [u8; 4] {
+        return match (self.colours.get(index)) {
+            Some(colour) => *colour,
+            None         => DEFAULT_COLOURS[index % DEFAULT_COLOURS.len()]
+        };
+    }
+}
+
+
+// Colours given to the equations in turn, as in Desmos : Red, Green, Blue, Alpha
+pub const DEFAULT_COLOURS : [[u8; 4]; 6] = [
+    [0xc7, 0x44, 0x40, 0xff], // Red
+    [0x2d, 0x70, 0xb3, 0xff], // Blue
+    [0x38, 0x8c, 0x46, 0xff], // Green
+    [0x60, 0x42, 0xa6, 0xff], // Purple
+    [0xfa, 0x7e, 0x19, 0xff], // Orange
+    [0x00, 0x00, 0x00, 0xff]  // Black
+];
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Evaluation {
+    // Sample the equations at points. Fast, but thin features can be missed.
+    Points,
+    // Evaluate over whole cells with interval arithmetic. Cells are only
+    // discarded when no curve can pass through them.
+    Intervals,
+    // Sample at points over the complex numbers, defining `i`, and draw
+    // only the values that are real. Fractional powers of negative numbers
+    // keep their real branch, so `x^(1/3)` is drawn for negative `x`.
+    Complex
+}
+
+
+#[derive(Clone)]
+pub struct GcodeSettings {
+    // Burning speed in millimetres per minute.
+    pub feed       : f64,
+    // Spindle value while burning, `S` in `M3`/`M4`. GRBL's default range is 0 to 1000.
+    pub power      : u32,
+    pub laser_mode : LaserMode
+}
+impl Default for GcodeSettings {
+    fn default() -> GcodeSettings {
+        return GcodeSettings::new();
+    }
+}
+impl GcodeSettings {
+    pub fn new() -> GcodeSettings {
+        return GcodeSettings {
+            feed       : 1000.0,
+            power      : 1000,
+            laser_mode : LaserMode::Dynamic
+        };
+    }
+    pub fn set_feed(mut self, feed : f64) -> GcodeSettings {
+        self.feed = feed;
+        return self;
+    }
+    pub fn set_power(mut self, power : u32) -> GcodeSettings {
+        self.power = power;
+        return self;
+    }
+    pub fn set_laser_mode(mut self, laser_mode : LaserMode) -> GcodeSettings {
+        self.laser_mode = laser_mode;
+        return self;
+    }
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LaserMode {
+    // `M3`, the same power at any speed.
+    Constant,
+    // `M4`, power scaled with speed, so corners are not overburnt.
+    Dynamic
+}
+
+
+#[derive(Clone)]
+pub struct SvgSettings {
+    // Line width in millimetres.
+    pub stroke_width : f64,
+    // Draw the `x` and `y` axes.
+    pub axes         : bool,
+    // Spacing of grid lines in graph units, `None` for no grid.
+    pub grid         : Option<f64>
+}
+impl Default for SvgSettings {
+    fn default() -> SvgSettings {
+        return SvgSettings::new();
+    }
+}
+impl SvgSettings {
+    pub fn new() -> SvgSettings {
+        return SvgSettings {
+            stroke_width : 0.2,
+            axes         : false,
+            grid         : None
+        };
+    }
+    pub fn set_stroke_width(mut self, stroke_width : f64) -> SvgSettings {
+        self.stroke_width = stroke_width;
+        return self;
+    }
+    pub fn set_axes(mut self, axes : bool) -> SvgSettings {
+        self.axes = axes;
+        return self;
+    }
+    // Spacings that are not positive numbers draw no grid. Spacings too
+    // small for the frame fail when the SVG is written, see `svg`.
+    pub fn set_grid(mut self, grid : Option<f64>) -> SvgSettings {
+        self.grid = grid.filter(|spacing| {
+            let valid = spacing.is_finite() && *spacing > 0.0;
+            if (! valid) {
+                log!(WARN, "Skipping grid with spacing `{}`, it is not a positive number.", spacing);
+            }
+            return valid;
+        });
+        return self;
+    }
+}
+
+
+#[derive(Clone)]
+pub struct HatchSettings {
+    // Distance between fill lines in millimetres.
+    pub spacing       : f64,
+    // Direction of the fill lines in degrees, anticlockwise from the `x` axis.
+    pub angle         : f64,
+    // Add a second pass of lines at right angles to the first.
+    pub cross         : bool,
+    // Run every other line back the other way.
+    pub bidirectional : bool,
+    // Also fill the areas enclosed by closed curves, not only regions.
+    pub closed        : bool
+}
+impl Default for HatchSettings {
+    fn default() -> HatchSettings {
+        return HatchSettings::new();
+    }
+}
+impl HatchSettings {
+    pub fn new() -> HatchSettings {
+        return HatchSettings {
+            spacing       : 0.5,
+            angle         : 0.0,
+            cross         : false,
+            bidirectional : false,
+            closed        : false
+        };
+    }
+    // Spacings that are not positive numbers keep the spacing already set.
+    // Spacings too small for the job fail when hatching, see `hatch`.
+    pub fn set_spacing(mut self, spacing : f64) -> HatchSettings {
+        if (! (spacing.is_finite() && spacing > 0.0)) {
+            log!(WARN, "Keeping hatch spacing `{}`, `{}` is not a positive number.", self.spacing, spacing);
+            return self;
+        }
+        self.spacing = spacing;
+        return self;
+    }
+    pub fn set_angle(mut self, angle : f64) -> HatchSettings {
+        self.angle = angle;
+        return self;
+    }
+    pub fn set_cross(mut self, cross : bool) -> HatchSettings {
+        self.cross = cross;
+        return self;
+    }
+    pub fn set_bidirectional(mut self, bidirectional : bool) -> HatchSettings {
+        self.bidirectional = bidirectional;
+        return self;
+    }
+    pub fn set_closed(mut self, closed : bool) -> HatchSettings {
+        self.closed = closed;
+        return self;
+    }
 }
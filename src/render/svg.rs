@@ -0,0 +1,210 @@
+// SVG documents with one layer per equation.
+//
+//     <svg width="100mm" height="100mm" viewBox="0 0 100 100" ...>
+//       <g id="equation-1" inkscape:groupmode="layer" inkscape:label="(y = sin(x))" stroke="#c74440" ...>
+//         <polyline points="0.195,41.602 12.305,66.797 ..."/>
+//       </g>
+//     </svg>
+//
+// Units are millimetres, so the document is drawn at the job size. Laser
+// software such as LightBurn picks cut settings by stroke colour, and
+// Inkscape shows each `<g>` as a layer. Regions get their fill lines in a
+// `<g>` of their own inside the layer.
+
+use loggerithm::{logger, log};
+use loggerithm::level::DEBUG;
+logger!(super);
+
+use crate::error::Error;
+use crate::helper;
+use crate::render::settings::RenderSettings;
+use super::path::DASH;
+
+
+// Most grid lines drawn across the frame on each axis.
+pub const MAX_GRID_LINES : usize = 1000;
+
+
+// Paths of one equation.
+pub struct Layer {
+    // Index in the equation list.
+    pub index    : usize,
+    pub label    : String,
+    // Red, Green, Blue, Alpha
+    pub colour   : [u8; 4],
+    // Lists of points in graph space.
+    pub paths    : Vec<Vec<[f64; 2]>>,
+    // Draw the paths dashed, for borders that are not part of their region.
+    pub dashed   : bool,
+    // Fill lines of a region, in graph space.
+    pub hatching : Vec<Vec<[f64; 2]>>
+}
+
+
+pub fn write(layers : &[Layer], settings : &RenderSettings) -> Result<String, Error> {
+    let size = settings.size;
+    log!(DEBUG,
+        "Writing {} layer{} as SVG for a {}x{}mm job.",
+        helper::commaify_i64(layers.len() as i64),
+        if (layers.len() == 1) {""} else {"s"},
+        size[0], size[1]
+    );
+
+    let mut lines = vec![
+        String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>"),
+        format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" xmlns:inkscape=\"http://www.inkscape.org/namespaces/inkscape\" width=\"{}mm\" height=\"{}mm\" viewBox=\"0 0 {} {}\">",
+            size[0], size[1], size[0], size[1]
+        )
+    ];
+    if (settings.svg.axes || settings.svg.grid.is_some()) {
+        lines.append(&mut write_axes(settings)?);
+    }
+    for layer in layers {
+        lines.push(format!(
+            "  <g id=\"equation-{}\" inkscape:groupmode=\"layer\" inkscape:label=\"{}\" stroke=\"{}\" stroke-opacity=\"{}\" stroke-width=\"{}\" stroke-linecap=\"round\" stroke-linejoin=\"round\" fill=\"none\"{}>",
+            layer.index + 1, escape(&layer.label), hex_colour(layer.colour), opacity(layer.colour), settings.svg.stroke_width,
+            if (layer.dashed) {format!(" stroke-dasharray=\"{} {}\"", DASH[0], DASH[1])} else {String::new()}
+        ));
+        for path in &layer.paths {
+            if let Some(line) = write_path(path, settings) {
+                lines.push(format!("    {}", line));
+            }
+        }
+        if (! layer.hatching.is_empty()) {
+            lines.push(format!("    <g id=\"equation-{}-hatching\" stroke-dasharray=\"none\">", layer.index + 1));
+            for path in &layer.hatching {
+                if let Some(line) = write_path(path, settings) {
+                    lines.push(format!("      {}", line));
+                }
+            }
+            lines.push(String::from("    </g>"));
+        }
+        lines.push(String::from("  </g>"));
+    }
+    lines.push(String::from("</svg>"));
+    return Ok(lines.join("\n") + "\n");
+}
+
+
+fn write_path(path : &[[f64; 2]], settings : &RenderSettings) -> Option<String> {
+    if (path.is_empty()) {
+        return None;
+    }
+    let mut points = path.iter()
+        .map(|point| to_millimetres(*point, settings))
+        .collect::<Vec<[f64; 2]>>();
+    // A lone point is drawn as a dot.
+    if (points.len() == 1) {
+        points.push(points[0]);
+    }
+    return Some(format!(
+        "<polyline points=\"{}\"/>",
+        points.iter().map(|point| format!("{:.3},{:.3}", point[0], point[1])).collect::<Vec<String>>().join(" ")
+    ));
+}
+
+// Grid lines and axes, in their own layer under the equations.
+fn write_axes(settings : &RenderSettings) -> Result<Vec<String>, Error> {
+    let frame = settings.frame;
+    let mut lines = vec![String::from(
+        "  <g id=\"axes\" inkscape:groupmode=\"layer\" inkscape:label=\"Axes\" stroke=\"#999999\" fill=\"none\">"
+    )];
+    if let Some(spacing) = settings.svg.grid {
+        for x in grid_lines(spacing, frame[0], frame[2])? {
+            lines.push(write_line([x, frame[1]], [x, frame[3]], settings.svg.stroke_width / 4.0, settings));
+        }
+        for y in grid_lines(spacing, frame[1], frame[3])? {
+            lines.push(write_line([frame[0], y], [frame[2], y], settings.svg.stroke_width / 4.0, settings));
+        }
+    }
+    if (settings.svg.axes) {
+        if (frame[0] <= 0.0 && 0.0 <= frame[2]) {
+            lines.push(write_line([0.0, frame[1]], [0.0, frame[3]], settings.svg.stroke_width / 2.0, settings));
+        }
+        if (frame[1] <= 0.0 && 0.0 <= frame[3]) {
+            lines.push(write_line([frame[0], 0.0], [frame[2], 0.0], settings.svg.stroke_width / 2.0, settings));
+        }
+    }
+    lines.push(String::from("  </g>"));
+    return Ok(lines);
+}
+
+// Multiples of `spacing` from `low` to `high`.
+fn grid_lines(spacing : f64, low : f64, high : f64) -> Result<Vec<f64>, Error> {
+    if (! (spacing.is_finite() && spacing > 0.0)) {
+        return Err(Error::GridSpacing(spacing));
+    }
+    let first = (low / spacing).ceil();
+    let last  = (high / spacing).floor();
+    if (last - first >= MAX_GRID_LINES as f64) {
+        return Err(Error::GridSpacing(spacing));
+    }
+    return Ok((first as i64..=last as i64).map(|i| i as f64 * spacing).collect());
+}
+
+fn write_line(start : [f64; 2], end : [f64; 2], width : f64, settings : &RenderSettings) -> String {
+    let start = to_millimetres(start, settings);
+    let end   = to_millimetres(end, settings);
+    return format!(
+        "    <line x1=\"{:.3}\" y1=\"{:.3}\" x2=\"{:.3}\" y2=\"{:.3}\" stroke-width=\"{}\"/>",
+        start[0], start[1], end[0], end[1], width
+    );
+}
+
+// Graph space to document space. SVG's `y` points down.
+fn to_millimetres(point : [f64; 2], settings : &RenderSettings) -> [f64; 2] {
+    return [
+        (point[0] - settings.frame[0]) / (settings.frame[2] - settings.frame[0]) * settings.size[0],
+        (settings.frame[3] - point[1]) / (settings.frame[3] - settings.frame[1]) * settings.size[1]
+    ];
+}
+
+fn hex_colour(colour : [u8; 4]) -> String {
+    return format!("#{:02x}{:02x}{:02x}", colour[0], colour[1], colour[2]);
+}
+
+fn opacity(colour : [u8; 4]) -> String {
+    return format!("{:.3}", colour[3] as f64 / 255.0).trim_end_matches('0').trim_end_matches('.').to_string();
+}
+
+fn escape(text : &str) -> String {
+    return text
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;");
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::settings::SvgSettings;
+
+    #[test]
+    fn grid_lines_are_multiples_of_the_spacing() {
+        assert_eq!(grid_lines(2.0, -5.0, 5.0).unwrap(), [-4.0, -2.0, 0.0, 2.0, 4.0]);
+        assert_eq!(grid_lines(0.5, 0.2, 1.0).unwrap(), [0.5, 1.0]);
+    }
+
+    #[test]
+    fn grid_spacing_must_be_positive() {
+        for spacing in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            assert!(matches!(grid_lines(spacing, -5.0, 5.0), Err(Error::GridSpacing(_))));
+        }
+    }
+
+    #[test]
+    fn grid_lines_are_capped() {
+        assert_eq!(grid_lines(10.0 / (MAX_GRID_LINES - 1) as f64, 0.0, 10.0).unwrap().len(), MAX_GRID_LINES);
+        assert!(matches!(grid_lines(1e-6, -5.0, 5.0), Err(Error::GridSpacing(_))));
+    }
+
+    #[test]
+    fn invalid_grid_settings_draw_no_grid() {
+        assert_eq!(SvgSettings::new().set_grid(Some(0.0)).grid, None);
+        assert_eq!(SvgSettings::new().set_grid(Some(f64::NAN)).grid, None);
+        assert_eq!(SvgSettings::new().set_grid(Some(0.5)).grid, Some(0.5));
+    }
+}
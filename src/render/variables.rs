@@ -0,0 +1,210 @@
+use std::collections::HashMap;
+
+use crate::error::Error;
+use crate::parse::node::Node;
+use crate::parse::values::EvaluatedValues;
+use crate::parse::complex::{Complex, EvaluatedComplex};
+use crate::parse::bytecode::{Compiler, Program};
+use crate::render::settings::{RenderSettings, Evaluation};
+use super::constants;
+use super::dependency::Dependencies;
+
+
+// Variables for evaluating equations at a point. With
+// `Evaluation::Complex` they hold complex numbers, including the constant
+// `i`, and only values with a nearly zero imaginary part are given back.
+#[derive(Clone)]
+pub enum PointVariables {
+    Real    (HashMap<String, EvaluatedValues>),
+    Complex (HashMap<String, EvaluatedComplex>, f64) // Variables, Imaginary tolerance
+}
+impl PointVariables {
+    // The constants.
+    pub fn new(settings : &RenderSettings) -> PointVariables {
+        let mut point_variables = match (settings.evaluation) {
+            Evaluation::Complex => {
+                let mut variables = HashMap::new();
+                variables.insert(String::from("i"), EvaluatedComplex::from(vec![Complex::i()]));
+                PointVariables::Complex(variables, settings.imaginary_tolerance)
+            },
+            _ => PointVariables::Real(HashMap::new())
+        };
+        for (name, value) in constants() {
+            point_variables.set(name, value);
+        }
+        return point_variables;
+    }
+
+    // The constants, and definitions evaluated once to be copied for each point.
+    pub fn with_definitions(settings : &RenderSettings, definitions : &[&Node]) -> PointVariables {
+        let mut variables = PointVariables::new(settings);
+        for definition in definitions {
+            // Definitions are checked up front, see `dependency`.
+            let _ = variables.evaluate(definition);
+        }
+        return variables;
+    }
+
+    pub fn set(&mut self, name : &str, value : f64) {
+        match (self) {
+            PointVariables::Real    (variables)    => {variables.insert(String::from(name), EvaluatedValues::from(vec![value]));},
+            PointVariables::Complex (variables, _) => {variables.insert(String::from(name), EvaluatedComplex::real(value));}
+        };
+    }
+
+    // Evaluate a node, binding its variable if it is a definition.
+    pub fn evaluate(&mut self, node : &Node) -> Result<EvaluatedValues, Error> {
+        return Ok(match (self) {
+            PointVariables::Real    (variables)            => node.evaluate(&String::from("y"), variables)?,
+            PointVariables::Complex (variables, tolerance) => {
+                EvaluatedValues::from(node.evaluate_complex(&String::from("y"), variables)?.real_values(*tolerance))
+            }
+        });
+    }
+
+    // `left - right`.
+    pub fn difference(&mut self, left : &Node, right : &Node) -> Result<EvaluatedValues, Error> {
+        return Ok(match (self) {
+            PointVariables::Real (variables) => {
+                let left  = left.evaluate(&String::from("y"), variables)?;
+                let right = right.evaluate(&String::from("y"), variables)?;
+                left.subtraction(&right)
+            },
+            PointVariables::Complex (variables, tolerance) => {
+                let left  = left.evaluate_complex(&String::from("y"), variables)?;
+                let right = right.evaluate_complex(&String::from("y"), variables)?;
+                EvaluatedValues::from(left.subtraction(&right).real_values(*tolerance))
+            }
+        });
+    }
+}
+
+
+// What is drawn of an equation.
+#[derive(Clone, Copy)]
+pub enum Quantity<'l> {
+    // The value of an expression, such as the right side of `y = f(x)`.
+    Value      (&'l Node),
+    // `left - right` of a relation.
+    Difference (&'l Node, &'l Node)
+}
+
+// Equations evaluated at many points, such as every column or grid point.
+// Real values are compiled once, see `parse::bytecode`. Complex values
+// walk the trees.
+pub struct PointEquations<'l> {
+    evaluator : Evaluator<'l>,
+    // Names of the variables given at each point, such as `x` and `y`.
+    inputs    : Vec<&'static str>,
+    // Index in the equation list of each equation evaluated, in order.
+    indices   : Vec<usize>
+}
+enum Evaluator<'l> {
+    Compiled (Program),
+    Tree     (PointVariables, Vec<&'l Node>, Vec<Quantity<'l>>) // Constants, Definitions that read the inputs, Quantities
+}
+impl<'l> PointEquations<'l> {
+    // Equations that fail to evaluate are left out, and given back with their index in the equation list.
+    pub fn new(settings : &RenderSettings, nodes : &'l [Node], dependencies : &Dependencies, inputs : &[&'static str], quantities : Vec<(usize, Quantity<'l>)>) -> (PointEquations<'l>, Vec<(usize, Error)>) {
+        let mut indices  = vec![];
+        let mut failures = vec![];
+        let evaluator = match (settings.evaluation) {
+            Evaluation::Complex => {
+                let constants   = PointVariables::with_definitions(settings, &dependencies.constants(nodes));
+                let definitions = dependencies.varying(nodes);
+                // Failures do not depend on the inputs, so the first point finds them all.
+                let mut variables = constants.clone();
+                set_inputs(&mut variables, inputs, &vec![0.0; inputs.len()]);
+                for definition in &definitions {
+                    let _ = variables.evaluate(definition);
+                }
+                let mut kept = vec![];
+                for (index, quantity) in quantities {
+                    match (evaluate_quantity(&mut variables, quantity)) {
+                        Ok(_)      => {
+                            indices.push(index);
+                            kept.push(quantity);
+                        },
+                        Err(error) => failures.push((index, error))
+                    };
+                }
+                Evaluator::Tree(constants, definitions, kept)
+            },
+            Evaluation::Points | Evaluation::Intervals => {
+                let mut compiler = Compiler::new();
+                for (name, value) in constants() {
+                    compiler.set_constant(name, &[value]);
+                }
+                for name in inputs {
+                    compiler.set_input(name);
+                }
+                // Definitions are checked up front, see `dependency`. Those
+                // reading a variable that is not an input are left unbound.
+                for definition in dependencies.constants(nodes).into_iter().chain(dependencies.varying(nodes)) {
+                    let _ = compiler.compile(definition);
+                }
+                let mut outputs = vec![];
+                for (index, quantity) in quantities {
+                    let register = match (quantity) {
+                        Quantity::Value      (node)        => compiler.compile(node),
+                        Quantity::Difference (left, right) => compiler.difference(left, right)
+                    };
+                    match (register) {
+                        Ok(register) => {
+                            indices.push(index);
+                            outputs.push(register);
+                        },
+                        Err(error) => failures.push((index, error))
+                    };
+                }
+                Evaluator::Compiled(compiler.finish(outputs))
+            }
+        };
+        return (PointEquations {evaluator, inputs: inputs.to_vec(), indices}, failures);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.indices.is_empty();
+    }
+
+    // Index in the equation list of each equation evaluated, in the order of `evaluate`.
+    pub fn indices(&self) -> &Vec<usize> {
+        return &self.indices;
+    }
+
+    // Values of each equation, with a value for each input.
+    pub fn evaluate(&self, point : &[f64]) -> Vec<EvaluatedValues> {
+        return match (&self.evaluator) {
+            Evaluator::Compiled(program) => {
+                let mut machine = program.machine();
+                program.run(&mut machine, point);
+                (0..program.outputs()).map(|i| EvaluatedValues::from(program.output(&machine, i).to_vec())).collect()
+            },
+            Evaluator::Tree(constants, definitions, quantities) => {
+                let mut variables = constants.clone();
+                set_inputs(&mut variables, &self.inputs, point);
+                for definition in definitions {
+                    // Definitions are checked up front, see `dependency`.
+                    let _ = variables.evaluate(definition);
+                }
+                quantities.iter()
+                    // Failing equations are already left out.
+                    .map(|quantity| evaluate_quantity(&mut variables, *quantity).unwrap_or_else(|_| EvaluatedValues::new()))
+                    .collect()
+            }
+        };
+    }
+}
+
+fn set_inputs(variables : &mut PointVariables, inputs : &[&str], point : &[f64]) {
+    for (name, value) in inputs.iter().zip(point) {
+        variables.set(name, *value);
+    }
+}
+
+fn evaluate_quantity(variables : &mut PointVariables, quantity : Quantity) -> Result<EvaluatedValues, Error> {
+    return match (quantity) {
+        Quantity::Value      (node)        => variables.evaluate(node),
+        Quantity::Difference (left, right) => variables.difference(left, right)
+    };
+}